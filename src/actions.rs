@@ -0,0 +1,91 @@
+//! Outputs for GitHub Actions, so that workflows can use the results of a run without parsing
+//! its output. Only written when running in Actions.
+
+use crate::{
+    failure::{self, ErrorFormat, Failure, FailureKind},
+    gzip,
+    job::Job,
+    log::Log,
+};
+use std::{
+    env,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Sets the outputs `new_warnings`, if the run had a baseline, `ices` and `report_path`, the
+/// reports of the jobs separated by spaces, in `GITHUB_OUTPUT` and adds a summary of the run to
+/// `GITHUB_STEP_SUMMARY`.
+pub fn write(
+    jobs: &[Job],
+    new_warnings: Option<usize>,
+    failures: &[Failure],
+) -> Result<(), String> {
+    if env::var_os("GITHUB_ACTIONS").is_none_or(|actions| actions != "true") {
+        return Ok(());
+    }
+    let ices = failures
+        .iter()
+        .filter(|failure| failure.kind == FailureKind::Ice)
+        .count();
+    let reports: Vec<String> = jobs
+        .iter()
+        .map(|job| format!("logs/{}_report.md", job.name))
+        .filter(|report| Path::new(report).exists())
+        .collect();
+    if let Some(path) = env::var_os("GITHUB_OUTPUT") {
+        let mut outputs = String::new();
+        if let Some(new_warnings) = new_warnings {
+            outputs.push_str(&format!("new_warnings={}\n", new_warnings));
+        }
+        outputs.push_str(&format!("ices={}\n", ices));
+        outputs.push_str(&format!("report_path={}\n", reports.join(" ")));
+        append(&PathBuf::from(path), &outputs)?;
+    }
+    if let Some(path) = env::var_os("GITHUB_STEP_SUMMARY") {
+        append(&PathBuf::from(path), &summary(jobs, new_warnings, failures))?;
+    }
+    Ok(())
+}
+
+fn summary(jobs: &[Job], new_warnings: Option<usize>, failures: &[Failure]) -> String {
+    let mut summary = String::from("## clippy-lintcheck\n\n| config | warnings | lints | ICEs |\n");
+    summary.push_str("| --- | --- | --- | --- |\n");
+    for job in jobs {
+        match Log::load(gzip::find(job.log_path())) {
+            Ok(log) => summary.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                job.name,
+                log.warnings.len(),
+                log.lint_counts().len(),
+                log.ices.len()
+            )),
+            Err(_) => summary.push_str(&format!("| {} | no log | | |\n", job.name)),
+        }
+    }
+    if let Some(new_warnings) = new_warnings {
+        summary.push_str(&format!(
+            "\n{} new warning(s) compared to the baseline\n",
+            new_warnings
+        ));
+    }
+    if failures.is_empty() {
+        summary.push_str("\nNo failures\n");
+    } else {
+        summary.push_str(&format!(
+            "\n```text\n{}```\n",
+            failure::render(failures, ErrorFormat::Human)
+        ));
+    }
+    summary
+}
+
+fn append(path: &Path, text: &str) -> Result<(), String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+}
@@ -0,0 +1,67 @@
+//! Status badge of the latest run in the [shields.io endpoint format](https://shields.io/endpoint),
+//! so that READMEs can show the ecosystem impact of a clippy branch.
+
+use crate::{
+    failure::{Failure, FailureKind},
+    publish,
+};
+use serde::Serialize;
+use std::path::Path;
+
+pub const BADGE_PATH: &str = "logs/badge.json";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Badge {
+    schema_version: u8,
+    label: &'static str,
+    message: String,
+    color: &'static str,
+}
+
+impl Badge {
+    /// The badge of a run that ended with `failures`. `new_warnings` is the number of warnings
+    /// not in the baseline, if the run had one.
+    pub fn new(new_warnings: Option<usize>, failures: &[Failure]) -> Self {
+        let count = |kind| {
+            failures
+                .iter()
+                .filter(|failure| failure.kind == kind)
+                .count()
+        };
+        let (regressions, ices) = (count(FailureKind::Regression), count(FailureKind::Ice));
+        let mut parts = Vec::new();
+        if let Some(new_warnings) = new_warnings {
+            parts.push(format!("{} new warnings", new_warnings));
+        }
+        parts.push(format!("{} regressions", regressions));
+        if ices > 0 {
+            parts.push(format!("{} ICEs", ices));
+        }
+        let (message, color) = if regressions > 0 || ices > 0 {
+            (parts.join(" / "), "red")
+        } else if !failures.is_empty() {
+            ("run failed".to_string(), "lightgrey")
+        } else if new_warnings.unwrap_or_default() > 0 {
+            (parts.join(" / "), "yellow")
+        } else {
+            (parts.join(" / "), "brightgreen")
+        };
+        Self {
+            schema_version: 1,
+            label: "lintcheck",
+            message,
+            color,
+        }
+    }
+
+    /// Writes the badge to `logs/badge.json` and to `copy`, if given.
+    pub fn write(&self, copy: Option<&Path>) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).expect("badge is serializable") + "\n";
+        for path in std::iter::once(Path::new(BADGE_PATH)).chain(copy) {
+            publish::write(path, &json)
+                .map_err(|e| format!("couldn't write {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+//! `cargo lintcheck-runner`, clippy-lintcheck as a cargo subcommand. `cargo install` installs it
+//! next to `clippy-lintcheck`, which it runs with the same arguments.
+
+use std::{
+    env,
+    process::{self, Command},
+};
+
+/// Exit code of clippy-lintcheck for problems with the environment.
+const ENVIRONMENT_EXIT_CODE: i32 = 4;
+
+fn main() {
+    let mut args = env::args_os().skip(1).peekable();
+    // Cargo passes the name of the subcommand as the first argument.
+    if args.peek().is_some_and(|arg| arg == "lintcheck-runner") {
+        args.next();
+    }
+    let binary = format!("clippy-lintcheck{}", env::consts::EXE_SUFFIX);
+    let status = env::current_exe().and_then(|exe| {
+        Command::new(exe.with_file_name(&binary))
+            .args(args)
+            .status()
+    });
+    match status {
+        Ok(status) => process::exit(status.code().unwrap_or(ENVIRONMENT_EXIT_CODE)),
+        Err(e) => {
+            eprintln!("couldn't execute {}: {}", binary, e);
+            process::exit(ENVIRONMENT_EXIT_CODE);
+        }
+    }
+}
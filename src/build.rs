@@ -0,0 +1,57 @@
+//! Building clippy once before the jobs of a run. Lintcheck builds clippy itself, which is a
+//! no-op once it is up to date, so every config after the first reuses the built driver.
+
+use crate::wrapper_config::Checkout;
+use std::{fs, path::Path, process::Command};
+
+/// The binaries lintcheck runs the crates through.
+const BINARIES: &[&str] = &["cargo-clippy", "clippy-driver"];
+
+/// Builds `cargo-clippy` and `clippy-driver` in the checkout.
+pub fn build(clippy: &Checkout) -> Result<(), String> {
+    println!("building clippy in {}", clippy.path.display());
+    let mut command = Command::new("cargo");
+    if let Some(toolchain) = &clippy.toolchain {
+        command.arg(format!("+{}", toolchain));
+    }
+    if let Some(target_dir) = &clippy.target_dir {
+        command.env("CARGO_TARGET_DIR", target_dir);
+    }
+    command.arg("build");
+    for binary in BINARIES {
+        command.args(["--bin", binary]);
+    }
+    let status = command
+        .current_dir(&clippy.path)
+        .status()
+        .map_err(|e| format!("couldn't execute cargo: {}", e))?;
+    if !status.success() {
+        return Err(format!("building clippy failed with {}", status));
+    }
+    Ok(())
+}
+
+/// Copies the clippy build of the checkout to `target_dir`, so that lintcheck runs with their
+/// own target dir don't rebuild clippy. `cp -a` keeps the modification times, so cargo considers
+/// the copy up to date.
+pub fn seed(clippy: &Checkout, target_dir: &Path) -> Result<(), String> {
+    let built = clippy.target_dir().join("debug");
+    let seeded = target_dir.join("debug");
+    fs::create_dir_all(&seeded)
+        .map_err(|e| format!("couldn't create {}: {}", seeded.display(), e))?;
+    let status = Command::new("cp")
+        .args(["-a", "--reflink=auto"])
+        .arg(built.join("."))
+        .arg(&seeded)
+        .status()
+        .map_err(|e| format!("couldn't execute cp: {}", e))?;
+    if !status.success() {
+        return Err(format!(
+            "copying {} to {} failed with {}",
+            built.display(),
+            seeded.display(),
+            status
+        ));
+    }
+    Ok(())
+}
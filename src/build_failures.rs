@@ -0,0 +1,106 @@
+//! Report of the corpus crates that don't compile, so that the corpus can be maintained based on
+//! data instead of guesswork.
+
+use crate::{clippy::Clippy, crates::Krate, wrapper_config::Checkout};
+use std::fmt;
+
+/// Number of compiler errors shown per crate.
+const MAX_ERRORS: usize = 3;
+
+/// Why a crate doesn't compile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cause {
+    /// The crate compiles with the reference toolchain, but not with the toolchain of the checkout
+    ToolchainBump,
+    /// The crate doesn't compile with either toolchain
+    Crate,
+}
+
+impl fmt::Display for Cause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ToolchainBump => "toolchain bump",
+            Self::Crate => "crate",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct BuildFailure {
+    pub krate: Krate,
+    /// Rendered compiler errors with the toolchain of the checkout
+    pub errors: Vec<String>,
+    pub cause: Cause,
+}
+
+/// Builds `krates` with the clippy of the checkout to collect their compiler errors. Crates that
+/// don't compile are built with the clippy of `toolchain` as well, to attribute the errors to
+/// either the toolchain of the checkout or the crate itself.
+pub fn investigate(
+    clippy: &Checkout,
+    krates: &[Krate],
+    toolchain: &str,
+) -> Result<Vec<BuildFailure>, String> {
+    let target_dir = clippy.path.join("target/lintcheck/build_failures");
+    let mut failures = Vec::new();
+    for krate in krates {
+        let source_dir = clippy.path.join(&krate.source_dir);
+        if !source_dir.exists() {
+            continue;
+        }
+        println!(
+            "Building {} {} to find its errors...",
+            krate.name, krate.version
+        );
+        let errors = Clippy::Checkout(clippy)
+            .lint(krate, &source_dir, &target_dir.join("checkout"))?
+            .errors;
+        if errors.is_empty() {
+            continue;
+        }
+        let reference =
+            Clippy::Toolchain(toolchain).lint(krate, &source_dir, &target_dir.join(toolchain))?;
+        failures.push(BuildFailure {
+            krate: krate.clone(),
+            errors,
+            cause: if reference.errors.is_empty() {
+                Cause::ToolchainBump
+            } else {
+                Cause::Crate
+            },
+        });
+    }
+    Ok(failures)
+}
+
+/// Renders the build failures of `job` as markdown.
+pub fn render(job: &str, failures: &[BuildFailure], toolchain: &str) -> String {
+    let mut out = format!("# Build failures in `{}`\n\n", job);
+    if failures.is_empty() {
+        out.push_str("All crates compiled.\n");
+        return out;
+    }
+    for failure in failures {
+        out.push_str(&format!(
+            "## {} {}\n\nCause: {} ({} with the `{}` toolchain)\n\n",
+            failure.krate.name,
+            failure.krate.version,
+            failure.cause,
+            match failure.cause {
+                Cause::ToolchainBump => "compiles",
+                Cause::Crate => "fails as well",
+            },
+            toolchain
+        ));
+        for error in failure.errors.iter().take(MAX_ERRORS) {
+            out.push_str(&format!("```text\n{}\n```\n\n", error.trim_end()));
+        }
+        if failure.errors.len() > MAX_ERRORS {
+            out.push_str(&format!(
+                "and {} more error(s)\n\n",
+                failure.errors.len() - MAX_ERRORS
+            ));
+        }
+    }
+    out
+}
@@ -0,0 +1,139 @@
+//! Line charts of the run history, written as plain SVG so that no plotting tools are needed.
+
+use crate::history::Run;
+use std::{collections::BTreeSet, fmt::Write};
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 300.0;
+/// Space left of the plot for the y axis labels, and right of it for the legend.
+const LEFT: f64 = 50.0;
+const RIGHT: f64 = 160.0;
+/// Space above the plot for the title, and below it for the x axis labels.
+const TOP: f64 = 30.0;
+const BOTTOM: f64 = 30.0;
+/// Colors of the series, reused when there are more series than colors.
+const COLORS: &[&str] = &[
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+    "#bcbd22", "#17becf",
+];
+
+/// Warnings per lint group over `runs`, one line per group.
+pub fn groups(title: &str, runs: &[Run]) -> String {
+    let groups: BTreeSet<_> = runs.iter().flat_map(|run| run.groups.keys()).collect();
+    let series: Vec<_> = groups
+        .into_iter()
+        .map(|group| {
+            let counts = runs
+                .iter()
+                .map(|run| run.groups.get(group).copied().unwrap_or_default())
+                .collect();
+            (group.as_str(), counts)
+        })
+        .collect();
+    render(title, &series)
+}
+
+/// ICEs over `runs`.
+pub fn ices(title: &str, runs: &[Run]) -> String {
+    render(
+        title,
+        &[("ICEs", runs.iter().map(|run| run.ices).collect())],
+    )
+}
+
+/// Renders `series` of counts, which all have one count per run, as a line chart.
+fn render(title: &str, series: &[(&str, Vec<usize>)]) -> String {
+    let runs = series
+        .iter()
+        .map(|(_, counts)| counts.len())
+        .max()
+        .unwrap_or(0);
+    let max = series
+        .iter()
+        .flat_map(|(_, counts)| counts)
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let plot_width = WIDTH - LEFT - RIGHT;
+    let plot_height = HEIGHT - TOP - BOTTOM;
+    let x = |run: usize| LEFT + plot_width * run as f64 / runs.saturating_sub(1).max(1) as f64;
+    let y = |count: usize| TOP + plot_height * (1.0 - count as f64 / max as f64);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" font-size=\"12\">\n\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"white\"/>\n\
+         <text x=\"{cx}\" y=\"20\" text-anchor=\"middle\" font-size=\"14\">{title}</text>\n",
+        w = WIDTH,
+        h = HEIGHT,
+        cx = WIDTH / 2.0,
+        title = escape(title),
+    );
+    // Axes with the maximum and zero on the y axis and the first and last run on the x axis.
+    let _ = writeln!(
+        svg,
+        "<polyline points=\"{l},{t} {l},{b} {r},{b}\" fill=\"none\" stroke=\"black\"/>",
+        l = LEFT,
+        t = TOP,
+        b = TOP + plot_height,
+        r = LEFT + plot_width,
+    );
+    for (count, anchor_y) in [(max, y(max)), (0, y(0))] {
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+            LEFT - 5.0,
+            anchor_y + 4.0,
+            count
+        );
+    }
+    if runs > 0 {
+        let _ = writeln!(
+            svg,
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"start\">run 1</text>\n\
+             <text x=\"{}\" y=\"{}\" text-anchor=\"end\">run {}</text>",
+            LEFT,
+            HEIGHT - 10.0,
+            LEFT + plot_width,
+            HEIGHT - 10.0,
+            runs
+        );
+    }
+
+    for (i, (name, counts)) in series.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let points: Vec<_> = counts
+            .iter()
+            .enumerate()
+            .map(|(run, &count)| format!("{:.1},{:.1}", x(run), y(count)))
+            .collect();
+        let _ = writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>",
+            points.join(" "),
+            color
+        );
+        let legend_y = TOP + 16.0 * i as f64;
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{}\" y=\"{}\" width=\"10\" height=\"10\" fill=\"{}\"/>\n\
+             <text x=\"{}\" y=\"{}\">{} {}</text>",
+            WIDTH - RIGHT + 15.0,
+            legend_y,
+            color,
+            WIDTH - RIGHT + 30.0,
+            legend_y + 9.0,
+            escape(name),
+            counts.last().copied().unwrap_or_default()
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
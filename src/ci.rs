@@ -0,0 +1,162 @@
+//! Support for the `ci` mode, which only checks the crates added to the configs by the change
+//! under test.
+
+use crate::{crates::CratesConfig, lints, wrapper_config::Checkout};
+use std::{
+    env,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// Remote that branch names taken from CI environment variables are resolved against.
+const REMOTE: &str = "origin";
+/// Base used when neither `--base-ref` nor a CI environment variable is set.
+const DEFAULT_BASE_REF: &str = "origin/main";
+/// Environment variables of CI providers that hold the target branch of a pull/merge request.
+const BASE_BRANCH_VARS: &[&str] = &["GITHUB_BASE_REF", "CI_MERGE_REQUEST_TARGET_BRANCH_NAME"];
+/// Crates that are added to the integration config whenever the clippy checkout adds a lint.
+pub const NEW_LINTS_CONFIG: &str = "config/new_lints.toml";
+
+/// Determines the ref to diff the configs against and makes sure it is available locally.
+///
+/// An explicit `base_ref` takes precedence over the CI environment variables.
+pub fn resolve_base_ref(base_ref: Option<&str>) -> Result<String, String> {
+    let base_ref = base_ref.map(str::to_string).unwrap_or_else(|| {
+        BASE_BRANCH_VARS
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|branch| !branch.is_empty())
+            .map_or_else(
+                || DEFAULT_BASE_REF.to_string(),
+                |branch| format!("{}/{}", REMOTE, branch),
+            )
+    });
+    if !ref_exists(&base_ref)? {
+        fetch(&base_ref)?;
+    }
+    Ok(base_ref)
+}
+
+fn ref_exists(git_ref: &str) -> Result<bool, String> {
+    let status = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{}^{{commit}}", git_ref))
+        .stdout(Stdio::null())
+        .status()
+        .map_err(|e| format!("couldn't execute git rev-parse: {}", e))?;
+    Ok(status.success())
+}
+
+/// Fetches a missing `remote/branch` ref, e.g. in shallow clones of CI checkouts.
+fn fetch(git_ref: &str) -> Result<(), String> {
+    let (remote, branch) = git_ref
+        .split_once('/')
+        .ok_or_else(|| format!("base ref {} doesn't exist and can't be fetched", git_ref))?;
+    println!("fetching missing base ref {}", git_ref);
+    let status = Command::new("git")
+        .args(["fetch", "--depth=1", remote])
+        .arg(format!(
+            "+refs/heads/{0}:refs/remotes/{1}/{0}",
+            branch, remote
+        ))
+        .status()
+        .map_err(|e| format!("couldn't execute git fetch: {}", e))?;
+    if !status.success() {
+        return Err(format!(
+            "fetching base ref {} failed with {}",
+            git_ref, status
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the names of the lints declared in the clippy checkout, but not at
+/// `clippy_base_ref`.
+pub fn new_lints(clippy: &Checkout, clippy_base_ref: &str) -> Result<Vec<String>, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(&clippy.path)
+        .args(["diff", clippy_base_ref, "--", "clippy_lints/src"])
+        .output()
+        .map_err(|e| format!("couldn't execute git diff in the clippy checkout: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git diff against {} in the clippy checkout exited with {}",
+            clippy_base_ref, output.status
+        ));
+    }
+    // New declarations only consist of added lines, so removing everything else leaves them
+    // intact.
+    let added: String = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.starts_with("+++"))
+        .filter_map(|line| line.strip_prefix('+'))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    Ok(lints::parse_declarations(&added)
+        .into_iter()
+        .map(|declaration| declaration.name)
+        .collect())
+}
+
+/// Returns a config containing the crates that were added to `config/<name>.toml` since
+/// `base_ref` and the crates of `extra`.
+pub fn added_crates(
+    name: &str,
+    base_ref: &str,
+    extra: &CratesConfig,
+) -> Result<CratesConfig, String> {
+    let mut diff = Command::new("git")
+        .arg("diff")
+        .args([base_ref, "--", &format!("config/{}.toml", name)])
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("couldn't execute git diff: {}", e))?;
+    let grep = Command::new("grep")
+        .args(["-E", r"^\+\w+"])
+        .stdin(
+            diff.stdout
+                .take()
+                .ok_or("failed to process git diff output")?,
+        )
+        .output()
+        .map_err(|e| format!("couldn't execute grep: {}", e))?;
+    let status = diff
+        .wait()
+        .map_err(|e| format!("couldn't wait for git diff: {}", e))?;
+    if !status.success() {
+        return Err(format!("git diff exited with {}", status));
+    }
+    let mut added = String::from("[crates]\n");
+    for l in String::from_utf8_lossy(&grep.stdout)
+        .lines()
+        .map(|l| &l[1..])
+    {
+        added.push_str(l);
+        added.push('\n');
+    }
+    let mut config: CratesConfig =
+        toml::from_str(&added).map_err(|e| format!("invalid crates added to {}: {}", name, e))?;
+    for (key, krate) in &extra.crates {
+        config
+            .crates
+            .entry(key.clone())
+            .or_insert_with(|| krate.clone());
+    }
+    Ok(config)
+}
+
+/// Loads the crates added for new lints, or an empty config if `new_lints` is empty.
+pub fn new_lints_config(new_lints: &[String]) -> Result<CratesConfig, String> {
+    if new_lints.is_empty() {
+        return Ok(CratesConfig::default());
+    }
+    let config = CratesConfig::load(Path::new(NEW_LINTS_CONFIG))?;
+    println!(
+        "new lint(s) {}, adding {} crate(s) from {} to the ci integration config",
+        new_lints.join(", "),
+        config.crates.len(),
+        NEW_LINTS_CONFIG
+    );
+    Ok(config)
+}
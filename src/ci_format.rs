@@ -0,0 +1,110 @@
+//! Service messages of CI systems other than GitHub, which fold the output of every job into a
+//! collapsible section and turn failures into annotations of the build.
+
+use crate::failure::Failure;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiFormat {
+    Buildkite,
+    TeamCity,
+    Azure,
+}
+
+impl FromStr for CiFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "buildkite" => Ok(Self::Buildkite),
+            "teamcity" => Ok(Self::TeamCity),
+            "azure" => Ok(Self::Azure),
+            err => Err(format!("Invalid CI format {}", err)),
+        }
+    }
+}
+
+impl CiFormat {
+    /// Opens the section of the job `job`.
+    pub fn start(self, job: &str) -> String {
+        match self {
+            Self::Buildkite => format!("--- lintcheck {}", job),
+            Self::TeamCity => format!("##teamcity[blockOpened name='{}']", teamcity_escape(job)),
+            Self::Azure => format!("##[group]lintcheck {}", job),
+        }
+    }
+
+    /// Closes the section of the job `job`. Buildkite sections end where the next one starts,
+    /// but a failed job's section is expanded.
+    pub fn end(self, job: &str, failed: bool) -> Option<String> {
+        match self {
+            Self::Buildkite if failed => Some("^^^ +++".to_string()),
+            Self::Buildkite => None,
+            Self::TeamCity => Some(format!(
+                "##teamcity[blockClosed name='{}']",
+                teamcity_escape(job)
+            )),
+            Self::Azure => Some("##[endgroup]".to_string()),
+        }
+    }
+
+    /// An annotation of the build with `failure`.
+    pub fn annotate(self, failure: &Failure) -> String {
+        let description = failure.to_string();
+        match self {
+            // Buildkite annotations need the agent, an expanded section has to do.
+            Self::Buildkite => format!("+++ {}", description),
+            Self::TeamCity => format!(
+                "##teamcity[buildProblem description='{}' identity='{}']",
+                teamcity_escape(&description),
+                teamcity_escape(&format!(
+                    "{}:{}:{}",
+                    failure.config,
+                    failure.kind,
+                    failure.krate.as_deref().unwrap_or_default()
+                ))
+            ),
+            Self::Azure => {
+                let mut properties = String::from("type=error");
+                if let Some(log) = failure.logs.first() {
+                    properties.push_str(&format!(
+                        ";sourcepath={}",
+                        azure_escape(&log.display().to_string())
+                    ));
+                }
+                format!(
+                    "##vso[task.logissue {}]{}",
+                    properties,
+                    azure_escape(&description)
+                )
+            }
+        }
+    }
+}
+
+/// Escapes the value of an attribute of a TeamCity service message.
+fn teamcity_escape(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '|' | '\'' | '[' | ']' => {
+                escaped.push('|');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("|n"),
+            '\r' => escaped.push_str("|r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes a value of an Azure logging command.
+fn azure_escape(value: &str) -> String {
+    value
+        .replace('%', "%AZP25")
+        .replace(';', "%3B")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+        .replace(']', "%5D")
+}
@@ -0,0 +1,184 @@
+//! Running clippy directly on crate sources that lintcheck downloaded, outside of lintcheck.
+
+use crate::{crates::Krate, log::Warning, wrapper_config::Checkout};
+use serde_json::Value;
+use std::{fs, path::Path, process::Command};
+
+/// Same lint configuration lintcheck uses.
+const CLIPPY_ARGS: &[&str] = &["--cap-lints=warn", "-Wclippy::pedantic", "-Wclippy::cargo"];
+
+/// The clippy to run.
+#[derive(Debug, Clone, Copy)]
+pub enum Clippy<'a> {
+    /// `cargo clippy` of an installed toolchain
+    Toolchain(&'a str),
+    /// The `cargo-clippy` lintcheck built in a checkout
+    Checkout(&'a Checkout),
+}
+
+/// A diagnostic emitted by clippy.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub warning: Warning,
+    /// Machine applicable suggestions of the diagnostic
+    pub fixes: Vec<Fix>,
+}
+
+/// Replacement of the bytes `start..end` in `file` with `replacement`.
+#[derive(Debug, Clone)]
+pub struct Fix {
+    pub file: String,
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+/// Result of linting a crate.
+#[derive(Debug, Default)]
+pub struct Output {
+    pub diagnostics: Vec<Diagnostic>,
+    /// Rendered compiler errors
+    pub errors: Vec<String>,
+}
+
+impl Clippy<'_> {
+    fn command(self) -> Command {
+        match self {
+            Self::Toolchain(toolchain) => {
+                let mut command = Command::new("cargo");
+                command.arg(format!("+{}", toolchain)).arg("clippy");
+                command
+            }
+            Self::Checkout(checkout) => {
+                let target_dir = checkout.target_dir();
+                let mut command = Command::new(target_dir.join("debug/cargo-clippy"));
+                if let Some(toolchain) = checkout_toolchain(checkout) {
+                    command.env("RUSTUP_TOOLCHAIN", toolchain);
+                }
+                // `cargo-clippy` skips its first argument, which is `clippy` when called through
+                // cargo.
+                command.arg("--");
+                command
+            }
+        }
+    }
+
+    pub fn version(self) -> String {
+        let mut command = self.command();
+        command.arg("--version");
+        if let Self::Checkout(checkout) = self {
+            command.current_dir(&checkout.path);
+        }
+        command
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Lints the sources of `krate` in `dir`, using `target_dir` for build artifacts.
+    pub fn lint(self, krate: &Krate, dir: &Path, target_dir: &Path) -> Result<Output, String> {
+        self.lint_with(krate, dir, target_dir, &[])
+    }
+
+    /// Like `lint`, passing `cargo_args`, e.g. feature flags, on to cargo.
+    pub fn lint_with(
+        self,
+        krate: &Krate,
+        dir: &Path,
+        target_dir: &Path,
+        cargo_args: &[String],
+    ) -> Result<Output, String> {
+        let output = self
+            .command()
+            .args(["--quiet", "--message-format=json", "--target-dir"])
+            .arg(target_dir)
+            .args(cargo_args)
+            .arg("--")
+            .args(CLIPPY_ARGS)
+            .current_dir(dir)
+            .output()
+            .map_err(|e| format!("couldn't execute clippy: {}", e))?;
+        let mut result = Output::default();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let json: Value = match serde_json::from_str(line) {
+                Ok(json) => json,
+                Err(_) => continue,
+            };
+            if json["reason"] != "compiler-message" {
+                continue;
+            }
+            if json["message"]["level"] == "error" {
+                let message = &json["message"];
+                result.errors.push(
+                    message["rendered"]
+                        .as_str()
+                        .or_else(|| message["message"].as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                );
+            } else if let Some(diagnostic) = parse_diagnostic(&json["message"], krate) {
+                result.diagnostics.push(diagnostic);
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// The toolchain lintcheck uses in `checkout`: the configured one or the one pinned in the
+/// `rust-toolchain` file.
+fn checkout_toolchain(checkout: &Checkout) -> Option<String> {
+    if let Some(toolchain) = &checkout.toolchain {
+        return Some(toolchain.clone());
+    }
+    let file = fs::read_to_string(checkout.path.join("rust-toolchain")).ok()?;
+    if file.contains("[toolchain]") {
+        let value: toml::Value = toml::from_str(&file).ok()?;
+        return Some(
+            value
+                .get("toolchain")?
+                .get("channel")?
+                .as_str()?
+                .to_string(),
+        );
+    }
+    file.lines().next().map(|line| line.trim().to_string())
+}
+
+fn parse_diagnostic(message: &Value, krate: &Krate) -> Option<Diagnostic> {
+    let lint = message["code"]["code"].as_str()?;
+    if !lint.starts_with("clippy::") {
+        return None;
+    }
+    let span = message["spans"]
+        .as_array()?
+        .iter()
+        .find(|span| span["is_primary"] == true)?;
+    let fixes = message["children"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|child| child["spans"].as_array())
+        .flatten()
+        .filter(|span| span["suggestion_applicability"] == "MachineApplicable")
+        .filter_map(|span| {
+            Some(Fix {
+                file: span["file_name"].as_str()?.to_string(),
+                start: span["byte_start"].as_u64()? as usize,
+                end: span["byte_end"].as_u64()? as usize,
+                replacement: span["suggested_replacement"].as_str()?.to_string(),
+            })
+        })
+        .collect();
+    Some(Diagnostic {
+        warning: Warning {
+            krate: krate.name.clone(),
+            version: krate.version.clone(),
+            file: span["file_name"].as_str()?.to_string(),
+            line: span["line_start"].as_u64()? as usize,
+            column: span["column_start"].as_u64().map(|column| column as usize),
+            lint: lint.to_string(),
+            message: message["message"].as_str()?.to_string(),
+        },
+        fixes,
+    })
+}
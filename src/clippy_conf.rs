@@ -0,0 +1,88 @@
+//! The `clippy.toml` injected into the lintcheck run with `--clippy-conf`, to evaluate how
+//! configuration values change the results on the whole corpus.
+//!
+//! Clippy reads its configuration from the directory in `CLIPPY_CONF_DIR`, which takes
+//! precedence over the `clippy.toml` of the crates.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tempfile::TempDir;
+
+/// File names clippy looks for in `CLIPPY_CONF_DIR`.
+const CONF_FILES: &[&str] = &["clippy.toml", ".clippy.toml"];
+
+#[derive(Debug)]
+pub struct ClippyConf {
+    /// Directory exported as `CLIPPY_CONF_DIR`
+    pub dir: PathBuf,
+    /// Keeps the directory of a config given as a file alive
+    _tempdir: Option<TempDir>,
+}
+
+impl ClippyConf {
+    /// The config at `path`, which is either a `clippy.toml` file or a directory containing
+    /// one. A file is copied into a temporary directory, because clippy only takes directories.
+    pub fn new(path: &Path) -> Result<Self, String> {
+        let path = path
+            .canonicalize()
+            .map_err(|e| format!("couldn't find {}: {}", path.display(), e))?;
+        if path.is_dir() {
+            let conf = Self {
+                dir: path,
+                _tempdir: None,
+            };
+            if conf.file().is_none() {
+                return Err(format!(
+                    "{} contains neither a clippy.toml nor a .clippy.toml",
+                    conf.dir.display()
+                ));
+            }
+            return Ok(conf);
+        }
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        text.parse::<toml::Value>()
+            .map_err(|e| format!("invalid clippy config {}: {}", path.display(), e))?;
+        let tempdir =
+            tempfile::tempdir().map_err(|e| format!("couldn't create a temp dir: {}", e))?;
+        let copy = tempdir.path().join("clippy.toml");
+        fs::write(&copy, text).map_err(|e| format!("couldn't write {}: {}", copy.display(), e))?;
+        Ok(Self {
+            dir: tempdir.path().to_path_buf(),
+            _tempdir: Some(tempdir),
+        })
+    }
+
+    /// A config that sets `msrv` and otherwise is `base`, or clippy's defaults without a base.
+    pub fn with_msrv(base: Option<&Self>, msrv: &str) -> Result<Self, String> {
+        let mut table = match base.and_then(Self::file) {
+            Some(path) => fs::read_to_string(&path)
+                .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?
+                .parse::<toml::Value>()
+                .ok()
+                .and_then(|value| value.as_table().cloned())
+                .ok_or_else(|| format!("invalid clippy config {}", path.display()))?,
+            None => toml::value::Table::new(),
+        };
+        table.insert("msrv".to_string(), toml::Value::String(msrv.to_string()));
+        let tempdir =
+            tempfile::tempdir().map_err(|e| format!("couldn't create a temp dir: {}", e))?;
+        let path = tempdir.path().join("clippy.toml");
+        let text = toml::to_string(&table).expect("clippy config is serializable");
+        fs::write(&path, text).map_err(|e| format!("couldn't write {}: {}", path.display(), e))?;
+        Ok(Self {
+            dir: tempdir.path().to_path_buf(),
+            _tempdir: Some(tempdir),
+        })
+    }
+
+    /// The config file clippy reads from the directory.
+    pub fn file(&self) -> Option<PathBuf> {
+        CONF_FILES
+            .iter()
+            .map(|file| self.dir.join(file))
+            .find(|path| path.is_file())
+    }
+}
@@ -0,0 +1,175 @@
+//! Searching crate sources for code that matches a pattern, to find crates that would trigger a
+//! lint that the corpus doesn't cover. crates.io has no code search, so the crates extracted on
+//! this machine serve as the index: the sources lintcheck extracted into the clippy checkout, the
+//! sources in the registry cache of cargo and any other directories of extracted crates.
+
+use crate::{crates::LINTCHECK_SOURCES, lints, pattern::Regex};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// Length in bytes above which lines aren't searched. Such lines are generated code, like unicode
+/// tables or bindings, that nobody would add a crate to the corpus for.
+const MAX_LINE_LENGTH: usize = 1000;
+
+/// A crate whose sources match the pattern.
+#[derive(Debug)]
+pub struct Candidate {
+    pub name: String,
+    pub version: String,
+    /// Number of matching lines
+    pub matches: usize,
+    /// The first matching line, with its location relative to the crate
+    pub example: String,
+}
+
+/// The directories of extracted crates, named `<name>-<version>`, that are searched by default:
+/// the lintcheck sources of the checkout at `clippy` and the registry sources of cargo.
+pub fn default_roots(clippy: &Path) -> Vec<PathBuf> {
+    let mut roots = vec![clippy.join(LINTCHECK_SOURCES)];
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")));
+    if let Some(Ok(registries)) = cargo_home.map(|home| fs::read_dir(home.join("registry/src"))) {
+        roots.extend(registries.flatten().map(|registry| registry.path()));
+    }
+    roots
+}
+
+/// The crates in the directories `roots` with lines of Rust code that `pattern` matches, except
+/// for the crates that `skip` rejects by name, ordered by their number of matching lines. Of
+/// crates with several versions, the version with the most matches is kept.
+pub fn search(roots: &[PathBuf], pattern: &Regex, skip: impl Fn(&str) -> bool) -> Vec<Candidate> {
+    let mut candidates = BTreeMap::<String, Candidate>::new();
+    for root in roots {
+        let entries = match fs::read_dir(root) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            let file_name = entry.file_name();
+            let (name, version) = match split_name(&file_name.to_string_lossy()) {
+                Some((name, version)) if !skip(name) && dir.is_dir() => {
+                    (name.to_string(), version.to_string())
+                }
+                _ => continue,
+            };
+            let candidate = match search_crate(&dir, pattern) {
+                Some((matches, example)) => Candidate {
+                    name,
+                    version,
+                    matches,
+                    example,
+                },
+                None => continue,
+            };
+            let better = candidates
+                .get(&candidate.name)
+                .is_none_or(|other| other.matches < candidate.matches);
+            if better {
+                candidates.insert(candidate.name.clone(), candidate);
+            }
+        }
+    }
+    let mut candidates: Vec<Candidate> = candidates.into_values().collect();
+    candidates.sort_by(|a, b| b.matches.cmp(&a.matches).then_with(|| a.name.cmp(&b.name)));
+    candidates
+}
+
+/// The number of lines of the Rust files in `dir` that `pattern` matches and the first of them,
+/// or `None` if there are none. Lines longer than `MAX_LINE_LENGTH` are skipped.
+fn search_crate(dir: &Path, pattern: &Regex) -> Option<(usize, String)> {
+    let mut files = Vec::new();
+    lints::collect_rust_files(dir, &mut files);
+    files.sort();
+    let mut matches = 0;
+    let mut example = None;
+    for file in files {
+        let source = match fs::read_to_string(&file) {
+            Ok(source) => source,
+            Err(_) => continue,
+        };
+        for (i, line) in source.lines().enumerate() {
+            if line.len() > MAX_LINE_LENGTH || !pattern.is_match(line) {
+                continue;
+            }
+            matches += 1;
+            example.get_or_insert_with(|| {
+                let file = file.strip_prefix(dir).unwrap_or(&file);
+                format!("{}:{}: {}", file.display(), i + 1, line.trim())
+            });
+        }
+    }
+    example.map(|example| (matches, example))
+}
+
+/// Splits the name of the directory of an extracted crate into the name and the version of the
+/// crate, at the last `-` that is followed by a digit.
+fn split_name(dir: &str) -> Option<(&str, &str)> {
+    dir.char_indices()
+        .rev()
+        .filter(|&(_, c)| c == '-')
+        .map(|(i, _)| (&dir[..i], &dir[i + 1..]))
+        .find(|(name, version)| {
+            !name.is_empty() && version.starts_with(|c: char| c.is_ascii_digit())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{search, split_name, MAX_LINE_LENGTH};
+    use crate::pattern::Regex;
+    use std::{convert::TryFrom, fs, path::PathBuf};
+
+    fn pattern() -> Regex {
+        Regex::try_from(r"\.iter\(\)\.count\(\)".to_string()).unwrap()
+    }
+
+    #[test]
+    fn proposes_matching_crates() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/code_search");
+        let candidates = search(&[root], &pattern(), |name| name == "serde");
+        let found: Vec<_> = candidates
+            .iter()
+            .map(|candidate| {
+                (
+                    candidate.name.as_str(),
+                    candidate.version.as_str(),
+                    candidate.matches,
+                )
+            })
+            .collect();
+        assert_eq!(found, [("foo", "1.0.0", 2), ("bar-rs", "2.0.0-rc.1", 1)]);
+        assert_eq!(
+            candidates[0].example,
+            "src/lib.rs:2: let n = v.iter().count();"
+        );
+    }
+
+    #[test]
+    fn skips_long_lines() {
+        let root = tempfile::tempdir().unwrap();
+        let src = root.path().join("generated-1.0.0/src");
+        fs::create_dir_all(&src).unwrap();
+        let long = format!("{}v.iter().count()\n", "x".repeat(MAX_LINE_LENGTH));
+        fs::write(src.join("lib.rs"), long.repeat(3) + "w.iter().count()\n").unwrap();
+        let candidates = search(&[root.path().to_path_buf()], &pattern(), |_| false);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].matches, 1);
+        assert_eq!(candidates[0].example, "src/lib.rs:4: w.iter().count()");
+    }
+
+    #[test]
+    fn splits_directory_names() {
+        assert_eq!(split_name("serde-1.0.0"), Some(("serde", "1.0.0")));
+        assert_eq!(
+            split_name("bar-rs-2.0.0-rc.1"),
+            Some(("bar-rs", "2.0.0-rc.1"))
+        );
+        assert_eq!(split_name("foo-2d-0.1.0"), Some(("foo-2d", "0.1.0")));
+        assert_eq!(split_name("registry"), None);
+    }
+}
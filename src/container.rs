@@ -0,0 +1,152 @@
+//! `--in-container`, which runs the whole run inside a Docker or Podman container, so that the
+//! toolchain and system libraries come from the image instead of the machine the run happens on.
+//!
+//! This binary is mounted into the container and run there with the same arguments, minus the
+//! container options, so the image needs a libc the binary was linked against. The working
+//! directory, the clippy checkout and its target dir are mounted at the same paths as on the
+//! host, so that every path, e.g. in the wrapper config, means the same inside and outside of
+//! the container.
+
+use crate::wrapper_config::Checkout;
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+/// Where this binary is mounted in the container.
+const BINARY: &str = "/usr/local/bin/clippy-lintcheck";
+/// The options that select the container.
+pub const OPTIONS: &[&str] = &["--in-container", "--container-engine"];
+/// Environment variables passed into the container, if they are set.
+const FORWARDED_ENV: &[&str] = &["GITHUB_TOKEN", "RUST_BACKTRACE", "CARGO_TERM_COLOR"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    Docker,
+    Podman,
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "docker" => Ok(Self::Docker),
+            "podman" => Ok(Self::Podman),
+            err => Err(format!("Invalid option {}", err)),
+        }
+    }
+}
+
+impl Engine {
+    /// Docker if it is installed, else Podman.
+    pub fn detect() -> Self {
+        let installed = Command::new("docker")
+            .arg("--version")
+            .output()
+            .is_ok_and(|output| output.status.success());
+        if installed {
+            Self::Docker
+        } else {
+            Self::Podman
+        }
+    }
+
+    fn command(self) -> &'static str {
+        match self {
+            Self::Docker => "docker",
+            Self::Podman => "podman",
+        }
+    }
+}
+
+/// Runs this binary with `args` in a container of `image`, with `clippy` mounted. Returns the
+/// exit code of the run in the container.
+pub fn run(
+    engine: Engine,
+    image: &str,
+    clippy: &Checkout,
+    args: impl IntoIterator<Item = OsString>,
+) -> Result<i32, String> {
+    let binary = env::current_exe()
+        .map_err(|e| format!("couldn't find the clippy-lintcheck binary: {}", e))?;
+    let cwd =
+        env::current_dir().map_err(|e| format!("couldn't get the working directory: {}", e))?;
+    let mut mounts: Vec<PathBuf> = vec![cwd.clone(), cwd.join(&clippy.path)];
+    mounts.extend(
+        clippy
+            .target_dir
+            .iter()
+            .map(|target_dir| cwd.join(target_dir)),
+    );
+    mounts.sort();
+    // Directories inside of another mounted directory are mounted with it.
+    mounts.dedup_by(|dir, outer| dir.starts_with(outer));
+    let mut command = Command::new(engine.command());
+    // `--init` reaps the processes of lintcheck and forwards the signals the engine proxies.
+    command.args(["run", "--rm", "--init"]);
+    for dir in &mounts {
+        command.arg("--volume").arg(volume(dir, dir, false));
+    }
+    command
+        .arg("--volume")
+        .arg(volume(&binary, Path::new(BINARY), true))
+        .arg("--workdir")
+        .arg(&cwd);
+    for var in FORWARDED_ENV {
+        if env::var_os(var).is_some() {
+            command.args(["--env", var]);
+        }
+    }
+    let status = command
+        .arg(image)
+        .arg(BINARY)
+        .args(args)
+        .status()
+        .map_err(|e| format!("couldn't execute {}: {}", engine.command(), e))?;
+    match status.code() {
+        // The engine's own errors, e.g. for a missing image or binary.
+        Some(code @ 125..=127) => Err(format!(
+            "{} couldn't run the container, it exited with {}",
+            engine.command(),
+            code
+        )),
+        Some(code) => Ok(code),
+        None => Err(format!("{} was killed by a signal", engine.command())),
+    }
+}
+
+/// The arguments of this process without `options` and their values, e.g. the container
+/// options, which the run in the container must not get.
+pub fn forwarded_args(options: &[&str]) -> Vec<OsString> {
+    let mut args = Vec::new();
+    let mut skip_value = false;
+    for arg in env::args_os().skip(1) {
+        if std::mem::take(&mut skip_value) {
+            continue;
+        }
+        let text = arg.to_string_lossy();
+        if options.contains(&text.as_ref()) {
+            skip_value = true;
+        } else if !options
+            .iter()
+            .any(|option| text.starts_with(&format!("{}=", option)))
+        {
+            args.push(arg);
+        }
+    }
+    args
+}
+
+fn volume(source: &Path, target: &Path, read_only: bool) -> OsString {
+    let mut volume = OsString::from(source);
+    volume.push(":");
+    volume.push(target);
+    if read_only {
+        volume.push(":ro");
+    }
+    volume
+}
@@ -0,0 +1,163 @@
+//! Mapping of the crates that triggered each lint in previous runs, used to propose crates for
+//! lints that the configs barely cover and to find the lints that no crate triggers at all.
+
+use crate::{crates::CratesConfig, lints::LintIndex, log::Log, publish};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::Path,
+};
+
+/// Where the mapping is stored between runs.
+pub const COVERAGE_PATH: &str = "logs/coverage.json";
+/// Number of configured crates triggering a lint from which on nothing is suggested.
+const WELL_COVERED: usize = 3;
+/// Number of crates suggested at most.
+pub const MAX_SUGGESTIONS: usize = 10;
+/// Group of the lints that were removed from clippy and can't fire.
+const DEPRECATED: &str = "deprecated";
+
+/// The crates that triggered a lint: lint -> crate -> version -> number of warnings in the most
+/// recent log of that crate version.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Coverage {
+    lints: BTreeMap<String, BTreeMap<String, BTreeMap<String, usize>>>,
+}
+
+impl Coverage {
+    /// Loads the mapping from `path`. A missing file is treated as an empty mapping.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| format!("invalid coverage mapping {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("couldn't read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).expect("coverage is serializable");
+        publish::write(path, json + "\n")
+            .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+    }
+
+    /// Records the warnings of `log`, replacing the counts of earlier logs of the same crate
+    /// versions.
+    pub fn record(&mut self, log: &Log) {
+        let mut counts = BTreeMap::<_, usize>::new();
+        for warning in &log.warnings {
+            *counts
+                .entry((&warning.lint, &warning.krate, &warning.version))
+                .or_default() += 1;
+        }
+        for ((lint, krate, version), count) in counts {
+            self.lints
+                .entry(lint.clone())
+                .or_default()
+                .entry(krate.clone())
+                .or_default()
+                .insert(version.clone(), count);
+        }
+    }
+
+    /// Renders the lints of `lints` that no crate triggered in the recorded runs, by group, so
+    /// that the lints without any test coverage in the ecosystem stand out. Deprecated lints and
+    /// the groups that `includes_group` rejects are left out.
+    pub fn gaps(&self, lints: &LintIndex, includes_group: impl Fn(&str) -> bool) -> String {
+        let mut groups = BTreeMap::<&str, (usize, Vec<&str>)>::new();
+        for (name, lint) in lints.iter() {
+            if lint.group == DEPRECATED || !includes_group(&lint.group) {
+                continue;
+            }
+            let (total, gaps) = groups.entry(&lint.group).or_default();
+            *total += 1;
+            if self.lints.get(name).is_none_or(BTreeMap::is_empty) {
+                gaps.push(name);
+            }
+        }
+        let crates: BTreeSet<(&str, &str)> = self
+            .lints
+            .values()
+            .flatten()
+            .flat_map(|(krate, versions)| {
+                versions
+                    .keys()
+                    .map(move |version| (krate.as_str(), version.as_str()))
+            })
+            .collect();
+        let total: usize = groups.values().map(|(total, _)| total).sum();
+        let never: usize = groups.values().map(|(_, gaps)| gaps.len()).sum();
+        let mut out = format!(
+            "{} of {} lint(s) never fired in the {} crate version(s) with warnings in the \
+             recorded runs\n",
+            never,
+            total,
+            crates.len()
+        );
+        for (group, (total, gaps)) in groups {
+            if gaps.is_empty() {
+                continue;
+            }
+            out.push_str(&format!(
+                "\n{}: {} of {} never fired\n",
+                group,
+                gaps.len(),
+                total
+            ));
+            for lint in gaps {
+                out.push_str(&format!("    {}\n", lint));
+            }
+        }
+        out
+    }
+
+    /// Renders the crates of `config` triggering `lint` and, if there are few of them, the
+    /// crates that triggered it before, ordered by their number of warnings.
+    pub fn suggest(&self, lint: &str, config: &CratesConfig) -> String {
+        let lint = if lint.starts_with("clippy::") {
+            lint.to_string()
+        } else {
+            format!("clippy::{}", lint)
+        };
+        let crates = self.lints.get(&lint).cloned().unwrap_or_default();
+        let (covered, candidates): (Vec<_>, Vec<_>) = crates
+            .into_iter()
+            .partition(|(krate, _)| config.crates.values().any(|source| &source.name == krate));
+
+        let mut out = format!(
+            "{} is triggered by {} configured crate(s)",
+            lint,
+            covered.len()
+        );
+        if !covered.is_empty() {
+            let names: Vec<_> = covered.iter().map(|(krate, _)| krate.as_str()).collect();
+            out.push_str(&format!(": {}", names.join(", ")));
+        }
+        out.push('\n');
+        if covered.len() >= WELL_COVERED {
+            return out;
+        }
+
+        let mut candidates: Vec<_> = candidates
+            .into_iter()
+            .filter_map(|(krate, versions)| {
+                let (version, count) = versions.into_iter().max_by_key(|&(_, count)| count)?;
+                Some((krate, version, count))
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        if candidates.is_empty() {
+            out.push_str("no other crate triggered it in the recorded runs\n");
+        } else {
+            out.push_str("crates that triggered it before:\n");
+            for (krate, version, count) in candidates.into_iter().take(MAX_SUGGESTIONS) {
+                out.push_str(&format!(
+                    "    {} {}: {} warning(s)\n",
+                    krate, version, count
+                ));
+            }
+        }
+        out
+    }
+}
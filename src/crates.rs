@@ -0,0 +1,652 @@
+//! Model of the crate configs in `config/` that are handed to lintcheck.
+//!
+//! Besides the `[crates]` understood by lintcheck, a config can import crate lists from URLs:
+//!
+//! ```toml
+//! [[import]]
+//! url = "https://example.com/corpus.toml"
+//! # optional, but without it the imported crates can change unnoticed
+//! sha256 = "..."
+//! ```
+//!
+//! and fetch crates from the alternative registries registered in the wrapper config:
+//!
+//! ```toml
+//! [crates]
+//! internal = {name = "internal", versions = ['1.0.0'], registry = "internal"}
+//! ```
+//!
+//! A config can extend another one, relative to its own location, and add crates to it or remove
+//! some of its crates, so that variants of a config share one crate list:
+//!
+//! ```toml
+//! extends = "integration.toml"
+//! remove = ["syn"]
+//!
+//! [crates]
+//! regex = {name = "regex", versions = ['1.5.4']}
+//! ```
+//!
+//! Large corpora can be split into many small files that a config includes, with paths relative
+//! to its own location in which `*` and `?` match any characters of a file name. A crate may only
+//! be in one of the included files:
+//!
+//! ```toml
+//! include = ["crates/*.toml"]
+//! ```
+//!
+//! Besides TOML, configs can be written in JSON or YAML, picked by the extension `.json`, `.yaml`
+//! or `.yml` of their location, with the same structure. They are converted to TOML before they
+//! are handed to lintcheck.
+//!
+//! Crates can also be linted with other feature combinations than the default features, which
+//! lintcheck uses:
+//!
+//! ```toml
+//! [crates]
+//! serde = {name = "serde", versions = ['1.0.136'], features = ["all-features", "derive,rc"]}
+//! ```
+
+use crate::{
+    glob,
+    wrapper_config::{Registries, Registry},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// Directory, relative to the clippy checkout, into which lintcheck extracts crate sources.
+pub const LINTCHECK_SOURCES: &str = "target/lintcheck/sources";
+/// Directory, relative to the clippy checkout, lintcheck downloads crates into.
+pub const LINTCHECK_DOWNLOADS: &str = "target/lintcheck/downloads";
+/// Directory into which crates of alternative registries are extracted.
+const REGISTRY_SOURCES: &str = "target/registry-sources";
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct CratesConfig {
+    /// Empty in configs that only include or extend other configs
+    #[serde(default)]
+    pub crates: BTreeMap<String, CrateSource>,
+    /// Remote crate lists merged into this config, so that several repositories can share one
+    /// corpus
+    #[serde(default, skip_serializing)]
+    pub import: Vec<Import>,
+    /// Config whose crates this config inherits, relative to the location of this config
+    #[serde(default, skip_serializing)]
+    pub extends: Option<String>,
+    /// Keys of the inherited crates that are left out
+    #[serde(default, skip_serializing)]
+    pub remove: Vec<String>,
+    /// Globs of the configs whose crates are part of this config, relative to the location of
+    /// this config
+    #[serde(default, skip_serializing)]
+    pub include: Vec<String>,
+}
+
+/// An `[[import]]` entry. Pinning the `sha256` of the file makes sure that the crates don't
+/// change unnoticed.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Import {
+    pub url: String,
+    pub sha256: Option<String>,
+}
+
+/// A `[crates]` entry. Exactly one of `versions`, `git_url` + `git_hash` or `path` is set.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct CrateSource {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub versions: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Alternative registry the `versions` are fetched from instead of crates.io
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// Feature combinations linted besides the default features: `all-features`,
+    /// `no-default-features` or comma separated features
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub features: Option<Vec<String>>,
+}
+
+/// A single crate lintcheck checks. A `CrateSource` with several versions yields one per
+/// version.
+#[derive(Debug, Clone)]
+pub struct Krate {
+    pub name: String,
+    /// The version as lintcheck writes it into the log: the crates.io version, the git hash or
+    /// `local` for path crates
+    pub version: String,
+    /// Location of the sources relative to the clippy checkout
+    pub source_dir: PathBuf,
+}
+
+impl CratesConfig {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        Self::parse(&path.to_string_lossy(), &text)
+    }
+
+    /// Parses the `text` of the config at `location` in the format of its extension.
+    fn parse(location: &str, text: &str) -> Result<Self, String> {
+        let error = |e: &dyn std::fmt::Display| format!("invalid config {}: {}", location, e);
+        match Format::of(location) {
+            Format::Toml => toml::from_str(text).map_err(|e| error(&e)),
+            Format::Json => serde_json::from_str(text).map_err(|e| error(&e)),
+            Format::Yaml => serde_yaml::from_str(text).map_err(|e| error(&e)),
+        }
+    }
+
+    /// Loads the config at `location`, which is either a path or an http(s) URL whose content
+    /// must have the SHA-256 `sha256` if given, and merges the crates of the configs it
+    /// includes, of its imports and of the config it extends into it. Crates of the config itself
+    /// take precedence over included ones, which take precedence over imported ones, which take
+    /// precedence over inherited ones.
+    pub fn resolve(location: &str, sha256: Option<&str>) -> Result<Self, String> {
+        Self::resolve_extending(location, sha256, &mut Vec::new())
+    }
+
+    /// `resolve`, where `extending` are the locations of the configs that extend, include or
+    /// import the one at `location`, to find cycles.
+    fn resolve_extending(
+        location: &str,
+        sha256: Option<&str>,
+        extending: &mut Vec<String>,
+    ) -> Result<Self, String> {
+        if extending.iter().any(|extender| extender == location) {
+            return Err(format!(
+                "configs extend or include each other: {} -> {}",
+                extending.join(" -> "),
+                location
+            ));
+        }
+        let mut config = if is_url(location) {
+            Self::parse(location, &download(location, sha256)?)?
+        } else {
+            Self::load(Path::new(location))?
+        };
+        extending.push(location.to_string());
+        let included = config.included(location, extending);
+        let inherited = config
+            .extends
+            .take()
+            .map(|base| -> Result<_, String> {
+                let base = relative_to(location, &base);
+                Ok((Self::resolve_extending(&base, None, extending)?, base))
+            })
+            .transpose();
+        let imported: Result<Vec<_>, String> = std::mem::take(&mut config.import)
+            .iter()
+            .map(|import| {
+                let url = relative_to(location, &import.url);
+                Self::resolve_extending(&url, import.sha256.as_deref(), extending)
+            })
+            .collect();
+        extending.pop();
+        for (key, krate) in included? {
+            config.crates.entry(key).or_insert(krate);
+        }
+        for imported in imported? {
+            for (key, krate) in imported.crates {
+                config.crates.entry(key).or_insert(krate);
+            }
+        }
+        if let Some((mut inherited, base)) = inherited? {
+            for key in std::mem::take(&mut config.remove) {
+                if inherited.crates.remove(&key).is_none() {
+                    return Err(format!(
+                        "{} removes crate {}, which {} doesn't have",
+                        location, key, base
+                    ));
+                }
+            }
+            for (key, krate) in inherited.crates {
+                config.crates.entry(key).or_insert(krate);
+            }
+        } else if !config.remove.is_empty() {
+            return Err(format!(
+                "{} removes crates, but doesn't extend a config",
+                location
+            ));
+        }
+        Ok(config)
+    }
+
+    /// The resolved crates of the configs that the config at `location` includes.
+    fn included(
+        &mut self,
+        location: &str,
+        extending: &mut Vec<String>,
+    ) -> Result<BTreeMap<String, CrateSource>, String> {
+        let mut paths = BTreeSet::new();
+        for pattern in std::mem::take(&mut self.include) {
+            if is_url(location) {
+                return Err(format!(
+                    "{} includes {}, but configs from URLs can't include files",
+                    location, pattern
+                ));
+            }
+            let matched = glob::expand(Path::new(&relative_to(location, &pattern)))?;
+            if matched.is_empty() {
+                return Err(format!(
+                    "{} includes {}, which matches no config",
+                    location, pattern
+                ));
+            }
+            paths.extend(matched);
+        }
+        let mut included = BTreeMap::<String, (String, CrateSource)>::new();
+        for path in paths {
+            let path = path.to_string_lossy().into_owned();
+            for (key, krate) in Self::resolve_extending(&path, None, extending)?.crates {
+                if let Some((other, _)) = included.get(&key) {
+                    return Err(format!("crate {} is in both {} and {}", key, other, path));
+                }
+                included.insert(key, (path.clone(), krate));
+            }
+        }
+        Ok(included
+            .into_iter()
+            .map(|(key, (_, krate))| (key, krate))
+            .collect())
+    }
+
+    /// Config of crates from crates.io at a single version each, given by their names and
+    /// versions.
+    pub fn from_releases(releases: impl IntoIterator<Item = (String, String)>) -> Self {
+        let crates = releases
+            .into_iter()
+            .map(|(name, version)| {
+                let source = CrateSource {
+                    name: name.clone(),
+                    versions: Some(vec![version]),
+                    git_url: None,
+                    git_hash: None,
+                    path: None,
+                    registry: None,
+                    features: None,
+                };
+                (name, source)
+            })
+            .collect();
+        Self {
+            crates,
+            ..Default::default()
+        }
+    }
+
+    /// Renders the config like the configs in `config/`, one inline table per crate, below the
+    /// comment `header`.
+    pub fn render(&self, header: &str) -> String {
+        let quote = |value: &str| toml::Value::String(value.to_string()).to_string();
+        let mut text = format!("# {}\n[crates]\n", header);
+        for (key, source) in &self.crates {
+            let mut fields = vec![format!("name = {}", quote(&source.name))];
+            if let Some(versions) = &source.versions {
+                let versions: Vec<String> = versions.iter().map(|version| quote(version)).collect();
+                fields.push(format!("versions = [{}]", versions.join(", ")));
+            }
+            if let Some(git_url) = &source.git_url {
+                fields.push(format!("git_url = {}", quote(git_url)));
+            }
+            if let Some(git_hash) = &source.git_hash {
+                fields.push(format!("git_hash = {}", quote(git_hash)));
+            }
+            if let Some(path) = &source.path {
+                fields.push(format!("path = {}", quote(path)));
+            }
+            let bare = key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+            let key = if bare { key.clone() } else { quote(key) };
+            text.push_str(&format!("{} = {{{}}}\n", key, fields.join(", ")));
+        }
+        text
+    }
+
+    /// Whether the config can't be handed to lintcheck as it is.
+    pub fn needs_resolving(&self) -> bool {
+        !self.import.is_empty()
+            || self.extends.is_some()
+            || !self.include.is_empty()
+            || self.crates.values().any(|krate| krate.registry.is_some())
+    }
+
+    /// Downloads the crates of alternative registries and replaces them by path crates, because
+    /// lintcheck only downloads from crates.io.
+    pub fn fetch_registry_crates(&mut self, registries: &Registries) -> Result<(), String> {
+        for (key, source) in std::mem::take(&mut self.crates) {
+            let registry_name = match &source.registry {
+                Some(registry) => registry,
+                None => {
+                    self.crates.insert(key, source);
+                    continue;
+                }
+            };
+            let registry = registries.get(registry_name).ok_or_else(|| {
+                format!(
+                    "crate {} uses the registry `{}`, which isn't in the wrapper config",
+                    source.name, registry_name
+                )
+            })?;
+            let versions = source.versions.as_deref().unwrap_or_default();
+            if versions.is_empty() {
+                return Err(format!(
+                    "crate {} from registry `{}` has no versions",
+                    source.name, registry_name
+                ));
+            }
+            for version in versions {
+                let dir = fetch_crate(registry_name, registry, &source.name, version)?;
+                let key = if versions.len() == 1 {
+                    key.clone()
+                } else {
+                    format!("{}-{}", key, version)
+                };
+                self.crates.insert(
+                    key,
+                    CrateSource {
+                        name: source.name.clone(),
+                        versions: None,
+                        git_url: None,
+                        git_hash: None,
+                        path: Some(dir.display().to_string()),
+                        registry: None,
+                        features: source.features.clone(),
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn krates(&self) -> Vec<Krate> {
+        self.crates.values().flat_map(CrateSource::krates).collect()
+    }
+
+    /// Splits the config into configs of at most `size` crates each, the crates that take the
+    /// longest according to `duration` first, so that the crates of a batch take about as long
+    /// and lintcheck's threads finish together. Crates without a duration come first, as they
+    /// may take as long as any.
+    pub fn batches(&self, size: usize, duration: impl Fn(&str) -> Option<f64>) -> Vec<Self> {
+        let mut crates: Vec<_> = self.crates.iter().collect();
+        crates.sort_by(|(_, a), (_, b)| {
+            let duration = |source: &CrateSource| duration(&source.name).unwrap_or(f64::INFINITY);
+            duration(b).total_cmp(&duration(a))
+        });
+        crates
+            .chunks(size.max(1))
+            .map(|chunk| Self {
+                crates: chunk
+                    .iter()
+                    .map(|&(key, source)| (key.clone(), source.clone()))
+                    .collect(),
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+/// Downloads and extracts `krate` from the registry, unless that happened in an earlier run.
+/// Returns the absolute path of the sources.
+fn fetch_crate(
+    registry_name: &str,
+    registry: &Registry,
+    krate: &str,
+    version: &str,
+) -> Result<PathBuf, String> {
+    let dir = Path::new(REGISTRY_SOURCES).join(registry_name);
+    let sources = dir.join(format!("{}-{}", krate, version));
+    if !sources.is_dir() {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("couldn't create {}: {}", dir.display(), e))?;
+        let archive = dir.join(format!("{}-{}.crate", krate, version));
+        let url = registry
+            .download
+            .replace("{crate}", krate)
+            .replace("{version}", version);
+        let mut curl = Command::new("curl");
+        curl.args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--location",
+            "--output",
+        ])
+        .arg(&archive)
+        .arg(&url);
+        if let Some(token) = registry.token()? {
+            curl.arg("--header")
+                .arg(format!("Authorization: {}", token));
+        }
+        let output = curl
+            .output()
+            .map_err(|e| format!("couldn't execute curl: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "downloading {} {} from registry `{}` failed: {}",
+                krate,
+                version,
+                registry_name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let status = Command::new("tar")
+            .arg("-xzf")
+            .arg(&archive)
+            .arg("-C")
+            .arg(&dir)
+            .status()
+            .map_err(|e| format!("couldn't execute tar: {}", e))?;
+        if !status.success() {
+            return Err(format!(
+                "extracting {} failed with {}",
+                archive.display(),
+                status
+            ));
+        }
+    }
+    sources
+        .canonicalize()
+        .map_err(|e| format!("couldn't find {}: {}", sources.display(), e))
+}
+
+/// Location of `target`, a path or URL relative to the config at `location`.
+fn relative_to(location: &str, target: &str) -> String {
+    if is_url(target) {
+        target.to_string()
+    } else if is_url(location) {
+        match location.rsplit_once('/') {
+            Some((dir, _)) => format!("{}/{}", dir, target),
+            None => target.to_string(),
+        }
+    } else {
+        let dir = Path::new(location)
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        dir.join(target).to_string_lossy().into_owned()
+    }
+}
+
+/// Formats of configs, of which lintcheck only understands TOML.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl Format {
+    /// The format of the config at `location`, by its extension. Configs without a known
+    /// extension are TOML.
+    fn of(location: &str) -> Self {
+        let path = location.split(['?', '#']).next().unwrap_or(location);
+        match Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("json") => Self::Json,
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Toml,
+        }
+    }
+}
+
+/// Whether the config at `location` is written in TOML, which lintcheck can read.
+pub fn is_toml(location: &str) -> bool {
+    Format::of(location) == Format::Toml
+}
+
+pub fn is_url(location: &str) -> bool {
+    location.starts_with("https://") || location.starts_with("http://")
+}
+
+/// Downloads `url` with curl and checks it against the pinned `sha256`. Without a pin the
+/// checksum is printed, so that it can be added to the import.
+fn download(url: &str, sha256: Option<&str>) -> Result<String, String> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location", url])
+        .output()
+        .map_err(|e| format!("couldn't execute curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "downloading {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let checksum = sha256sum(&output.stdout)?;
+    match sha256 {
+        Some(expected) if !expected.eq_ignore_ascii_case(&checksum) => {
+            return Err(format!(
+                "checksum mismatch for {}: expected {}, got {}",
+                url, expected, checksum
+            ))
+        }
+        Some(_) => {}
+        None => println!("{} isn't pinned, its sha256 is {}", url, checksum),
+    }
+    String::from_utf8(output.stdout).map_err(|e| format!("{} isn't valid UTF-8: {}", url, e))
+}
+
+pub fn sha256sum(data: &[u8]) -> Result<String, String> {
+    let mut child = Command::new("sha256sum")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("couldn't execute sha256sum: {}", e))?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(data)
+        .map_err(|e| format!("couldn't write to sha256sum: {}", e))?;
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("couldn't wait for sha256sum: {}", e))?;
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .next()
+        .map(str::to_string)
+        .ok_or_else(|| format!("sha256sum exited with {}", output.status))
+}
+
+impl CrateSource {
+    pub fn krates(&self) -> Vec<Krate> {
+        let sources = Path::new(LINTCHECK_SOURCES);
+        if let Some(versions) = &self.versions {
+            versions
+                .iter()
+                .map(|version| Krate {
+                    name: self.name.clone(),
+                    version: version.clone(),
+                    source_dir: sources.join(format!("{}-{}", self.name, version)),
+                })
+                .collect()
+        } else if let Some(git_hash) = &self.git_hash {
+            vec![Krate {
+                name: self.name.clone(),
+                version: git_hash.clone(),
+                source_dir: sources.join(&self.name),
+            }]
+        } else if let Some(path) = &self.path {
+            vec![Krate {
+                name: self.name.clone(),
+                version: "local".to_string(),
+                source_dir: PathBuf::from(path),
+            }]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CratesConfig;
+    use std::fs;
+
+    #[test]
+    fn import_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("a.toml"),
+            "extends = \"b.toml\"\n\n[crates]\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("b.toml"),
+            "[[import]]\nurl = \"a.toml\"\n\n[crates]\n",
+        )
+        .unwrap();
+        let a = dir.path().join("a.toml").to_string_lossy().into_owned();
+        let error = CratesConfig::resolve(&a, None).unwrap_err();
+        assert!(
+            error.starts_with("configs extend or include each other"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn relative_import() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("corpus")).unwrap();
+        fs::write(
+            dir.path().join("main.toml"),
+            "[[import]]\nurl = \"corpus/extra.toml\"\n\n[crates]\n\
+             serde = {name = \"serde\", versions = ['1.0.0']}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("corpus/extra.toml"),
+            "[crates]\nserde = {name = \"serde\", versions = ['0.9.0']}\n\
+             syn = {name = \"syn\", versions = ['2.0.0']}\n",
+        )
+        .unwrap();
+        let main = dir.path().join("main.toml").to_string_lossy().into_owned();
+        let config = CratesConfig::resolve(&main, None).unwrap();
+        let versions: Vec<_> = config
+            .crates
+            .iter()
+            .map(|(key, source)| (key.as_str(), source.versions.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            versions,
+            [
+                ("serde", vec!["1.0.0".to_string()]),
+                ("syn", vec!["2.0.0".to_string()])
+            ]
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! The parts of the [crates.io API](https://crates.io/data-access) needed to keep the pinned
+//! versions of the configs up to date, to check its most downloaded crates and to find the crates
+//! that depend on a crate.
+
+use serde::{de::DeserializeOwned, Deserialize};
+use std::process::Command;
+
+/// crates.io rejects requests without a user agent that identifies the client.
+const USER_AGENT: &str = "clippy-lintcheck (https://github.com/flip1995/clippy-lintcheck)";
+
+#[derive(Debug, Deserialize)]
+struct CrateResponse {
+    versions: Vec<Version>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CratesResponse {
+    crates: Vec<Summary>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Summary {
+    name: String,
+    max_stable_version: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReverseDependenciesResponse {
+    dependencies: Vec<Dependency>,
+    versions: Vec<DependentVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Dependency {
+    version_id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct DependentVersion {
+    id: u64,
+    #[serde(rename = "crate")]
+    krate: String,
+    num: String,
+    yanked: bool,
+}
+
+/// Number of crates per page of the crate list, the maximum crates.io allows.
+const PAGE_SIZE: usize = 100;
+
+/// A published version of a crate.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Version {
+    pub num: String,
+    pub yanked: bool,
+}
+
+/// Every published version of the crate `name`.
+pub fn versions(name: &str) -> Result<Vec<Version>, String> {
+    get::<CrateResponse>(&format!("https://crates.io/api/v1/crates/{}", name))
+        .map(|response| response.versions)
+}
+
+/// The names and latest releases of the `count` most downloaded crates, skipping crates without
+/// a release.
+pub fn top(count: usize) -> Result<Vec<(String, String)>, String> {
+    let mut top = Vec::new();
+    for page in 1.. {
+        let url = format!(
+            "https://crates.io/api/v1/crates?sort=downloads&per_page={}&page={}",
+            PAGE_SIZE, page
+        );
+        let crates = get::<CratesResponse>(&url)?.crates;
+        let last = crates.len() < PAGE_SIZE;
+        top.extend(
+            crates
+                .into_iter()
+                .filter_map(|krate| Some((krate.name, krate.max_stable_version?))),
+        );
+        if last || top.len() >= count {
+            break;
+        }
+    }
+    top.truncate(count);
+    Ok(top)
+}
+
+/// The names and versions of the `count` most downloaded crates that depend on the crate `name`,
+/// at the version that does, skipping yanked versions.
+pub fn reverse_dependencies(name: &str, count: usize) -> Result<Vec<(String, String)>, String> {
+    let mut dependents: Vec<(String, String)> = Vec::new();
+    for page in 1.. {
+        let url = format!(
+            "https://crates.io/api/v1/crates/{}/reverse_dependencies?per_page={}&page={}",
+            name, PAGE_SIZE, page
+        );
+        let response = get::<ReverseDependenciesResponse>(&url)?;
+        let last = response.dependencies.len() < PAGE_SIZE;
+        for dependency in &response.dependencies {
+            let version = response
+                .versions
+                .iter()
+                .find(|version| version.id == dependency.version_id);
+            if let Some(version) = version.filter(|version| !version.yanked) {
+                if !dependents.iter().any(|(krate, _)| *krate == version.krate) {
+                    dependents.push((version.krate.clone(), version.num.clone()));
+                }
+            }
+        }
+        if last || dependents.len() >= count {
+            break;
+        }
+    }
+    dependents.truncate(count);
+    Ok(dependents)
+}
+
+fn get<T: DeserializeOwned>(url: &str) -> Result<T, String> {
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args(["--user-agent", USER_AGENT])
+        .arg(url)
+        .output()
+        .map_err(|e| format!("couldn't execute curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "downloading {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("invalid response from {}: {}", url, e))
+}
+
+/// The newest version of `versions` that isn't yanked or a pre-release.
+pub fn latest(versions: &[Version]) -> Option<&str> {
+    versions
+        .iter()
+        .filter(|version| !version.yanked)
+        .filter_map(|version| Some((release(&version.num)?, version.num.as_str())))
+        .max()
+        .map(|(_, num)| num)
+}
+
+/// The newest release of `versions` that isn't yanked and is semver compatible with `version`,
+/// or the newest release at all if there is none.
+pub fn replacement<'a>(versions: &'a [Version], version: &str) -> Option<&'a str> {
+    let compatible = |(major, minor, _)| match release(version) {
+        Some((0, pinned_minor, _)) => major == 0 && minor == pinned_minor,
+        Some((pinned_major, ..)) => major == pinned_major,
+        None => false,
+    };
+    versions
+        .iter()
+        .filter(|candidate| !candidate.yanked)
+        .filter_map(|candidate| Some((release(&candidate.num)?, candidate.num.as_str())))
+        .filter(|&(candidate, _)| compatible(candidate))
+        .max()
+        .map(|(_, num)| num)
+        .or_else(|| latest(versions))
+}
+
+/// The `major.minor.patch` of a version that isn't a pre-release.
+pub fn release(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.split('+').next()?;
+    if version.contains('-') {
+        return None;
+    }
+    let mut parts = version.split('.').map(str::parse);
+    let release = (
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+    );
+    parts.next().is_none().then_some(release)
+}
@@ -0,0 +1,204 @@
+//! `config dead-crates`, which proposes to remove the crates of a config that lintcheck couldn't
+//! check in the last runs of its job, or to bump them to a newer release where crates.io has
+//! one, because the broken release often only needs the fixes of a newer one to compile with the
+//! current nightly. The proposal is a diff of the config and a commit message that justifies
+//! every change, ready to be applied and opened as a pull request.
+
+use crate::{
+    crates::{self, CratesConfig},
+    crates_io,
+    history::{self, Broken, History},
+    lock, publish, versions,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const DIFF_PATH: &str = "logs/dead_crates.diff";
+pub const MESSAGE_PATH: &str = "logs/dead_crates_commit.txt";
+/// Lines of context around the changes of the diff.
+const CONTEXT: usize = 3;
+
+/// Writes the proposal to remove or bump the crates of the TOML config at `path` that were broken
+/// in at least the last `runs` runs of `job` to `DIFF_PATH` and `MESSAGE_PATH`. Returns the
+/// number of changed crates, nothing is written if there are none.
+pub fn propose(path: &Path, job: &str, runs: usize) -> Result<usize, String> {
+    if !crates::is_toml(&path.to_string_lossy()) {
+        return Err(format!(
+            "{} isn't a TOML config, only those can be edited in place",
+            path.display()
+        ));
+    }
+    let history = {
+        let _shared = lock::shared_files();
+        History::load(Path::new(history::HISTORY_PATH))?
+    };
+    let broken: BTreeMap<&str, &Broken> = history.broken(job, runs).into_iter().collect();
+    let config = CratesConfig::load(path)?;
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+    let old: Vec<String> = text.lines().map(str::to_string).collect();
+    // The new content of the changed lines, `None` for removed ones.
+    let mut changes = BTreeMap::<usize, Option<String>>::new();
+    let mut justification = Vec::new();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+
+    for (key, source) in &config.crates {
+        let krates = source.krates();
+        let dead: Vec<(&str, &Broken)> = krates
+            .iter()
+            .filter_map(|krate| {
+                let (name, broken) =
+                    broken.get_key_value(format!("{} {}", krate.name, krate.version).as_str())?;
+                Some((*name, *broken))
+            })
+            .collect();
+        if dead.is_empty() {
+            continue;
+        }
+        let why = |broken: &Broken| {
+            format!(
+                "broken in the last {} run(s) of {} over {} day(s): {}",
+                broken.runs,
+                job,
+                now.saturating_sub(broken.since) / (24 * 60 * 60),
+                broken.reason
+            )
+        };
+        let i = match versions::entry_line(&old, key) {
+            Some(i) => i,
+            None => {
+                for (krate, broken) in &dead {
+                    println!("{}: {}, entry not found, left as is", krate, why(broken));
+                }
+                continue;
+            }
+        };
+        let line = &old[i];
+        let single = match source.versions.as_deref() {
+            Some([version]) if source.registry.is_none() => Some(version),
+            _ => None,
+        };
+        if let Some(current) = single {
+            let versions = crates_io::versions(&source.name)?;
+            let newer = crates_io::latest(&versions)
+                .filter(|latest| crates_io::release(latest) > crates_io::release(current));
+            if let Some(latest) = newer {
+                changes.insert(i, Some(versions::replace_version(line, current, latest)));
+                justification.push(format!(
+                    "- {} {}: bumped to {}, {}",
+                    source.name,
+                    current,
+                    latest,
+                    why(dead[0].1)
+                ));
+                continue;
+            }
+        }
+        if dead.len() == krates.len() {
+            changes.insert(i, None);
+            for (krate, broken) in &dead {
+                justification.push(format!("- {}: removed, {}", krate, why(broken)));
+            }
+            continue;
+        }
+        let mut edited = line.clone();
+        for (krate, broken) in &dead {
+            let version = krate.rsplit(' ').next().unwrap_or(krate);
+            match remove_version(&edited, version) {
+                Some(removed) => {
+                    edited = removed;
+                    justification.push(format!("- {}: removed, {}", krate, why(broken)));
+                }
+                None => println!("{}: {}, version not found, left as is", krate, why(broken)),
+            }
+        }
+        if edited != *line {
+            changes.insert(i, Some(edited));
+        }
+    }
+
+    if changes.is_empty() {
+        return Ok(0);
+    }
+    let diff = unified_diff(&path.to_string_lossy(), &old, &changes);
+    let message = format!(
+        "Remove or bump crates of {} that are broken in {}\n\n{}\n",
+        path.display(),
+        job,
+        justification.join("\n")
+    );
+    publish::write(Path::new(DIFF_PATH), diff)
+        .map_err(|e| format!("couldn't write {}: {}", DIFF_PATH, e))?;
+    publish::write(Path::new(MESSAGE_PATH), message)
+        .map_err(|e| format!("couldn't write {}: {}", MESSAGE_PATH, e))?;
+    Ok(changes.len())
+}
+
+/// `line` with the quoted `version` removed from its list of versions, `None` if it isn't in it.
+fn remove_version(line: &str, version: &str) -> Option<String> {
+    ['\'', '"'].iter().find_map(|quote| {
+        let quoted = format!("{0}{1}{0}", quote, version);
+        [
+            format!(", {}", quoted),
+            format!("{}, ", quoted),
+            format!(",{}", quoted),
+            format!("{},", quoted),
+        ]
+        .iter()
+        .find(|pattern| line.contains(pattern.as_str()))
+        .map(|pattern| line.replacen(pattern.as_str(), "", 1))
+    })
+}
+
+/// Renders the `changes` of the `lines` of the file at `path`, replaced or, if `None`, removed
+/// lines by index, as a diff that `git apply` understands.
+fn unified_diff(path: &str, lines: &[String], changes: &BTreeMap<usize, Option<String>>) -> String {
+    let mut diff = format!("diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n", path);
+    // Groups the changes into hunks whose contexts touch or overlap.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &i in changes.keys() {
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + CONTEXT + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    // Number of lines the file shrank by before the current hunk.
+    let mut removed = 0;
+    for (start, end) in hunks {
+        let mut body = String::new();
+        let mut new_count = 0;
+        for (i, line) in lines.iter().enumerate().take(end).skip(start) {
+            match changes.get(&i) {
+                Some(change) => {
+                    body.push_str(&format!("-{}\n", line));
+                    if let Some(new) = change {
+                        body.push_str(&format!("+{}\n", new));
+                        new_count += 1;
+                    }
+                }
+                None => {
+                    body.push_str(&format!(" {}\n", line));
+                    new_count += 1;
+                }
+            }
+        }
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n{}",
+            start + 1,
+            end - start,
+            start + 1 - removed,
+            new_count,
+            body
+        ));
+        removed += end - start - new_count;
+    }
+    diff
+}
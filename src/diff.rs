@@ -0,0 +1,383 @@
+//! Comparison of the warnings of two lintcheck runs.
+
+use crate::{
+    gzip,
+    lints::LintIndex,
+    log::{self, Log, Warning},
+    publish,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// Location and lint of a warning. The crate version is left out, so that runs of the same crate
+/// from different sources can be compared.
+type Span<'a> = (&'a str, &'a str, usize, Option<usize>, &'a str);
+
+fn span(warning: &Warning) -> Span<'_> {
+    (
+        &warning.krate,
+        &warning.file,
+        warning.line,
+        warning.column,
+        &warning.lint,
+    )
+}
+
+#[derive(Debug, Default)]
+pub struct Diff<'o, 'n> {
+    /// Warnings only emitted by the new run
+    pub added: Vec<&'n Warning>,
+    /// Warnings only emitted by the old run
+    pub removed: Vec<&'o Warning>,
+    /// Warnings emitted by both runs for the same span, but with a different message, as
+    /// `(old, new)` pairs
+    pub reworded: Vec<(&'o Warning, &'n Warning)>,
+}
+
+impl<'o, 'n> Diff<'o, 'n> {
+    pub fn new(old: &'o [Warning], new: &'n [Warning]) -> Self {
+        let old_keys: BTreeSet<_> = old.iter().map(|w| (span(w), &w.message)).collect();
+        let new_keys: BTreeSet<_> = new.iter().map(|w| (span(w), &w.message)).collect();
+        let mut removed = BTreeMap::<_, Vec<_>>::new();
+        for warning in old {
+            if !new_keys.contains(&(span(warning), &warning.message)) {
+                removed.entry(span(warning)).or_default().push(warning);
+            }
+        }
+
+        let mut diff = Self::default();
+        for warning in new {
+            if old_keys.contains(&(span(warning), &warning.message)) {
+                continue;
+            }
+            // A warning at the same span with another message was reworded.
+            match removed.get_mut(&span(warning)).and_then(Vec::pop) {
+                Some(old_warning) => diff.reworded.push((old_warning, warning)),
+                None => diff.added.push(warning),
+            }
+        }
+        diff.removed = removed.into_values().flatten().collect();
+        diff
+    }
+
+    /// Renders the diff as `+`/`-` prefixed log lines annotated with the lint group, followed by
+    /// the reworded warnings.
+    pub fn render(&self, lints: &LintIndex) -> String {
+        let line = |prefix, warning| line(prefix, warning, lints);
+        let mut out = String::new();
+        for warning in &self.removed {
+            out.push_str(&line('-', warning));
+        }
+        for warning in &self.added {
+            out.push_str(&line('+', warning));
+        }
+        if !self.reworded.is_empty() {
+            out.push_str("\nReworded\n\n");
+            for (old, new) in &self.reworded {
+                out.push_str(&line('-', old));
+                out.push_str(&line('+', new));
+            }
+        }
+        out
+    }
+}
+
+fn line(prefix: char, warning: &Warning, lints: &LintIndex) -> String {
+    format!("{}{} [{}]\n", prefix, warning, lints.group(&warning.lint))
+}
+
+/// A diff that was written to a file, with what's needed to check it.
+#[derive(Debug, Default)]
+pub struct Written<'a> {
+    pub added: Vec<&'a Warning>,
+    pub removed: usize,
+    pub reworded: usize,
+    /// Number of warnings per lint in the old log
+    pub old_counts: BTreeMap<String, usize>,
+}
+
+impl Written<'_> {
+    /// Lints whose number of warnings changed, with the old and new count.
+    pub fn count_changes<'a>(&'a self, new: &'a Log) -> BTreeMap<&'a str, (usize, usize)> {
+        let new_counts = new.lint_counts();
+        self.old_counts
+            .keys()
+            .map(String::as_str)
+            .chain(new_counts.keys().copied())
+            .map(|lint| {
+                let before = self.old_counts.get(lint).copied().unwrap_or(0);
+                (lint, (before, new_counts.get(lint).copied().unwrap_or(0)))
+            })
+            .filter(|(_, (before, after))| before != after)
+            .collect()
+    }
+}
+
+/// Order in which the warnings of a lintcheck log are sorted, which is the order of their text,
+/// but without the crate version: the crate, the span as text and the message.
+type Key = (String, String, String);
+
+fn key(warning: &Warning) -> Key {
+    let text = warning.to_string();
+    let span_start = warning.krate.len() + 1 + warning.version.len();
+    // The message is written as ` "message"`.
+    let span_end = text.len() - warning.message.len() - 3;
+    (
+        warning.krate.clone(),
+        text[span_start..span_end].to_string(),
+        warning.message.clone(),
+    )
+}
+
+/// Diffs the warnings of the log at `old`, which may be gzipped, that are `included` against
+/// `new` and writes the rendered diff to `out`.
+///
+/// Lintcheck sorts its logs, so the old log is merged with the sorted new warnings while it is
+/// read, instead of being loaded into memory. Only if it turns out not to be sorted it is loaded
+/// as a whole.
+pub fn write<'a>(
+    old: &Path,
+    new: &'a [Warning],
+    included: impl Fn(&Warning) -> bool,
+    lints: &LintIndex,
+    out: &Path,
+) -> Result<Written<'a>, String> {
+    let staged = publish::staged_path(out);
+    let merged = gzip::open(old)
+        .map_err(|e| format!("couldn't read {}: {}", old.display(), e))
+        .and_then(|file| {
+            let mut writer = BufWriter::new(
+                File::create(&staged)
+                    .map_err(|e| format!("couldn't write {}: {}", staged.display(), e))?,
+            );
+            let warnings =
+                log::warnings(file).filter(|warning| warning.as_ref().map_or(true, &included));
+            let written = merge(warnings, new, lints, &mut writer)
+                .map_err(|e| format!("couldn't diff against {}: {}", old.display(), e))?;
+            writer
+                .flush()
+                .map_err(|e| format!("couldn't write {}: {}", staged.display(), e))?;
+            Ok(written)
+        })?;
+    if let Some(written) = merged {
+        return std::fs::rename(&staged, out)
+            .map(|()| written)
+            .map_err(|e| format!("couldn't write {}: {}", out.display(), e));
+    }
+
+    let old_log = Log::load(old)?;
+    let old_warnings: Vec<_> = old_log
+        .warnings
+        .into_iter()
+        .filter(|w| included(w))
+        .collect();
+    let diff = Diff::new(&old_warnings, new);
+    publish::write(out, diff.render(lints))
+        .map_err(|e| format!("couldn't write {}: {}", out.display(), e))?;
+    let mut old_counts = BTreeMap::new();
+    for warning in &old_warnings {
+        *old_counts.entry(warning.lint.clone()).or_default() += 1;
+    }
+    Ok(Written {
+        added: diff.added,
+        removed: diff.removed.len(),
+        reworded: diff.reworded.len(),
+        old_counts,
+    })
+}
+
+/// Merges the sorted `old` warnings with `new`, with the same result as `Diff`, writing the
+/// removed warnings to `out` as they are found. Returns `None` if `old` isn't sorted.
+fn merge<'a>(
+    old: impl Iterator<Item = io::Result<Warning>>,
+    new: &'a [Warning],
+    lints: &LintIndex,
+    out: &mut impl Write,
+) -> io::Result<Option<Written<'a>>> {
+    let mut new: Vec<_> = new.iter().map(|warning| (key(warning), warning)).collect();
+    new.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let mut new = new.into_iter().peekable();
+    let mut old = old.map(|warning| warning.map(|warning| (key(&warning), warning)));
+    let mut next_old = old.next().transpose()?;
+
+    let mut written = Written::default();
+    let mut reworded = String::new();
+    loop {
+        // Warnings with the same crate and span form a group, because rewording happens within
+        // a span.
+        let group = match (&next_old, new.peek()) {
+            (None, None) => break,
+            (Some((key, _)), None) | (None, Some((key, _))) => (key.0.clone(), key.1.clone()),
+            (Some((old_key, _)), Some((new_key, _))) => {
+                let (old_key, new_key) = ((&old_key.0, &old_key.1), (&new_key.0, &new_key.1));
+                let key = old_key.min(new_key);
+                (key.0.clone(), key.1.clone())
+            }
+        };
+        let in_group = |key: &Key| key.0 == group.0 && key.1 == group.1;
+        let mut olds = Vec::new();
+        while let Some((key, warning)) = next_old.take() {
+            if !in_group(&key) {
+                next_old = Some((key, warning));
+                break;
+            }
+            next_old = old.next().transpose()?;
+            if matches!(&next_old, Some((next, _)) if next < &key) {
+                return Ok(None);
+            }
+            *written.old_counts.entry(warning.lint.clone()).or_default() += 1;
+            olds.push(warning);
+        }
+        let mut news = Vec::new();
+        while let Some((_, warning)) = new.next_if(|(key, _)| in_group(key)) {
+            news.push(warning);
+        }
+
+        let old_messages: BTreeSet<_> = olds.iter().map(|w| &w.message).collect();
+        let new_messages: BTreeSet<_> = news.iter().map(|w| &w.message).collect();
+        let mut removed: Vec<_> = olds
+            .iter()
+            .filter(|w| !new_messages.contains(&w.message))
+            .collect();
+        for warning in news {
+            if old_messages.contains(&warning.message) {
+                continue;
+            }
+            match removed.pop() {
+                Some(old_warning) => {
+                    reworded.push_str(&line('-', old_warning, lints));
+                    reworded.push_str(&line('+', warning, lints));
+                    written.reworded += 1;
+                }
+                None => written.added.push(warning),
+            }
+        }
+        for warning in removed {
+            out.write_all(line('-', warning, lints).as_bytes())?;
+            written.removed += 1;
+        }
+    }
+    for warning in &written.added {
+        out.write_all(line('+', warning, lints).as_bytes())?;
+    }
+    if !reworded.is_empty() {
+        out.write_all(b"\nReworded\n\n")?;
+        out.write_all(reworded.as_bytes())?;
+    }
+    Ok(Some(written))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge, Diff};
+    use crate::{lints::LintIndex, log::Warning};
+
+    fn warnings(lines: &[&str]) -> Vec<Warning> {
+        lines
+            .iter()
+            .map(|line| Warning::parse(line).unwrap())
+            .collect()
+    }
+
+    /// The `-` and `+` lines of a rendered diff, sorted, and its reworded pairs, sorted.
+    fn sections(rendered: &str) -> (Vec<&str>, Vec<(&str, &str)>) {
+        let (changed, reworded) = rendered
+            .split_once("\nReworded\n\n")
+            .unwrap_or((rendered, ""));
+        let mut changed: Vec<_> = changed.lines().collect();
+        changed.sort_unstable();
+        let reworded: Vec<_> = reworded.lines().collect();
+        let mut reworded: Vec<_> = reworded.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+        reworded.sort_unstable();
+        (changed, reworded)
+    }
+
+    /// Checks that `merge` finds the same differences as `Diff`, and returns them.
+    fn compare(old: &[Warning], new: &[Warning]) -> String {
+        let lints = LintIndex::default();
+        let mut out = Vec::new();
+        let written = merge(old.iter().cloned().map(Ok), new, &lints, &mut out)
+            .unwrap()
+            .expect("the old warnings are sorted");
+        let merged = String::from_utf8(out).unwrap();
+        let diff = Diff::new(old, new);
+        let rendered = diff.render(&lints);
+
+        assert_eq!(sections(&merged), sections(&rendered));
+        let mut added: Vec<_> = written.added.iter().map(ToString::to_string).collect();
+        let mut diff_added: Vec<_> = diff.added.iter().map(ToString::to_string).collect();
+        added.sort();
+        diff_added.sort();
+        assert_eq!(added, diff_added);
+        assert_eq!(written.removed, diff.removed.len());
+        assert_eq!(written.reworded, diff.reworded.len());
+        rendered
+    }
+
+    #[test]
+    fn same_as_diff() {
+        let old = warnings(&[
+            r#"bar-1.0.0/src/lib.rs:3:1 clippy::foo "removed""#,
+            r#"foo-1.0.0/src/lib.rs:1:5 clippy::bar "kept""#,
+            r#"foo-1.0.0/src/lib.rs:2:5 clippy::bar "old wording""#,
+            r#"foo-1.0.0/src/main.rs:7:null clippy::baz "twice""#,
+            r#"foo-1.0.0/src/main.rs:7:null clippy::baz "twice""#,
+        ]);
+        let new = warnings(&[
+            r#"foo-1.1.0/src/main.rs:7:null clippy::baz "twice""#,
+            r#"foo-1.1.0/src/lib.rs:2:5 clippy::bar "new wording""#,
+            r#"foo-1.1.0/src/lib.rs:1:5 clippy::bar "kept""#,
+            r#"foo-1.1.0/src/lib.rs:9:1 clippy::bar "added""#,
+            r#"foo-1.1.0/src/lib.rs:9:1 clippy::bar "added""#,
+        ]);
+        let rendered = compare(&old, &new);
+        let (changed, reworded) = sections(&rendered);
+        assert_eq!(
+            changed,
+            [
+                r#"+foo-1.1.0/src/lib.rs:9:1 clippy::bar "added" [unknown]"#,
+                r#"+foo-1.1.0/src/lib.rs:9:1 clippy::bar "added" [unknown]"#,
+                r#"-bar-1.0.0/src/lib.rs:3:1 clippy::foo "removed" [unknown]"#,
+            ]
+        );
+        assert_eq!(
+            reworded,
+            [(
+                r#"-foo-1.0.0/src/lib.rs:2:5 clippy::bar "old wording" [unknown]"#,
+                r#"+foo-1.1.0/src/lib.rs:2:5 clippy::bar "new wording" [unknown]"#
+            )]
+        );
+    }
+
+    #[test]
+    fn empty_sides() {
+        let some = warnings(&[
+            r#"foo-1.0.0/src/lib.rs:1:5 clippy::bar "a""#,
+            r#"foo-1.0.0/src/lib.rs:1:5 clippy::bar "b""#,
+        ]);
+        compare(&some, &[]);
+        compare(&[], &some);
+        compare(&some, &some);
+    }
+
+    #[test]
+    fn unsorted_old() {
+        let old = warnings(&[
+            r#"foo-1.0.0/src/lib.rs:2:5 clippy::bar "second""#,
+            r#"foo-1.0.0/src/lib.rs:1:5 clippy::bar "first""#,
+        ]);
+        let new = warnings(&[r#"foo-1.0.0/src/lib.rs:1:5 clippy::bar "first""#]);
+        let mut out = Vec::new();
+        let merged = merge(
+            old.into_iter().map(Ok),
+            &new,
+            &LintIndex::default(),
+            &mut out,
+        );
+        assert!(merged.unwrap().is_none());
+    }
+}
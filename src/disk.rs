@@ -0,0 +1,145 @@
+//! Disk usage of lintcheck and pruning of its build artifacts, which otherwise grow with every
+//! crate and toolchain until the disk is full.
+
+use crate::{
+    crates::{LINTCHECK_DOWNLOADS, LINTCHECK_SOURCES},
+    wrapper_config::Checkout,
+};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Directories of a cargo profile dir that contain per crate artifacts.
+const ARTIFACT_DIRS: &[&str] = &[".fingerprint", "build", "deps", "incremental"];
+
+/// Renders the disk usage of the build artifacts, downloads and sources of lintcheck.
+pub fn report(clippy: &Checkout) -> String {
+    let downloads = clippy.path.join(LINTCHECK_DOWNLOADS);
+    let sources = clippy.path.join(LINTCHECK_SOURCES);
+    let artifacts = size(
+        &clippy.target_dir().join("lintcheck"),
+        &[&downloads, &sources],
+    );
+    format!(
+        "disk usage: build artifacts {}, downloads {}, sources {}\n",
+        gigabytes(artifacts),
+        gigabytes(size(&downloads, &[])),
+        gigabytes(size(&sources, &[]))
+    )
+}
+
+/// Deletes the least recently modified per crate build artifacts until the lintcheck target dir
+/// takes at most `max_bytes`. Returns the number of freed bytes.
+pub fn prune(clippy: &Checkout, max_bytes: u64) -> Result<u64, String> {
+    let downloads = clippy.path.join(LINTCHECK_DOWNLOADS);
+    let sources = clippy.path.join(LINTCHECK_SOURCES);
+    let target = clippy.target_dir().join("lintcheck");
+    let mut total = size(&target, &[&downloads, &sources]);
+    let mut units = BTreeMap::new();
+    collect_units(&target, &mut units);
+    let mut units: Vec<_> = units.into_values().collect();
+    units.sort_by_key(|unit| unit.modified);
+
+    let mut freed = 0;
+    for unit in units {
+        if total <= max_bytes {
+            break;
+        }
+        for path in &unit.paths {
+            let removed = if path.is_dir() {
+                fs::remove_dir_all(path)
+            } else {
+                fs::remove_file(path)
+            };
+            removed.map_err(|e| format!("couldn't remove {}: {}", path.display(), e))?;
+        }
+        total -= unit.size.min(total);
+        freed += unit.size;
+    }
+    Ok(freed)
+}
+
+pub fn gigabytes(bytes: u64) -> String {
+    format!("{:.2} GB", bytes as f64 / 1e9)
+}
+
+/// The artifacts of one compilation unit, which share the hash in their names.
+#[derive(Debug, Default)]
+struct Unit {
+    paths: Vec<PathBuf>,
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+/// Groups the artifacts of the profile dirs in `dir`, like `debug`, by profile dir and hash.
+fn collect_units(dir: &Path, units: &mut BTreeMap<(PathBuf, String), Unit>) {
+    if dir.join(".fingerprint").is_dir() {
+        for artifact_dir in ARTIFACT_DIRS {
+            for entry in read_dir(&dir.join(artifact_dir)) {
+                let path = entry.path();
+                let hash = match unit_hash(&entry.file_name().to_string_lossy()) {
+                    Some(hash) => hash,
+                    None => continue,
+                };
+                let unit = units.entry((dir.to_path_buf(), hash)).or_default();
+                unit.size += size(&path, &[]);
+                unit.modified = unit.modified.max(modified(&path));
+                unit.paths.push(path);
+            }
+        }
+        return;
+    }
+    for entry in read_dir(dir) {
+        if entry.path().is_dir() {
+            collect_units(&entry.path(), units);
+        }
+    }
+}
+
+/// The hash in artifact names like `libfoo-0123456789abcdef.rlib` or `foo-0123456789abcdef`.
+fn unit_hash(name: &str) -> Option<String> {
+    let (_, hash) = name.rsplit_once('-')?;
+    let hash = hash.split('.').next()?;
+    if hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(hash.to_string())
+    } else {
+        None
+    }
+}
+
+/// Size of everything in `path`, except for the `excluded` directories.
+fn size(path: &Path, excluded: &[&Path]) -> u64 {
+    if excluded.contains(&path) {
+        return 0;
+    }
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => read_dir(path)
+            .map(|entry| size(&entry.path(), excluded))
+            .sum(),
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    }
+}
+
+/// Latest modification time of anything in `path`.
+fn modified(path: &Path) -> Option<SystemTime> {
+    let own = fs::symlink_metadata(path).and_then(|m| m.modified()).ok();
+    if path.is_dir() {
+        read_dir(path)
+            .filter_map(|entry| modified(&entry.path()))
+            .chain(own)
+            .max()
+    } else {
+        own
+    }
+}
+
+fn read_dir(dir: &Path) -> impl Iterator<Item = fs::DirEntry> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(io::Result::ok)
+}
@@ -0,0 +1,163 @@
+//! `--distributed`, which spreads the crates of a run across the machines registered as
+//! `workers` in the wrapper config and aggregates their results into one report, so that a full
+//! run takes as long as its share on the slowest worker.
+//!
+//! The crates are split into more shards than there are workers, see `--shard`, and every worker
+//! takes the next shard from a shared queue once it is done with the last one, so that fast
+//! workers take over more of the work. Every worker is synced once like with `--remote`, into
+//! its own directory. The logs of every shard are synced back to `logs/workers/shard<i>/` and
+//! the output of its run goes to `logs/workers/shard<i>_output.log`. A shard whose worker fails,
+//! e.g. because it went offline, is put back into the queue for the remaining workers. At the end
+//! the shards are aggregated like with the `aggregate` subcommand.
+
+use crate::{
+    container,
+    failure::{Failure, FailureKind},
+    remote::{self, Remote},
+    shard::{self, Shard},
+    wrapper_config::Checkout,
+};
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    thread,
+};
+
+/// Where the logs and outputs of the shards are collected.
+pub const WORKERS_DIR: &str = "logs/workers";
+/// The options that distribute the run, which the runs on the workers must not get.
+const OPTIONS: &[&str] = &["--distributed-shards", "--remote-dir"];
+
+/// Runs `args` on `workers` in `shards` shards, in the directory `<dir>-<i>` of the `i`th
+/// worker, and aggregates the results.
+pub fn run(
+    workers: &[String],
+    dir: &str,
+    shards: usize,
+    clippy: &Checkout,
+    args: &[OsString],
+) -> Vec<Failure> {
+    let error = |message| Failure::new(FailureKind::Environment, "distributed", message);
+    if workers.is_empty() {
+        return vec![error(
+            "no workers are registered, add them as `workers` to the wrapper config".to_string(),
+        )];
+    }
+    if shards == 0 {
+        return vec![error("--distributed-shards must be at least 1".to_string())];
+    }
+    if let Err(e) = fs::remove_dir_all(WORKERS_DIR) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            return vec![error(format!("couldn't remove {}: {}", WORKERS_DIR, e))];
+        }
+    }
+    if let Err(e) = fs::create_dir_all(WORKERS_DIR) {
+        return vec![error(format!("couldn't create {}: {}", WORKERS_DIR, e))];
+    }
+
+    // Popped from the back, so the first shards run first.
+    let queue = Mutex::new(
+        (1..=shards)
+            .rev()
+            .map(|index| Shard {
+                index,
+                count: shards,
+            })
+            .collect::<Vec<_>>(),
+    );
+    let completed = Mutex::new(Vec::new());
+    let failures = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for (i, host) in workers.iter().enumerate() {
+            let dir = format!("{}-{}", dir, i + 1);
+            let (queue, completed, failures) = (&queue, &completed, &failures);
+            scope.spawn(move || {
+                let remote = Remote::new(host, &dir);
+                if let Err(message) = remote.sync(clippy) {
+                    let failure = Failure::new(FailureKind::Environment, host, message);
+                    lock(failures).push(failure.classify_network());
+                    return;
+                }
+                loop {
+                    let next = lock(queue).pop();
+                    let Some(shard) = next else { break };
+                    println!("[{}] running shard {}", host, shard);
+                    match run_shard(&remote, shard, args) {
+                        Ok(logs) => lock(completed).push(logs),
+                        Err(failure) => {
+                            println!("[{}] failed, shard {} is put back", host, shard);
+                            lock(queue).push(shard);
+                            lock(failures).push(failure.classify_network());
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    let mut failures = failures
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner);
+    let mut completed = completed
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner);
+    let left = queue.into_inner().unwrap_or_else(PoisonError::into_inner);
+    if completed.is_empty() {
+        failures.push(error("no shard finished on any worker".to_string()));
+        return failures;
+    }
+    if !left.is_empty() {
+        println!(
+            "{} shard(s) didn't run because no worker was left",
+            left.len()
+        );
+    }
+    completed.sort();
+    // Shards that didn't run are reported as missing.
+    failures.extend(shard::aggregate(&completed));
+    failures
+}
+
+/// Runs `shard` on `remote` and syncs its logs back. Returns the directory of its logs.
+fn run_shard(remote: &Remote<'_>, shard: Shard, args: &[OsString]) -> Result<PathBuf, Failure> {
+    let name = format!("shard{}", shard.index);
+    let error = |message| Failure::new(FailureKind::Environment, &name, message);
+    let output_path = Path::new(WORKERS_DIR).join(format!("{}_output.log", name));
+    let output = File::create(&output_path)
+        .map_err(|e| error(format!("couldn't create {}: {}", output_path.display(), e)))?;
+    let mut shard_args = args.to_vec();
+    shard_args.push("--shard".into());
+    shard_args.push(shard.to_string().into());
+    let code = remote.exec(shard_args, Some(output));
+    let logs = Path::new(WORKERS_DIR).join(&name);
+    let code = remote
+        .pull_logs(&logs)
+        .and(code)
+        .map_err(|message| error(message).with_log(&output_path))?;
+    // Regressions are recorded in the manifest, without it the shard didn't get to run. The one
+    // of an earlier shard on the same worker may be left behind.
+    let reported =
+        shard::Manifest::load(&logs).is_ok_and(|manifest| manifest.shard == shard.to_string());
+    if !reported {
+        return Err(
+            error(format!("the run exited with {} without a report", code)).with_log(&output_path),
+        );
+    }
+    Ok(logs)
+}
+
+/// The arguments of this process without the remote and distribution options.
+pub fn forwarded_args() -> Vec<OsString> {
+    let options: Vec<&str> = OPTIONS.iter().chain(remote::OPTIONS).copied().collect();
+    container::forwarded_args(&options)
+        .into_iter()
+        .filter(|arg| arg != "--distributed")
+        .collect()
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
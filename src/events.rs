@@ -0,0 +1,115 @@
+//! The event stream of `--events`, newline-delimited JSON events written while the run
+//! progresses, so that dashboards and bots can follow long runs without polling for the logs.
+//!
+//! ```json
+//! {"time":1700000000,"event":"run-started","configs":["integration","passes"]}
+//! {"time":1700000000,"event":"config-started","config":"integration","crates":17}
+//! {"time":1700000012,"event":"crate-started","config":"integration","crate":"serde",...}
+//! {"time":1700000400,"event":"crate-finished","config":"integration","crate":"serde",...}
+//! {"time":1700000400,"event":"ice-detected","config":"integration","crate":"syn",...}
+//! {"time":1700000811,"event":"config-finished","config":"integration","failures":0}
+//! {"time":1700000812,"event":"run-finished","exit_code":0,"failures":0}
+//! ```
+//!
+//! Lintcheck only reports when it starts linting a crate, so the `crate-finished` events of a
+//! config are emitted together once its log is written.
+
+use crate::status;
+use serde::Serialize;
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    sync::{Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The sink of the events, `None` without `--events`. Shared by the concurrently running jobs.
+static SINK: Mutex<Option<File>> = Mutex::new(None);
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event<'a> {
+    RunStarted {
+        configs: Vec<&'a str>,
+    },
+    ConfigStarted {
+        config: &'a str,
+        /// Number of crates of the config
+        crates: usize,
+    },
+    CrateStarted {
+        config: &'a str,
+        #[serde(rename = "crate")]
+        krate: &'a str,
+        version: &'a str,
+    },
+    CrateFinished {
+        config: &'a str,
+        #[serde(rename = "crate")]
+        krate: &'a str,
+        version: &'a str,
+        warnings: usize,
+        /// Why lintcheck didn't lint the crate, if it didn't
+        skipped: Option<&'a str>,
+    },
+    IceDetected {
+        config: &'a str,
+        #[serde(rename = "crate")]
+        krate: Option<&'a str>,
+        message: &'a str,
+    },
+    ConfigFinished {
+        config: &'a str,
+        failures: usize,
+    },
+    RunFinished {
+        exit_code: i32,
+        failures: usize,
+    },
+}
+
+#[derive(Serialize)]
+struct Timed<'a> {
+    time: u64,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+/// Sends the events to `target`, a path that is appended to or, if it is a number, an open
+/// file descriptor of the process, e.g. `3` for a pipe set up by the caller.
+pub fn open(target: &str) -> Result<(), String> {
+    let path = if !target.is_empty() && target.bytes().all(|b| b.is_ascii_digit()) {
+        format!("/dev/fd/{}", target)
+    } else {
+        target.to_string()
+    };
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("couldn't open the event stream {}: {}", path, e))?;
+    *SINK.lock().unwrap_or_else(PoisonError::into_inner) = Some(file);
+    Ok(())
+}
+
+/// Writes `event` to the stream, if there is one, and updates the status of the run. Events
+/// are best effort, a reader that went away doesn't fail the run.
+pub fn emit(event: Event<'_>) {
+    status::observe(&event);
+    let mut sink = SINK.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(file) = sink.as_mut() {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let mut line = serde_json::to_string(&Timed {
+            time,
+            event: &event,
+        })
+        .expect("events are serializable");
+        line.push('\n');
+        // A single write, so that readers never see a partial event.
+        if file.write_all(line.as_bytes()).is_err() {
+            *sink = None;
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Documentation of the exit codes, shown in `--help`.
+pub const EXIT_CODES_HELP: &str = "EXIT CODES:
+    0    Success
+    1    Regression: an expectation on the produced logs didn't hold
+    2    ICE: clippy crashed on at least one crate
+    3    Lintcheck invocation error, e.g. lintcheck failed or produced no log
+    4    Environment or setup error, e.g. invalid arguments or unwritable files
+    5    Infrastructure flake: a download failed because of the network
+    130  Interrupted by SIGINT or SIGTERM, the logs only have the results up to the interruption
+
+If failures of several classes occurred, the exit code of the first class in the order
+130, 4, 2, 1, 3, 5 is used, so that 3 and 5 are only returned if retrying the run could help.";
+
+/// Error messages of curl, cargo and lintcheck's HTTP client that point to network problems.
+const NETWORK_ERRORS: &[&str] = &[
+    "could not resolve host",
+    "couldn't resolve host",
+    "failed to resolve",
+    "connection refused",
+    "connection reset",
+    "connection timed out",
+    "operation timed out",
+    "network unreachable",
+    "spurious network error",
+    "failed to download",
+    "error sending request",
+    "ssl connect error",
+    "502 bad gateway",
+    "503 service unavailable",
+    "504 gateway timeout",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// An expectation on the produced log didn't hold, e.g. warnings in the passes config
+    Regression,
+    /// Clippy crashed on a crate
+    Ice,
+    /// Lintcheck itself couldn't be run or didn't produce a log
+    Lintcheck,
+    /// Something about the local setup is broken, e.g. files couldn't be read or written
+    Environment,
+    /// A download failed because of the network, which says nothing about clippy
+    Network,
+    /// The run was interrupted by SIGINT or SIGTERM
+    Interrupted,
+}
+
+impl FailureKind {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Regression => 1,
+            Self::Ice => 2,
+            Self::Lintcheck => 3,
+            Self::Environment => 4,
+            Self::Network => 5,
+            Self::Interrupted => 130,
+        }
+    }
+
+    /// Lintcheck and network errors are the only ones that may go away on a retry, so every
+    /// other kind takes precedence when choosing the exit code. An interruption explains every
+    /// other failure, so it takes precedence over all of them.
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Interrupted => 5,
+            Self::Environment => 4,
+            Self::Ice => 3,
+            Self::Regression => 2,
+            Self::Lintcheck => 1,
+            Self::Network => 0,
+        }
+    }
+}
+
+/// Returns the exit code for a run that ended with `failures`.
+pub fn exit_code(failures: &[Failure]) -> i32 {
+    failures
+        .iter()
+        .map(|failure| failure.kind)
+        .max_by_key(|kind| kind.precedence())
+        .map_or(0, FailureKind::exit_code)
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Regression => "regression",
+            Self::Ice => "ICE",
+            Self::Lintcheck => "lintcheck error",
+            Self::Environment => "environment error",
+            Self::Network => "infrastructure flake",
+            Self::Interrupted => "interrupted",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Failure {
+    pub kind: FailureKind,
+    pub config: String,
+    #[serde(rename = "crate")]
+    pub krate: Option<String>,
+    pub message: String,
+    /// Log files that help to investigate the failure
+    pub logs: Vec<PathBuf>,
+}
+
+impl Failure {
+    pub fn new(kind: FailureKind, config: &str, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            config: config.to_string(),
+            krate: None,
+            message: message.into(),
+            logs: Vec::new(),
+        }
+    }
+
+    pub fn with_crate(mut self, krate: &str) -> Self {
+        self.krate = Some(krate.to_string());
+        self
+    }
+
+    pub fn with_log(mut self, path: impl AsRef<Path>) -> Self {
+        self.logs.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Reclassifies lintcheck and environment failures whose message shows that a download
+    /// failed because of the network, so that they aren't mistaken for clippy breaking.
+    pub fn classify_network(mut self) -> Self {
+        if matches!(self.kind, FailureKind::Lintcheck | FailureKind::Environment)
+            && is_network_error(&self.message)
+        {
+            self.kind = FailureKind::Network;
+        }
+        self
+    }
+}
+
+/// Whether `message` shows that something failed because of the network.
+pub fn is_network_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    NETWORK_ERRORS.iter().any(|error| message.contains(error))
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.config, self.kind)?;
+        if let Some(krate) = &self.krate {
+            write!(f, " in {}", krate)?;
+        }
+        write!(f, ": {}", self.message)?;
+        for log in &self.logs {
+            write!(f, "\n    see {}", log.display())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            err => Err(format!("Invalid error format {}", err)),
+        }
+    }
+}
+
+/// Renders `failures` in the given format.
+pub fn render(failures: &[Failure], format: ErrorFormat) -> String {
+    match format {
+        ErrorFormat::Human => {
+            let (flakes, failures): (Vec<_>, Vec<_>) = failures
+                .iter()
+                .partition(|failure| failure.kind == FailureKind::Network);
+            let mut out = String::new();
+            if !failures.is_empty() {
+                out.push_str(&format!("{} failure(s):\n", failures.len()));
+                for failure in failures {
+                    out.push_str(&format!("{}\n", failure));
+                }
+            }
+            if !flakes.is_empty() {
+                out.push_str(&format!(
+                    "{} infrastructure flake(s), retrying may help:\n",
+                    flakes.len()
+                ));
+                for failure in flakes {
+                    out.push_str(&format!("{}\n", failure));
+                }
+            }
+            out
+        }
+        ErrorFormat::Json => {
+            let mut out =
+                serde_json::to_string_pretty(&serde_json::json!({ "failures": failures }))
+                    .expect("failures are serializable");
+            out.push('\n');
+            out
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Linting crates with the feature combinations of their `features` in the config, because
+//! lintcheck only checks the default features and many warnings are behind feature gates.
+
+use crate::{
+    clippy::Clippy,
+    crates::CratesConfig,
+    diff::Diff,
+    failure::{Failure, FailureKind},
+    job::{Expect, Job},
+    lints::LintIndex,
+    log::{Log, Warning},
+    publish,
+    wrapper_config::Checkout,
+};
+use std::path::Path;
+
+/// The cargo arguments that select the features of `combination`.
+fn cargo_args(combination: &str) -> Vec<String> {
+    match combination {
+        "default" => Vec::new(),
+        "all-features" => vec!["--all-features".to_string()],
+        "no-default-features" => vec!["--no-default-features".to_string()],
+        features => vec!["--features".to_string(), features.to_string()],
+    }
+}
+
+/// Lints every crate of `job` with `features` with each of its feature combinations and
+/// writes the warnings that the default features, whose warnings are in `default`, don't have to
+/// `logs/<job>_features_diff.txt`. In a config that must pass, every such warning is a
+/// regression.
+pub fn check(clippy: &Checkout, job: &Job, default: &Log, lints: &LintIndex) -> Vec<Failure> {
+    let error = |kind, message: String| vec![Failure::new(kind, &job.name, message)];
+    let config = match CratesConfig::load(&job.config) {
+        Ok(config) => config,
+        Err(e) => return error(FailureKind::Environment, e),
+    };
+    if config
+        .crates
+        .values()
+        .all(|source| source.features.is_none())
+    {
+        return Vec::new();
+    }
+    let target_dir = clippy.path.join("target/lintcheck/features");
+    let diff_path = format!("logs/{}_features_diff.txt", job.name);
+    let mut report = String::new();
+    let mut failures = Vec::new();
+    for source in config.crates.values() {
+        let combinations = match &source.features {
+            Some(combinations) => combinations,
+            None => continue,
+        };
+        for krate in source.krates() {
+            let source_dir = clippy.path.join(&krate.source_dir);
+            if !source_dir.exists() {
+                continue;
+            }
+            let default: Vec<_> = default
+                .warnings
+                .iter()
+                .filter(|warning| warning.krate == krate.name && warning.version == krate.version)
+                .cloned()
+                .collect();
+            // Lintcheck already linted the default features.
+            for combination in combinations
+                .iter()
+                .filter(|&combination| combination != "default")
+            {
+                println!(
+                    "Linting {} {} with features {}...",
+                    krate.name, krate.version, combination
+                );
+                let output = match Clippy::Checkout(clippy).lint_with(
+                    &krate,
+                    &source_dir,
+                    &target_dir,
+                    &cargo_args(combination),
+                ) {
+                    Ok(output) => output,
+                    Err(e) => return error(FailureKind::Lintcheck, e),
+                };
+                let warnings: Vec<Warning> = output
+                    .diagnostics
+                    .into_iter()
+                    .map(|diagnostic| diagnostic.warning)
+                    .collect();
+                let mut diff = Diff::new(&default, &warnings);
+                println!(
+                    "    {} warning(s) only with {}, {} only with the default features",
+                    diff.added.len(),
+                    combination,
+                    diff.removed.len()
+                );
+                if diff.added.is_empty() {
+                    continue;
+                }
+                diff.removed.clear();
+                report.push_str(&format!(
+                    "{} {} with {}\n{}\n",
+                    krate.name,
+                    krate.version,
+                    combination,
+                    diff.render(lints)
+                ));
+                if let Expect::Passes = job.expect {
+                    failures.push(
+                        Failure::new(
+                            FailureKind::Regression,
+                            &job.name,
+                            format!(
+                                "{} clippy warning(s) only with features {} in a config that \
+                                 must pass",
+                                diff.added.len(),
+                                combination
+                            ),
+                        )
+                        .with_crate(&krate.name)
+                        .with_log(&diff_path),
+                    );
+                }
+            }
+        }
+    }
+    if let Err(e) = publish::write(Path::new(&diff_path), report) {
+        failures.extend(error(
+            FailureKind::Environment,
+            format!("couldn't write {}: {}", diff_path, e),
+        ));
+    }
+    failures
+}
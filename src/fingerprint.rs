@@ -0,0 +1,70 @@
+//! The fingerprint of the effective config of a job: the SHA-256 of everything besides clippy
+//! that decides on the warnings of its log, i.e. the crates that are left after the shard,
+//! `--crates` and the CI diff extraction, the clippy config, the msrv and the filters of the
+//! warnings. It is written as `config_fingerprint` into the header of the log and recorded in
+//! the summary and the history, so that two runs are comparable exactly if their fingerprints
+//! are equal, no matter how their configs are formatted.
+
+use crate::{
+    clippy_conf::ClippyConf,
+    crates::{self, CratesConfig},
+    job::{Job, RunOptions},
+    suppress::Rule,
+};
+use serde::Serialize;
+use std::fs;
+
+/// Key of the fingerprint in the header of a log.
+pub const HEADER_KEY: &str = "config_fingerprint";
+
+/// What goes into the fingerprint, serialized as JSON. Lists whose order doesn't matter are
+/// sorted, so that the serialization is stable.
+#[derive(Debug, Serialize)]
+struct Effective<'a> {
+    /// The serialized `[crates]` entries
+    crates: Vec<String>,
+    msrv: Option<&'a str>,
+    clippy_conf: Option<String>,
+    groups: Vec<&'a str>,
+    shard: Option<String>,
+    crate_filter: Vec<&'a str>,
+    suppress: Vec<String>,
+}
+
+/// The fingerprint of `job` run with `options`.
+pub fn compute(job: &Job, options: &RunOptions) -> Result<String, String> {
+    let config = CratesConfig::load(&job.config)?;
+    let clippy_conf = options
+        .clippy_conf
+        .as_ref()
+        .and_then(ClippyConf::file)
+        .map(|path| {
+            fs::read_to_string(&path)
+                .map_err(|e| format!("couldn't read {}: {}", path.display(), e))
+        })
+        .transpose()?;
+    // The keys of `[crates]` are only labels, the entries decide on what is linted.
+    let mut crates: Vec<String> = config
+        .crates
+        .values()
+        .map(|source| serde_json::to_string(source).expect("crates are serializable"))
+        .collect();
+    crates.sort();
+    let effective = Effective {
+        crates,
+        msrv: job.msrv.as_deref(),
+        clippy_conf,
+        groups: sorted(&options.groups),
+        shard: options.shard.map(|shard| shard.to_string()),
+        crate_filter: sorted(&options.crates),
+        suppress: options.suppress.iter().map(Rule::describe).collect(),
+    };
+    let json = serde_json::to_string(&effective).expect("the effective config is serializable");
+    crates::sha256sum(json.as_bytes())
+}
+
+fn sorted(list: &[String]) -> Vec<&str> {
+    let mut list: Vec<_> = list.iter().map(String::as_str).collect();
+    list.sort_unstable();
+    list
+}
@@ -0,0 +1,171 @@
+//! Round-trip verification of machine applicable suggestions: after applying all of them, the
+//! lints that suggested them must not fire again.
+
+use crate::{
+    clippy::{Clippy, Fix},
+    crates::CratesConfig,
+    failure::{Failure, FailureKind},
+    job::Job,
+    publish,
+    wrapper_config::Checkout,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::Path,
+};
+
+/// Applies the suggestions of the checkout's clippy to a copy of every crate of `job` and lints
+/// the fixed code again. Every lint that still suggests fixes afterwards doesn't converge.
+///
+/// The results are written to `logs/<job>_fix_verification.txt`.
+pub fn verify(clippy: &Checkout, job: &Job) -> Vec<Failure> {
+    let error = |kind, message: String| vec![Failure::new(kind, &job.name, message)];
+    let config = match CratesConfig::load(&job.config) {
+        Ok(config) => config,
+        Err(e) => return error(FailureKind::Environment, e),
+    };
+    let checkout_clippy = Clippy::Checkout(clippy);
+    let target_dir = clippy.path.join("target/lintcheck/fix");
+    let report_path = format!("logs/{}_fix_verification.txt", job.name);
+    let mut report = String::new();
+    let mut failures = Vec::new();
+
+    for krate in config.krates() {
+        let source_dir = clippy.path.join(&krate.source_dir);
+        if !source_dir.exists() {
+            continue;
+        }
+        let work_dir = match tempfile::tempdir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                return error(
+                    FailureKind::Environment,
+                    format!("couldn't create tempdir: {}", e),
+                )
+            }
+        };
+        if let Err(e) = copy_dir(&source_dir, work_dir.path()) {
+            return error(
+                FailureKind::Environment,
+                format!("couldn't copy {}: {}", source_dir.display(), e),
+            );
+        }
+
+        println!("Verifying fixes for {} {}...", krate.name, krate.version);
+        let before = match checkout_clippy.lint(&krate, work_dir.path(), &target_dir) {
+            Ok(output) => output,
+            Err(e) => return error(FailureKind::Lintcheck, e),
+        };
+        let mut fixed_lints = BTreeSet::new();
+        let mut fixes = BTreeMap::<_, Vec<Fix>>::new();
+        for diagnostic in before.diagnostics {
+            for fix in diagnostic.fixes {
+                fixed_lints.insert(diagnostic.warning.lint.clone());
+                fixes.entry(fix.file.clone()).or_default().push(fix);
+            }
+        }
+        if fixes.is_empty() {
+            continue;
+        }
+        for (file, fixes) in fixes {
+            if let Err(e) = apply(&work_dir.path().join(&file), fixes) {
+                return error(
+                    FailureKind::Environment,
+                    format!("couldn't apply fixes to {}: {}", file, e),
+                );
+            }
+        }
+
+        let after = match checkout_clippy.lint(&krate, work_dir.path(), &target_dir) {
+            Ok(output) => output,
+            Err(e) => return error(FailureKind::Lintcheck, e),
+        };
+        if !after.errors.is_empty() {
+            report.push_str(&format!(
+                "{}-{}: applying the suggestions introduced {} compiler error(s)\n",
+                krate.name,
+                krate.version,
+                after.errors.len()
+            ));
+            failures.push(
+                Failure::new(
+                    FailureKind::Regression,
+                    &job.name,
+                    format!(
+                        "applying machine applicable suggestions introduced {} compiler error(s)",
+                        after.errors.len()
+                    ),
+                )
+                .with_crate(&krate.name)
+                .with_log(&report_path),
+            );
+        }
+        let mut remaining = BTreeMap::<_, usize>::new();
+        for diagnostic in &after.diagnostics {
+            if !diagnostic.fixes.is_empty() && fixed_lints.contains(&diagnostic.warning.lint) {
+                *remaining
+                    .entry(diagnostic.warning.lint.clone())
+                    .or_default() += 1;
+                report.push_str(&format!("{}\n", diagnostic.warning));
+            }
+        }
+        for (lint, count) in remaining {
+            failures.push(
+                Failure::new(
+                    FailureKind::Regression,
+                    &job.name,
+                    format!(
+                        "{} still suggests {} fix(es) after applying its suggestions",
+                        lint, count
+                    ),
+                )
+                .with_crate(&krate.name)
+                .with_log(&report_path),
+            );
+        }
+    }
+
+    if let Err(e) = publish::write(&report_path, report) {
+        failures.push(Failure::new(
+            FailureKind::Environment,
+            &job.name,
+            format!("couldn't write {}: {}", report_path, e),
+        ));
+    }
+    failures
+}
+
+/// Applies `fixes` to `file`, skipping fixes that overlap with an already applied one.
+fn apply(file: &Path, mut fixes: Vec<Fix>) -> io::Result<()> {
+    let mut source = fs::read(file)?;
+    fixes.sort_by_key(|fix| (fix.start, fix.end));
+    fixes.dedup_by(|a, b| a.start == b.start && a.end == b.end && a.replacement == b.replacement);
+    // Apply back to front, so that the byte offsets of the remaining fixes stay valid.
+    let mut limit = usize::MAX;
+    for fix in fixes.iter().rev() {
+        if fix.end > limit || fix.end > source.len() || fix.start > fix.end {
+            continue;
+        }
+        source.splice(fix.start..fix.end, fix.replacement.bytes());
+        limit = fix.start;
+    }
+    fs::write(file, source)
+}
+
+/// Recursively copies `from` into `to`, leaving out build artifacts.
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            if entry.file_name() != "target" {
+                copy_dir(&entry.path(), &target)?;
+            }
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,203 @@
+//! The `triage-report` subcommand, which turns the verdicts of `triage` into a report per lint
+//! with its false positive rate and representative false positives, ready to be attached to a
+//! clippy issue that argues for moving a lint to another group.
+//!
+//! The false positive rate only counts decided verdicts, findings that need investigation are
+//! listed but don't count either way. Lints are ordered by their false positive rate.
+
+use crate::{
+    lints::LintIndex,
+    log::Warning,
+    publish,
+    triage::{Entry, Verdict, Verdicts},
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    str::FromStr,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "markdown" => Ok(Self::Markdown),
+            "json" => Ok(Self::Json),
+            err => Err(format!("Invalid option {}", err)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct LintReport<'a> {
+    lint: String,
+    /// Group of the lint in the checkout the report was made with, `unknown` without one
+    group: &'a str,
+    true_positives: usize,
+    false_positives: usize,
+    needs_investigation: usize,
+    /// False positives per decided verdict, `None` without decided verdicts
+    false_positive_rate: Option<f64>,
+    examples: Vec<Example<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Example<'a> {
+    #[serde(rename = "crate")]
+    krate: &'a str,
+    version: &'a str,
+    location: String,
+    message: &'a str,
+    note: Option<&'a str>,
+    snippet: Option<&'a str>,
+}
+
+/// Renders the report of the verdicts of the `lints`, all if empty, with at most `examples`
+/// false positives per lint.
+pub fn render(
+    verdicts: &Verdicts,
+    lints: &[String],
+    examples: usize,
+    index: &LintIndex,
+    format: ReportFormat,
+) -> String {
+    let parsed: Vec<(Warning, &Entry)> = verdicts
+        .entries()
+        .filter_map(|entry| Some((Warning::parse(&entry.warning)?, entry)))
+        .filter(|(warning, _)| lints.is_empty() || lints.contains(&warning.lint))
+        .collect();
+    let mut by_lint = BTreeMap::<&str, Vec<&(Warning, &Entry)>>::new();
+    for finding in &parsed {
+        by_lint.entry(&finding.0.lint).or_default().push(finding);
+    }
+    let mut reports: Vec<LintReport<'_>> = by_lint
+        .into_iter()
+        .map(|(lint, findings)| report(lint, &findings, examples, index))
+        .collect();
+    reports.sort_by(|a, b| {
+        let rate = |report: &LintReport<'_>| report.false_positive_rate.unwrap_or(-1.0);
+        rate(b)
+            .total_cmp(&rate(a))
+            .then(b.false_positives.cmp(&a.false_positives))
+    });
+    match format {
+        ReportFormat::Json => {
+            serde_json::to_string_pretty(&reports).expect("reports are serializable") + "\n"
+        }
+        ReportFormat::Markdown => markdown(&reports),
+    }
+}
+
+fn report<'a>(
+    lint: &str,
+    findings: &[&'a (Warning, &'a Entry)],
+    examples: usize,
+    index: &'a LintIndex,
+) -> LintReport<'a> {
+    let count = |verdict| {
+        findings
+            .iter()
+            .filter(|(_, entry)| entry.verdict == verdict)
+            .count()
+    };
+    let true_positives = count(Verdict::TruePositive);
+    let false_positives = count(Verdict::FalsePositive);
+    let decided = true_positives + false_positives;
+    let mut candidates: Vec<&(Warning, &Entry)> = findings
+        .iter()
+        .copied()
+        .filter(|(_, entry)| entry.verdict == Verdict::FalsePositive)
+        .collect();
+    // Findings with a note say why they are false positives, and examples from many crates show
+    // that the problem isn't a quirk of one code base.
+    candidates.sort_by_key(|(warning, entry)| (entry.note.is_none(), warning.krate.clone()));
+    let mut crates = BTreeSet::new();
+    let (mut chosen, rest): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .partition(|(warning, _)| crates.insert(warning.krate.as_str()));
+    chosen.extend(rest);
+    chosen.truncate(examples);
+    LintReport {
+        lint: lint.to_string(),
+        group: index.group(lint),
+        true_positives,
+        false_positives,
+        needs_investigation: count(Verdict::NeedsInvestigation),
+        false_positive_rate: (decided > 0).then(|| false_positives as f64 / decided as f64),
+        examples: chosen
+            .into_iter()
+            .map(|(warning, entry)| Example {
+                krate: &warning.krate,
+                version: &warning.version,
+                location: format!("{}:{}", warning.file, warning.line),
+                message: &warning.message,
+                note: entry.note.as_deref(),
+                snippet: entry.snippet.as_deref(),
+            })
+            .collect(),
+    }
+}
+
+fn markdown(reports: &[LintReport<'_>]) -> String {
+    let mut out = String::from(
+        "# False positives\n\n| lint | group | true positives | false positives | needs \
+         investigation | false positive rate |\n| --- | --- | --- | --- | --- | --- |\n",
+    );
+    for report in reports {
+        out.push_str(&format!(
+            "| `{}` | {} | {} | {} | {} | {} |\n",
+            report.lint,
+            report.group,
+            report.true_positives,
+            report.false_positives,
+            report.needs_investigation,
+            rate(report.false_positive_rate)
+        ));
+    }
+    for report in reports.iter().filter(|report| !report.examples.is_empty()) {
+        out.push_str(&format!(
+            "\n## `{}`\n\n{} of {} decided finding(s) are false positives ({}).\n",
+            report.lint,
+            report.false_positives,
+            report.true_positives + report.false_positives,
+            rate(report.false_positive_rate)
+        ));
+        for example in &report.examples {
+            out.push_str(&format!(
+                "\n### {} {} `{}`\n\n{}\n",
+                example.krate, example.version, example.location, example.message
+            ));
+            if let Some(note) = example.note {
+                out.push_str(&format!("\n> {}\n", note));
+            }
+            if let Some(snippet) = example.snippet {
+                out.push_str(&format!("\n```text\n{}```\n", snippet));
+            }
+        }
+    }
+    out
+}
+
+fn rate(rate: Option<f64>) -> String {
+    rate.map_or_else(|| "-".to_string(), |rate| format!("{:.0}%", rate * 100.0))
+}
+
+/// Writes the report to `output`, or prints it without one.
+pub fn write(report: String, output: Option<&Path>) -> Result<(), String> {
+    match output {
+        Some(path) => publish::write(path, report)
+            .map_err(|e| format!("couldn't write {}: {}", path.display(), e)),
+        None => {
+            print!("{}", report);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! `--mode full`, a lightweight crater for clippy lints: it checks the most downloaded crates of
+//! crates.io, several thousand by default, and writes a regression report of the whole run
+//! against `--baseline` to `logs/full_regressions.md`.
+//!
+//! A run of that size takes hours, so it is checked in batches that `--resume` continues after
+//! an interruption, and the result cache is used unless `--result-cache` points it elsewhere. It
+//! can be spread over CI jobs with `--shard` or over machines with `--distributed`, in which case
+//! the report is written once the shards are aggregated.
+
+use crate::{
+    diff::Diff,
+    gzip,
+    lints::LintIndex,
+    log::{Ice, Log, Warning},
+    publish,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+pub const JOB_NAME: &str = "full";
+pub const REPORT_PATH: &str = "logs/full_regressions.md";
+/// Batch size of full runs without `--batch-size`
+pub const BATCH_SIZE: usize = 250;
+/// Number of crates listed per lint, the rest are only counted
+const MAX_CRATES: usize = 20;
+
+#[derive(Debug, Default)]
+struct LintChange<'a> {
+    before: usize,
+    after: usize,
+    /// New warnings per crate
+    added: BTreeMap<&'a str, usize>,
+    /// Removed warnings per crate
+    removed: BTreeMap<&'a str, usize>,
+}
+
+/// Compares the log of the full run with the one in `baseline`, both restricted to the warnings
+/// `includes` keeps, and writes the changes per lint and the new ICEs to the report.
+pub fn report(
+    baseline: &Path,
+    lints: &LintIndex,
+    includes: impl Fn(&Warning) -> bool,
+) -> Result<(), String> {
+    let log_name = format!("{}_logs.txt", JOB_NAME);
+    let old = Log::load(gzip::find(baseline.join(&log_name)))?;
+    let new = Log::load(gzip::find(Path::new("logs").join(&log_name)))?;
+    let kept = |log: &Log| -> Vec<Warning> {
+        log.warnings
+            .iter()
+            .filter(|warning| includes(warning))
+            .cloned()
+            .collect()
+    };
+    let (old_warnings, new_warnings) = (kept(&old), kept(&new));
+    let diff = Diff::new(&old_warnings, &new_warnings);
+
+    let mut changes = BTreeMap::<&str, LintChange<'_>>::new();
+    for warning in &old_warnings {
+        changes.entry(&warning.lint).or_default().before += 1;
+    }
+    for warning in &new_warnings {
+        changes.entry(&warning.lint).or_default().after += 1;
+    }
+    for warning in &diff.added {
+        let change = changes.entry(&warning.lint).or_default();
+        *change.added.entry(&warning.krate).or_default() += 1;
+    }
+    for warning in &diff.removed {
+        let change = changes.entry(&warning.lint).or_default();
+        *change.removed.entry(&warning.krate).or_default() += 1;
+    }
+    let mut changed: Vec<(&str, LintChange<'_>)> = changes
+        .into_iter()
+        .filter(|(_, change)| !change.added.is_empty() || !change.removed.is_empty())
+        .collect();
+    // The lints that regressed in the most crates come first.
+    changed.sort_by(|(_, a), (_, b)| {
+        (b.added.len(), b.removed.len()).cmp(&(a.added.len(), a.removed.len()))
+    });
+    let crates: BTreeSet<&str> = diff
+        .added
+        .iter()
+        .chain(&diff.removed)
+        .map(|warning| warning.krate.as_str())
+        .collect();
+    let new_ices: Vec<&Ice> = new
+        .ices
+        .iter()
+        .filter(|ice| {
+            !old.ices
+                .iter()
+                .any(|old| old.krate == ice.krate && old.message == ice.message)
+        })
+        .collect();
+
+    let summary = format!(
+        "{} new and {} removed warning(s) of {} lint(s) in {} crate(s), {} new ICE(s)",
+        diff.added.len(),
+        diff.removed.len(),
+        changed.len(),
+        crates.len(),
+        new_ices.len()
+    );
+    let mut text = format!(
+        "# Full run regression report\n\nAgainst `{}`, {} → {}.\n\n{}.\n",
+        baseline.display(),
+        old.clippy_version,
+        new.clippy_version,
+        summary
+    );
+    if !changed.is_empty() {
+        text.push_str(
+            "\n## Lints\n\n| lint | group | before | after | crates with new warnings | \
+             crates with removed warnings |\n| --- | --- | --- | --- | --- | --- |\n",
+        );
+        for (lint, change) in &changed {
+            text.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} |\n",
+                lints.markdown_link(lint),
+                lints.group(lint),
+                change.before,
+                change.after,
+                change.added.len(),
+                change.removed.len()
+            ));
+        }
+    }
+    for (lint, change) in changed
+        .iter()
+        .filter(|(_, change)| !change.added.is_empty())
+    {
+        text.push_str(&format!("\n## New `{}` warnings\n\n", lint));
+        let mut crates: Vec<_> = change.added.iter().collect();
+        crates.sort_by(|a, b| b.1.cmp(a.1));
+        for (krate, count) in crates.iter().take(MAX_CRATES) {
+            text.push_str(&format!("- {}: {}\n", krate, count));
+        }
+        if crates.len() > MAX_CRATES {
+            text.push_str(&format!(
+                "- and {} more crate(s)\n",
+                crates.len() - MAX_CRATES
+            ));
+        }
+    }
+    if !new_ices.is_empty() {
+        text.push_str("\n## New ICEs\n\n");
+        for ice in &new_ices {
+            text.push_str(&format!(
+                "- {}: {}\n",
+                ice.krate.as_deref().unwrap_or("unknown crate"),
+                ice.message
+            ));
+        }
+    }
+    publish::write(Path::new(REPORT_PATH), text)
+        .map_err(|e| format!("couldn't write {}: {}", REPORT_PATH, e))?;
+    println!("[{}] {}, see {}", JOB_NAME, summary, REPORT_PATH);
+    Ok(())
+}
@@ -0,0 +1,135 @@
+//! Commit statuses on GitHub, so that repositories that don't use the Checks API see the outcome
+//! of every job on the clippy commit that was tested.
+
+use crate::{failure::Failure, log::Log};
+use serde::Serialize;
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Environment variable holding the token the statuses are set with.
+const TOKEN_ENV: &str = "GITHUB_TOKEN";
+/// GitHub rejects longer descriptions.
+const MAX_DESCRIPTION: usize = 140;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    Pending,
+    Success,
+    Failure,
+    Error,
+}
+
+/// Sets the statuses `lintcheck/<job>` on the commit `sha` of the GitHub repository `repo`,
+/// e.g. "rust-lang/rust-clippy".
+#[derive(Debug)]
+pub struct Statuses {
+    repo: String,
+    sha: String,
+    /// The report the statuses link to
+    target_url: Option<String>,
+    token: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Status<'a> {
+    state: State,
+    context: String,
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_url: Option<&'a str>,
+}
+
+impl Statuses {
+    pub fn new(repo: &str, sha: &str, target_url: Option<&str>) -> Result<Self, String> {
+        let token = std::env::var(TOKEN_ENV)
+            .map_err(|_| format!("setting commit statuses requires a token in {}", TOKEN_ENV))?;
+        Ok(Self {
+            repo: repo.to_string(),
+            sha: sha.to_string(),
+            target_url: target_url.map(str::to_string),
+            token,
+        })
+    }
+
+    pub fn set(&self, job: &str, state: State, description: &str) -> Result<(), String> {
+        let mut description = description.to_string();
+        if description.len() > MAX_DESCRIPTION {
+            let mut end = MAX_DESCRIPTION - 3;
+            while !description.is_char_boundary(end) {
+                end -= 1;
+            }
+            description.truncate(end);
+            description.push_str("...");
+        }
+        let status = Status {
+            state,
+            context: format!("lintcheck/{}", job),
+            description,
+            target_url: self.target_url.as_deref(),
+        };
+        let url = format!(
+            "https://api.github.com/repos/{}/statuses/{}",
+            self.repo, self.sha
+        );
+        // The token is passed on stdin in a curl config, so that it doesn't show up in the
+        // process list.
+        let mut config = format!(
+            "header = \"Authorization: Bearer {}\"\n",
+            self.token.replace('\\', "\\\\").replace('"', "\\\"")
+        );
+        let json = serde_json::to_string(&status).expect("statuses are serializable");
+        config.push_str(&format!(
+            "data = \"{}\"\n",
+            json.replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+        let mut child = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--location"])
+            .args(["--header", "Accept: application/vnd.github+json"])
+            .args(["--config", "-"])
+            .arg(&url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("couldn't execute curl: {}", e))?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(config.as_bytes())
+            .map_err(|e| format!("couldn't pass the request to curl: {}", e))?;
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("couldn't wait for curl: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "setting the status of {} on {} failed: {}",
+                job,
+                self.sha,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The state and description of the status of a job that found `failures` and wrote `log`.
+pub fn outcome(log: Option<&Log>, failures: &[Failure]) -> (State, String) {
+    let mut description = match log {
+        Some(log) => format!(
+            "{} warning(s) from {} lint(s), {} ICE(s)",
+            log.warnings.len(),
+            log.lint_counts().len(),
+            log.ices.len()
+        ),
+        None => "no log".to_string(),
+    };
+    if failures.is_empty() {
+        return (State::Success, description);
+    }
+    description.push_str(&format!(", {} failure(s)", failures.len()));
+    (State::Failure, description)
+}
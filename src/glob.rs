@@ -0,0 +1,86 @@
+//! Shell-style globs over paths, where `*` matches any number and `?` a single character of a
+//! file name, but neither matches a `/` or the leading `.` of a hidden file.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// The paths that `pattern` matches, sorted so that they don't depend on the order of the file
+/// system. Components without wildcards don't need to exist to match.
+pub fn expand(pattern: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut paths = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let name = match component {
+            Component::Normal(name) => name.to_string_lossy(),
+            _ => {
+                for path in &mut paths {
+                    path.push(component);
+                }
+                continue;
+            }
+        };
+        if !name.contains(['*', '?']) {
+            for path in &mut paths {
+                path.push(&*name);
+            }
+            continue;
+        }
+        let mut matched = Vec::new();
+        for dir in &paths {
+            let read_dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            let entries = match fs::read_dir(read_dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries {
+                let entry =
+                    entry.map_err(|e| format!("couldn't read {}: {}", read_dir.display(), e))?;
+                let file_name = entry.file_name();
+                if matches(&name, &file_name.to_string_lossy()) {
+                    matched.push(dir.join(file_name));
+                }
+            }
+        }
+        paths = matched;
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Whether the file name `name` matches the glob `pattern`.
+fn matches(pattern: &str, name: &str) -> bool {
+    if name.starts_with('.') && !pattern.starts_with('.') {
+        return false;
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // Backtracks to the last `*`, letting it match one more character, on mismatches.
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
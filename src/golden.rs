@@ -0,0 +1,114 @@
+//! Golden files with the expected parsed output of a config that must pass, checked in as
+//! `config/<job>.golden.json`. A run compares its log with the golden file and `--bless`
+//! replaces the golden file with the log of the run.
+
+use crate::{
+    log::{Log, Warning},
+    publish,
+    structural_diff::Section,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Golden {
+    /// The warnings as lintcheck writes them, sorted
+    warnings: BTreeSet<String>,
+    /// The ICEs as `crate: message`, sorted
+    ices: BTreeSet<String>,
+    /// Whether the log ended with the ICE section
+    complete: bool,
+}
+
+impl Golden {
+    /// The golden file of the job `job`.
+    pub fn path(job: &str) -> PathBuf {
+        PathBuf::from(format!("config/{}.golden.json", job))
+    }
+
+    pub fn of(log: &Log) -> Self {
+        Self {
+            warnings: log.warnings.iter().map(ToString::to_string).collect(),
+            ices: log
+                .ices
+                .iter()
+                .map(|ice| {
+                    format!(
+                        "{}: {}",
+                        ice.krate.as_deref().unwrap_or("unknown"),
+                        ice.message
+                    )
+                })
+                .collect(),
+            complete: log.complete,
+        }
+    }
+
+    /// Loads the golden file at `path`, `None` if there is none.
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map(Some)
+                .map_err(|e| format!("invalid golden file {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("couldn't read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).expect("golden files are serializable");
+        publish::write(path, json + "\n")
+            .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+    }
+
+    /// What `self` lacks compared to `expected` and what it has in addition, with the warnings
+    /// grouped by their crate, see `structural_diff`.
+    pub fn diff(&self, expected: &Self) -> Vec<Section> {
+        let mut sections = Vec::new();
+        if self.complete != expected.complete {
+            sections.push(Section {
+                title: "complete".to_string(),
+                missing: vec![expected.complete.to_string()],
+                unexpected: vec![self.complete.to_string()],
+            });
+        }
+        // The warnings are grouped by their crate, whose name and version are left out of them.
+        let split = |warning: &String| match Warning::parse(warning) {
+            Some(parsed) => {
+                let prefix = format!("{}-{}/", parsed.krate, parsed.version);
+                let entry = warning.strip_prefix(&prefix).unwrap_or(warning);
+                (
+                    format!("{} {}", parsed.krate, parsed.version),
+                    entry.to_string(),
+                )
+            }
+            None => ("unparsed warnings".to_string(), warning.clone()),
+        };
+        let mut by_crate = BTreeMap::<String, Section>::new();
+        for missing in expected.warnings.difference(&self.warnings) {
+            let (title, entry) = split(missing);
+            by_crate.entry(title).or_default().missing.push(entry);
+        }
+        for added in self.warnings.difference(&expected.warnings) {
+            let (title, entry) = split(added);
+            by_crate.entry(title).or_default().unexpected.push(entry);
+        }
+        for (title, section) in &mut by_crate {
+            section.title = title.clone();
+        }
+        sections.extend(by_crate.into_values());
+        let ices = Section {
+            title: "ICEs".to_string(),
+            missing: expected.ices.difference(&self.ices).cloned().collect(),
+            unexpected: self.ices.difference(&expected.ices).cloned().collect(),
+        };
+        if !ices.missing.is_empty() || !ices.unexpected.is_empty() {
+            sections.push(ices);
+        }
+        sections
+    }
+}
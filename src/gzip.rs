@@ -0,0 +1,118 @@
+//! Transparent gzip compression of logs, based on the `.gz` extension, using the `gzip` binary.
+
+use crate::publish;
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdout, Command, Stdio},
+};
+
+pub fn is_gzip(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "gz")
+}
+
+/// `path` with `.gz` appended.
+pub fn gz_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut path = path.as_ref().as_os_str().to_owned();
+    path.push(".gz");
+    PathBuf::from(path)
+}
+
+/// The first of `path` and `path.gz` that exists, or `path` if neither does.
+pub fn find(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    let compressed = gz_path(path);
+    if !path.exists() && compressed.exists() {
+        compressed
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Opens `path` for reading, decompressing it if it's gzipped.
+pub fn open(path: &Path) -> io::Result<Box<dyn BufRead>> {
+    if !is_gzip(path) {
+        return Ok(Box::new(BufReader::new(File::open(path)?)));
+    }
+    // Open the file here, so that a missing file is reported like for uncompressed files.
+    let file = File::open(path)?;
+    let mut child = Command::new("gzip")
+        .arg("--decompress")
+        .arg("--stdout")
+        .stdin(file)
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    Ok(Box::new(BufReader::new(Decompressed { child, stdout })))
+}
+
+/// The output of `gzip --decompress`, which fails at the end if gzip did.
+struct Decompressed {
+    child: Child,
+    stdout: ChildStdout,
+}
+
+impl Read for Decompressed {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.stdout.read(buf)?;
+        if read == 0 && !buf.is_empty() {
+            let status = self.child.wait()?;
+            if !status.success() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("gzip exited with {}", status),
+                ));
+            }
+        }
+        Ok(read)
+    }
+}
+
+impl Drop for Decompressed {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Atomically replaces `path` with `contents`, compressed if `path` ends with `.gz`.
+pub fn write(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    if !is_gzip(path) {
+        return publish::write(path, contents);
+    }
+    let staged = publish::staged_path(path);
+    let mut child = Command::new("gzip")
+        .arg("--stdout")
+        .stdin(Stdio::piped())
+        .stdout(File::create(&staged)?)
+        .spawn()?;
+    let written = child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(contents.as_ref());
+    let status = child.wait()?;
+    written?;
+    if !status.success() {
+        return Err(io::Error::other(format!("gzip exited with {}", status)));
+    }
+    fs::rename(&staged, path)
+}
+
+/// Replaces the file at `path` by `path.gz`, returning the new path.
+pub fn compress(path: &Path) -> io::Result<PathBuf> {
+    let compressed = gz_path(path);
+    let staged = publish::staged_path(&compressed);
+    let status = Command::new("gzip")
+        .arg("--stdout")
+        .stdin(File::open(path)?)
+        .stdout(File::create(&staged)?)
+        .status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("gzip exited with {}", status)));
+    }
+    fs::rename(&staged, &compressed)?;
+    fs::remove_file(path)?;
+    Ok(compressed)
+}
@@ -0,0 +1,404 @@
+//! History of the warning counts of previous runs, used to spot lints whose counts creep up
+//! slowly over many runs, where no single run changes enough to fail against a baseline.
+//!
+//! It also records the churn of every lint, how many of its findings appeared and disappeared
+//! from one run to the next. Lints with a high churn over the last runs are flagged: findings
+//! that come and go while the crates stay the same are typically caused by unstable spans or
+//! nondeterminism in the lint implementation. Only the findings of the last run of every job
+//! are kept, as hashes, to compare the next run with.
+//!
+//! Finally it records how long linting every crate took the last two times it was linted, so
+//! that batched runs can check the longest crates first and the slowest crates of a run can be
+//! compared with their previous duration.
+//!
+//! And it records the crates that lintcheck couldn't check, because they didn't download or
+//! compile, in how many consecutive runs of a job, so that crates that stay broken can be
+//! removed from the corpus or bumped to a release that works.
+
+use crate::{fingerprint, lints::LintIndex, log::Log, publish, summary, triage};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Where the history is stored between runs.
+pub const HISTORY_PATH: &str = "logs/history.json";
+/// Number of runs kept per job, older ones are dropped.
+const MAX_RUNS: usize = 200;
+/// Churn per warning of a lint, on average over the runs, above which the lint is flagged.
+const HIGH_CHURN: f64 = 0.25;
+/// Number of runs a lint must have churned in to be flagged, so that a single change of the lint
+/// isn't mistaken for instability.
+const MIN_CHURNED_RUNS: usize = 2;
+
+/// The runs of every job, oldest first.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct History {
+    jobs: BTreeMap<String, Vec<Run>>,
+    /// The findings of the last run of every job
+    #[serde(default)]
+    findings: BTreeMap<String, Findings>,
+    /// How long linting every crate took, by crate name
+    #[serde(default)]
+    durations: BTreeMap<String, CrateDuration>,
+    /// The crates that were broken in the last runs of every job, by `<name> <version>`
+    #[serde(default)]
+    broken: BTreeMap<String, BTreeMap<String, Broken>>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct CrateDuration {
+    /// Seconds linting the crate took the last time it was linted
+    pub seconds: f64,
+    /// Seconds it took the time before, if it was linted before
+    pub previous: Option<f64>,
+    /// Seconds since the Unix epoch when it was last linted
+    pub time: u64,
+}
+
+/// A crate that lintcheck couldn't check in the last runs of a job.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Broken {
+    /// Number of consecutive runs it was broken in
+    pub runs: usize,
+    /// Seconds since the Unix epoch when the first of them started
+    pub since: u64,
+    /// Why it was broken in the last of them
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Findings {
+    config_fingerprint: Option<String>,
+    /// Hashes of the findings per lint, including their exact locations
+    lints: BTreeMap<String, BTreeSet<String>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Run {
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub clippy_sha: Option<String>,
+    /// Fingerprint of the effective config, see `fingerprint`. Runs with different fingerprints
+    /// checked different crates or filtered their warnings differently
+    #[serde(default)]
+    pub config_fingerprint: Option<String>,
+    /// Number of warnings per lint
+    pub lints: BTreeMap<String, usize>,
+    /// Number of warnings per lint group
+    pub groups: BTreeMap<String, usize>,
+    pub ices: usize,
+    /// Churn per lint compared to the previous run, `None` if there was no previous run with
+    /// the same config fingerprint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub churn: Option<BTreeMap<String, Churn>>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+pub struct Churn {
+    /// Findings that weren't in the previous run
+    pub appeared: usize,
+    /// Findings of the previous run that are gone
+    pub disappeared: usize,
+}
+
+/// A lint whose findings came and went over the last runs.
+#[derive(Debug)]
+pub struct Churning {
+    pub lint: String,
+    pub appeared: usize,
+    pub disappeared: usize,
+    /// Average number of warnings of the lint in the runs
+    pub average: f64,
+}
+
+/// A lint whose count rose over the last runs.
+#[derive(Debug)]
+pub struct Trend {
+    pub lint: String,
+    /// Counts of the lint in the runs, oldest first
+    pub counts: Vec<usize>,
+}
+
+impl History {
+    /// Loads the history from `path`. A missing file is treated as an empty history.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| format!("invalid history {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("couldn't read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).expect("history is serializable");
+        publish::write(path, json + "\n")
+            .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+    }
+
+    /// Appends the counts of `log` as the latest run of `job`.
+    pub fn record(&mut self, job: &str, log: &Log, lints: &LintIndex, clippy_sha: Option<&str>) {
+        let config_fingerprint = log.metadata.get(fingerprint::HEADER_KEY).cloned();
+        let mut findings = Findings {
+            config_fingerprint: config_fingerprint.clone(),
+            lints: BTreeMap::new(),
+        };
+        for warning in &log.warnings {
+            findings
+                .lints
+                .entry(warning.lint.clone())
+                .or_default()
+                .insert(triage::hash([warning.to_string().as_str()]));
+        }
+        let churn = self
+            .findings
+            .get(job)
+            .filter(|previous| previous.config_fingerprint == config_fingerprint)
+            .map(|previous| churn(previous, &findings));
+        self.findings.insert(job.to_string(), findings);
+        let run = Run {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+            clippy_sha: clippy_sha.map(str::to_string),
+            config_fingerprint,
+            lints: log
+                .lint_counts()
+                .into_iter()
+                .map(|(lint, count)| (lint.to_string(), count))
+                .collect(),
+            groups: summary::by_group(&log.warnings, lints)
+                .into_iter()
+                .map(|(group, count)| (group.to_string(), count))
+                .collect(),
+            ices: log.ices.len(),
+            churn,
+        };
+        let runs = self.jobs.entry(job.to_string()).or_default();
+        runs.push(run);
+        let excess = runs.len().saturating_sub(MAX_RUNS);
+        runs.drain(..excess);
+    }
+
+    /// Records how long linting the crates of `durations`, by crate name, took.
+    pub fn record_durations(&mut self, durations: &BTreeMap<String, Duration>) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        for (krate, duration) in durations {
+            let previous = self.durations.get(krate).map(|last| last.seconds);
+            self.durations.insert(
+                krate.clone(),
+                CrateDuration {
+                    seconds: duration.as_secs_f64(),
+                    previous,
+                    time,
+                },
+            );
+        }
+    }
+
+    /// Records which of the `checked` crates of a run of `job`, by `<name> <version>`, were
+    /// `broken` and why, and ends the streaks of the others. Crates that weren't part of the run
+    /// are left as they are.
+    pub fn record_broken(
+        &mut self,
+        job: &str,
+        checked: impl IntoIterator<Item = String>,
+        broken: &BTreeMap<String, String>,
+    ) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let streaks = self.broken.entry(job.to_string()).or_default();
+        for krate in checked {
+            match broken.get(&krate) {
+                Some(reason) => {
+                    let streak = streaks.entry(krate).or_insert_with(|| Broken {
+                        runs: 0,
+                        since: time,
+                        reason: String::new(),
+                    });
+                    streak.runs += 1;
+                    streak.reason = reason.clone();
+                }
+                None => {
+                    streaks.remove(&krate);
+                }
+            }
+        }
+    }
+
+    /// The crates of `job`, by `<name> <version>`, that were broken in at least the last `runs`
+    /// runs that checked them.
+    pub fn broken(&self, job: &str, runs: usize) -> Vec<(&str, &Broken)> {
+        self.broken.get(job).map_or_else(Vec::new, |streaks| {
+            streaks
+                .iter()
+                .filter(|(_, broken)| broken.runs >= runs)
+                .map(|(krate, broken)| (krate.as_str(), broken))
+                .collect()
+        })
+    }
+
+    /// Seconds linting `krate` took the last time it was linted, `None` if it wasn't yet.
+    pub fn duration(&self, krate: &str) -> Option<f64> {
+        self.durations.get(krate).map(|duration| duration.seconds)
+    }
+
+    /// The durations of those of `crates` that were linted at or after `since`, in seconds since
+    /// the Unix epoch, slowest first.
+    pub fn durations_since<'a>(
+        &self,
+        crates: impl IntoIterator<Item = &'a str>,
+        since: u64,
+    ) -> Vec<(&'a str, CrateDuration)> {
+        let mut durations: Vec<_> = crates
+            .into_iter()
+            .filter_map(|krate| Some((krate, *self.durations.get(krate)?)))
+            .filter(|(_, duration)| duration.time >= since)
+            .collect();
+        durations.sort_by(|(_, a), (_, b)| b.seconds.total_cmp(&a.seconds));
+        durations
+    }
+
+    /// The recorded runs of `job`, oldest first.
+    pub fn runs(&self, job: &str) -> &[Run] {
+        self.jobs.get(job).map_or(&[], Vec::as_slice)
+    }
+
+    /// Lints of `job` whose count never decreased over the last `runs` runs and increased from
+    /// more than half of them to the next. Only the trailing runs with the config fingerprint of
+    /// the last one are comparable, nothing is reported until there are enough of them.
+    pub fn trends(&self, job: &str, runs: usize) -> Vec<Trend> {
+        let recorded = self.runs(job);
+        let comparable = match recorded.last() {
+            Some(last) => recorded
+                .iter()
+                .rev()
+                .take_while(|run| run.config_fingerprint == last.config_fingerprint)
+                .count(),
+            None => 0,
+        };
+        if runs < 2 || comparable < runs {
+            return Vec::new();
+        }
+        let window = &recorded[recorded.len() - runs..];
+        let mut lints: Vec<_> = window.iter().flat_map(|run| run.lints.keys()).collect();
+        lints.sort();
+        lints.dedup();
+        lints
+            .into_iter()
+            .filter_map(|lint| {
+                let counts: Vec<usize> = window
+                    .iter()
+                    .map(|run| run.lints.get(lint).copied().unwrap_or_default())
+                    .collect();
+                let steps = counts.windows(2);
+                let increases = steps.clone().filter(|step| step[1] > step[0]).count();
+                let steady = steps.clone().all(|step| step[1] >= step[0]);
+                (steady && increases * 2 > steps.len()).then(|| Trend {
+                    lint: lint.clone(),
+                    counts,
+                })
+            })
+            .collect()
+    }
+
+    /// Lints of `job` whose findings appeared or disappeared in at least two of the last `runs`
+    /// runs, and more than `HIGH_CHURN` times their average count in total, highest churn first.
+    /// Only runs that are comparable to the run before them count.
+    pub fn churning(&self, job: &str, runs: usize) -> Vec<Churning> {
+        let recorded = self.runs(job);
+        let window = &recorded[recorded.len().saturating_sub(runs)..];
+        let mut totals = BTreeMap::<&str, (Churn, usize)>::new();
+        for churn in window.iter().filter_map(|run| run.churn.as_ref()) {
+            for (lint, churn) in churn {
+                let (total, churned) = totals.entry(lint).or_default();
+                total.appeared += churn.appeared;
+                total.disappeared += churn.disappeared;
+                *churned += 1;
+            }
+        }
+        let mut churning: Vec<Churning> = totals
+            .into_iter()
+            .filter(|(_, (_, churned))| *churned >= MIN_CHURNED_RUNS)
+            .map(|(lint, (total, _))| Churning {
+                lint: lint.to_string(),
+                appeared: total.appeared,
+                disappeared: total.disappeared,
+                average: window
+                    .iter()
+                    .map(|run| run.lints.get(lint).copied().unwrap_or_default())
+                    .sum::<usize>() as f64
+                    / window.len() as f64,
+            })
+            .filter(|churning| churning.rate() > HIGH_CHURN)
+            .collect();
+        churning.sort_by(|a, b| b.rate().total_cmp(&a.rate()));
+        churning
+    }
+}
+
+impl Churning {
+    /// Findings that appeared or disappeared per warning of the lint.
+    pub fn rate(&self) -> f64 {
+        (self.appeared + self.disappeared) as f64 / self.average.max(1.0)
+    }
+}
+
+/// The churn per lint from the `previous` findings to the `current` ones. Lints without any are
+/// left out.
+fn churn(previous: &Findings, current: &Findings) -> BTreeMap<String, Churn> {
+    let empty = BTreeSet::new();
+    let lints: BTreeSet<&String> = previous.lints.keys().chain(current.lints.keys()).collect();
+    lints
+        .into_iter()
+        .filter_map(|lint| {
+            let before = previous.lints.get(lint).unwrap_or(&empty);
+            let after = current.lints.get(lint).unwrap_or(&empty);
+            let churn = Churn {
+                appeared: after.difference(before).count(),
+                disappeared: before.difference(after).count(),
+            };
+            (churn.appeared + churn.disappeared > 0).then(|| (lint.clone(), churn))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{History, Run};
+
+    fn run(fingerprint: &str, count: usize) -> Run {
+        Run {
+            time: 0,
+            clippy_sha: None,
+            config_fingerprint: Some(fingerprint.to_string()),
+            lints: std::iter::once(("clippy::foo".to_string(), count)).collect(),
+            groups: Default::default(),
+            ices: 0,
+            churn: None,
+        }
+    }
+
+    #[test]
+    fn trends_of_comparable_runs() {
+        let mut history = History::default();
+        let runs = history.jobs.entry("job".to_string()).or_default();
+        // The corpus grew between the second and the third run.
+        runs.extend([run("a", 10), run("a", 11), run("b", 20), run("b", 21)]);
+        assert!(history.trends("job", 3).is_empty());
+        assert!(history.trends("job", 4).is_empty());
+
+        let runs = history.jobs.get_mut("job").unwrap();
+        runs.push(run("b", 22));
+        let trends = history.trends("job", 3);
+        assert_eq!(trends.len(), 1);
+        assert_eq!(trends[0].counts, [20, 21, 22]);
+    }
+}
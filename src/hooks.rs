@@ -0,0 +1,63 @@
+//! Commands of the `[hooks]` of the wrapper config that are run around every run, so that steps
+//! specific to a site, like checking the VPN, syncing the artifacts or paging someone, don't
+//! need a fork of the wrapper.
+//!
+//! Every command is run with `sh -c` in the current directory. `pre-run` commands run before
+//! the run and a failing one aborts it. `post-run` commands run after every run and `on-failure`
+//! commands after them if the run failed. They get the path of the JSON summary of the run, see
+//! `--summary-out`, in `CLIPPY_LINTCHECK_SUMMARY` and its exit code in
+//! `CLIPPY_LINTCHECK_EXIT_CODE`. The summary is written to `logs/summary.json` if no other path
+//! is given.
+
+use serde::Deserialize;
+use std::{path::Path, process::Command};
+
+/// Variable holding the path of the summary of the run.
+const SUMMARY_ENV: &str = "CLIPPY_LINTCHECK_SUMMARY";
+/// Variable holding the exit code of the run, not set for `pre-run` commands.
+const EXIT_CODE_ENV: &str = "CLIPPY_LINTCHECK_EXIT_CODE";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Hooks {
+    #[serde(default)]
+    pub pre_run: Vec<String>,
+    #[serde(default)]
+    pub post_run: Vec<String>,
+    #[serde(default)]
+    pub on_failure: Vec<String>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre_run.is_empty() && self.post_run.is_empty() && self.on_failure.is_empty()
+    }
+}
+
+/// Runs the `commands` of the hook `name` one after the other, up to the first one that fails,
+/// with the `summary` path and the `exit_code` of the run in their environment.
+pub fn run(
+    name: &str,
+    commands: &[String],
+    summary: &Path,
+    exit_code: Option<i32>,
+) -> Result<(), String> {
+    for command in commands {
+        println!("running {} hook: {}", name, command);
+        let mut sh = Command::new("sh");
+        sh.arg("-c").arg(command).env(SUMMARY_ENV, summary);
+        if let Some(code) = exit_code {
+            sh.env(EXIT_CODE_ENV, code.to_string());
+        }
+        let status = sh
+            .status()
+            .map_err(|e| format!("couldn't run {} hook `{}`: {}", name, command, e))?;
+        if !status.success() {
+            return Err(format!(
+                "{} hook `{}` exited with {}",
+                name, command, status
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! Uploading the ICEs of a run to a shared endpoint with `--report-ices <url>`, so that the ICEs
+//! found by all runners end up in one database.
+//!
+//! The endpoint receives a `POST` with a JSON array of the ICE records of the run.
+
+use crate::{crates, log::Log, metadata::RunMetadata};
+use serde::Serialize;
+use std::{
+    collections::BTreeSet,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+#[derive(Debug, Serialize)]
+pub struct IceRecord {
+    /// SHA-256 of the first line of the ICE with every number replaced by `N`, so that the same
+    /// ICE has the same fingerprint across clippy versions that moved its line
+    pub fingerprint: String,
+    #[serde(rename = "crate")]
+    pub krate: Option<String>,
+    /// `rustc --version` of the toolchain of the checkout
+    pub toolchain: Option<String>,
+    pub clippy_sha: Option<String>,
+    /// The message of the ICE as lintcheck logged it
+    pub backtrace: String,
+}
+
+/// The ICEs of `logs`, with each ICE of a crate only once even if several jobs ran into it.
+pub fn records<'a>(
+    logs: impl IntoIterator<Item = &'a Log>,
+    metadata: &RunMetadata,
+) -> Result<Vec<IceRecord>, String> {
+    let mut seen = BTreeSet::new();
+    let mut records = Vec::new();
+    for ice in logs.into_iter().flat_map(|log| &log.ices) {
+        let fingerprint = fingerprint(&ice.message)?;
+        if !seen.insert((fingerprint.clone(), ice.krate.clone())) {
+            continue;
+        }
+        records.push(IceRecord {
+            fingerprint,
+            krate: ice.krate.clone(),
+            toolchain: metadata.rustc_version.clone(),
+            clippy_sha: metadata.clippy_sha.clone(),
+            backtrace: ice.message.clone(),
+        });
+    }
+    Ok(records)
+}
+
+fn fingerprint(message: &str) -> Result<String, String> {
+    let first_line = message.lines().next().unwrap_or_default();
+    let mut normalized = String::new();
+    for c in first_line.chars() {
+        if !c.is_ascii_digit() {
+            normalized.push(c);
+        } else if !normalized.ends_with('N') {
+            normalized.push('N');
+        }
+    }
+    crates::sha256sum(normalized.as_bytes())
+}
+
+/// Posts `records` to `url`.
+pub fn upload(url: &str, records: &[IceRecord]) -> Result<(), String> {
+    let json = serde_json::to_string(records).expect("ICE records are serializable");
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location"])
+        .args(["--header", "Content-Type: application/json"])
+        .args(["--data-binary", "@-"])
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("couldn't execute curl: {}", e))?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(json.as_bytes())
+        .map_err(|e| format!("couldn't pass the ICEs to curl: {}", e))?;
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("couldn't wait for curl: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "uploading the ICEs to {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
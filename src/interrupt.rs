@@ -0,0 +1,97 @@
+//! Graceful handling of SIGINT and SIGTERM. An interruption stops the run from starting new
+//! work and terminates the running lintcheck processes, so that the jobs can publish what they
+//! have as partial logs instead of leaving orphaned processes behind.
+//!
+//! Lintcheck runs in its own process group, so that the whole tree of cargo, lintcheck and the
+//! clippy drivers is terminated, also when only this process received the signal. A second
+//! signal kills the run right away.
+
+use std::{
+    process::Child,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, Once, PoisonError,
+    },
+    thread,
+    time::Duration,
+};
+
+/// How often the process groups are checked against an interruption.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static INSTALLED: Once = Once::new();
+/// The process groups of the running lintcheck processes.
+static GROUPS: Mutex<Vec<libc::pid_t>> = Mutex::new(Vec::new());
+
+extern "C" fn handle(signal: libc::c_int) {
+    REQUESTED.store(true, Ordering::SeqCst);
+    // SAFETY: `signal` is async-signal-safe. Restoring the default makes a second signal kill
+    // the run.
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+    }
+}
+
+/// Installs the handlers for SIGINT and SIGTERM and starts the thread that terminates the
+/// process groups of `track` once a signal arrived. Only the first call has an effect.
+pub fn install() {
+    INSTALLED.call_once(install_handlers);
+}
+
+fn install_handlers() {
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: `handle` only touches an atomic and calls `signal`, which are
+        // async-signal-safe.
+        unsafe {
+            libc::signal(
+                signal,
+                handle as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            );
+        }
+    }
+    thread::spawn(|| loop {
+        if requested() {
+            for group in GROUPS
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .drain(..)
+            {
+                // SAFETY: `kill` has no memory safety requirements. A negative pid signals the
+                // process group.
+                unsafe {
+                    libc::kill(-group, libc::SIGTERM);
+                }
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    });
+}
+
+/// Whether the run was interrupted.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Terminates the process group `child` leads on an interruption, until the guard is dropped.
+/// The child must have been spawned with `process_group(0)`.
+pub fn track(child: &Child) -> Tracked {
+    let group = child.id() as libc::pid_t;
+    GROUPS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(group);
+    Tracked(group)
+}
+
+#[derive(Debug)]
+pub struct Tracked(libc::pid_t);
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        GROUPS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|&group| group != self.0);
+    }
+}
@@ -0,0 +1,1469 @@
+use crate::{
+    build_failures::{self, BuildFailure},
+    ci,
+    clippy_conf::ClippyConf,
+    coverage::{self, Coverage},
+    crates::{self, CratesConfig, Krate},
+    crates_io,
+    diff::{self, Diff},
+    events::{self, Event},
+    failure::{self, Failure, FailureKind},
+    features, fingerprint, fix,
+    golden::Golden,
+    gzip,
+    history::{self, History},
+    interrupt,
+    lint_policy::LintPolicy,
+    lintcheck::{self, Skipped, SoftErrors},
+    lints::LintIndex,
+    lock,
+    log::{self, Format, Log, LogFormat, Warning},
+    metadata::RunMetadata,
+    parquet,
+    progress::{self, Progress},
+    publish, report,
+    result_cache::ResultCache,
+    results,
+    run_lock::{self, RunLock},
+    sample::Sample,
+    sandbox,
+    shard::Shard,
+    slowest, snippet, stability, stable,
+    structural_diff::{self, Section},
+    summary,
+    suppress::{self, Rule},
+    triage::{self, Verdicts},
+    wrapper_config::{Checkout, CrateExpectations, IncreaseLimit, LevelBudgets, Registries},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tempfile::NamedTempFile;
+
+/// Number of warnings listed in the failure of a crate of the passes config.
+const MAX_LISTED_WARNINGS: usize = 10;
+/// Number of lines of a log without ICE section shown in its failure.
+const LOG_TAIL: usize = 10;
+
+/// What to do with the remaining work once something failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// Finish the checks of the failing configuration, then stop.
+    Default,
+    /// Stop at the very first failure.
+    FailFast,
+    /// Run everything and report all failures at the end.
+    KeepGoing,
+}
+
+impl Policy {
+    pub fn new(fail_fast: bool, keep_going: bool) -> Self {
+        if fail_fast {
+            Self::FailFast
+        } else if keep_going {
+            Self::KeepGoing
+        } else {
+            Self::Default
+        }
+    }
+
+    /// Returns whether the job has to stop because of `failures`, keeping only the first
+    /// failure if so.
+    fn stops(self, failures: &mut Vec<Failure>) -> bool {
+        if self == Self::FailFast && !failures.is_empty() {
+            failures.truncate(1);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// What a configuration is expected to produce.
+#[derive(Debug, Clone, Copy)]
+pub enum Expect {
+    /// No clippy warnings and no ICEs.
+    Passes,
+    /// Any number of clippy warnings, but no ICEs.
+    Integration,
+}
+
+/// A single lintcheck run over one configuration file.
+pub struct Job {
+    /// Name of the log file in `logs/`, without the `_logs.txt` suffix
+    pub name: String,
+    /// Absolute path of the configuration
+    pub config: PathBuf,
+    pub expect: Expect,
+    /// Also compare the results against the stable clippy of this toolchain
+    pub stable: Option<String>,
+    /// Lint with this `msrv` in the clippy config
+    pub msrv: Option<String>,
+    /// Keeps generated configurations alive until the job ran
+    _tempfile: Option<NamedTempFile>,
+}
+
+impl Job {
+    pub fn new(name: &str, expect: Expect, registries: &Registries) -> Result<Self, Failure> {
+        Self::from_config(
+            name,
+            &format!("config/{}.toml", name),
+            None,
+            expect,
+            registries,
+        )
+    }
+
+    /// Job over the config at `location`, which is a path or a URL whose content must have the
+    /// SHA-256 `sha256` if given. Downloaded configs, configs that aren't TOML and configs with
+    /// imports or registry crates are resolved into a temporary config, because lintcheck only
+    /// understands local TOML `[crates]` from crates.io, git or paths.
+    pub fn from_config(
+        name: &str,
+        location: &str,
+        sha256: Option<&str>,
+        expect: Expect,
+        registries: &Registries,
+    ) -> Result<Self, Failure> {
+        let error =
+            |message| Failure::new(FailureKind::Environment, name, message).classify_network();
+        if !crates::is_url(location) && crates::is_toml(location) {
+            let path = Path::new(location);
+            if !CratesConfig::load(path).map_err(error)?.needs_resolving() {
+                return Ok(Self {
+                    name: name.to_string(),
+                    config: path
+                        .canonicalize()
+                        .map_err(|e| error(format!("couldn't find {}: {}", path.display(), e)))?,
+                    expect,
+                    stable: None,
+                    msrv: None,
+                    _tempfile: None,
+                });
+            }
+        }
+        let config = CratesConfig::resolve(location, sha256).map_err(error)?;
+        Self::from_crates(name, config, expect, registries)
+    }
+
+    /// Job over the crates added to `config/<name>.toml` since `base_ref` and the crates of
+    /// `extra`.
+    pub fn ci(
+        name: &str,
+        expect: Expect,
+        base_ref: &str,
+        extra: &CratesConfig,
+        registries: &Registries,
+    ) -> Result<Self, Failure> {
+        let name_ci = format!("ci_{}", name);
+        let config = ci::added_crates(name, base_ref, extra)
+            .map_err(|message| Failure::new(FailureKind::Environment, &name_ci, message))?;
+        Self::from_crates(&name_ci, config, expect, registries)
+    }
+
+    /// Job `name` over the `count` most downloaded crates of crates.io at their latest release.
+    pub fn top(name: &str, count: usize, registries: &Registries) -> Result<Self, Failure> {
+        let crates = crates_io::top(count).map_err(|message| {
+            Failure::new(FailureKind::Environment, name, message).classify_network()
+        })?;
+        let config = CratesConfig::from_releases(crates);
+        Self::from_crates(name, config, Expect::Integration, registries)
+    }
+
+    /// Job over a generated config, which is written to a temporary file.
+    fn from_crates(
+        name: &str,
+        mut config: CratesConfig,
+        expect: Expect,
+        registries: &Registries,
+    ) -> Result<Self, Failure> {
+        let error =
+            |message| Failure::new(FailureKind::Environment, name, message).classify_network();
+        config.fetch_registry_crates(registries).map_err(error)?;
+        let mut file =
+            NamedTempFile::new().map_err(|e| error(format!("failed to create tempfile: {}", e)))?;
+        let text = toml::to_string(&config)
+            .map_err(|e| error(format!("couldn't serialize config: {}", e)))?;
+        file.write_all(text.as_bytes())
+            .map_err(|e| error(format!("couldn't write to tempfile: {}", e)))?;
+        Ok(Self {
+            name: name.to_string(),
+            config: file.path().to_path_buf(),
+            expect,
+            stable: None,
+            msrv: None,
+            _tempfile: Some(file),
+        })
+    }
+
+    /// Restricts the job to the crates of `shard`, so that each CI job of a matrix checks a
+    /// part of the config.
+    pub fn with_shard(self, shard: Shard, registries: &Registries) -> Result<Self, Failure> {
+        let config = CratesConfig::load(&self.config)
+            .map_err(|message| Failure::new(FailureKind::Environment, &self.name, message))?;
+        let selected = shard.select(&config);
+        println!(
+            "[{}] shard {} checks {} of {} crate(s)",
+            self.name,
+            shard,
+            selected.crates.len(),
+            config.crates.len()
+        );
+        let job = Self::from_crates(&self.name, selected, self.expect, registries)?;
+        Ok(Self {
+            stable: self.stable,
+            msrv: self.msrv,
+            ..job
+        })
+    }
+
+    /// Restricts the job to a random `sample` of its crates.
+    pub fn with_sample(self, sample: Sample, registries: &Registries) -> Result<Self, Failure> {
+        let config = CratesConfig::load(&self.config)
+            .map_err(|message| Failure::new(FailureKind::Environment, &self.name, message))?;
+        let selected = sample.select(&config);
+        println!(
+            "[{}] samples {} of {} crate(s), check the sample again with {}",
+            self.name,
+            selected.crates.len(),
+            config.crates.len(),
+            sample
+        );
+        let job = Self::from_crates(&self.name, selected, self.expect, registries)?;
+        Ok(Self {
+            stable: self.stable,
+            msrv: self.msrv,
+            ..job
+        })
+    }
+
+    /// Restricts the job to the crates named `names`, so that each CI job of a matrix checks a
+    /// single crate.
+    pub fn with_crates(self, names: &[String], registries: &Registries) -> Result<Self, Failure> {
+        let config = CratesConfig::load(&self.config)
+            .map_err(|message| Failure::new(FailureKind::Environment, &self.name, message))?;
+        let selected = CratesConfig {
+            crates: config
+                .crates
+                .iter()
+                .filter(|(_, source)| names.contains(&source.name))
+                .map(|(key, source)| (key.clone(), source.clone()))
+                .collect(),
+            ..Default::default()
+        };
+        println!(
+            "[{}] checks {} of {} crate(s)",
+            self.name,
+            selected.crates.len(),
+            config.crates.len()
+        );
+        let job = Self::from_crates(&self.name, selected, self.expect, registries)?;
+        Ok(Self {
+            stable: self.stable,
+            msrv: self.msrv,
+            ..job
+        })
+    }
+
+    /// Number of crates the job checks, counting every version of a crate.
+    pub fn crate_count(&self) -> usize {
+        CratesConfig::load(&self.config).map_or(0, |config| config.krates().len())
+    }
+
+    /// Names of the crates the job checks.
+    pub fn crate_names(&self) -> BTreeSet<String> {
+        CratesConfig::load(&self.config).map_or_else(
+            |_| BTreeSet::new(),
+            |config| {
+                config
+                    .crates
+                    .into_values()
+                    .map(|source| source.name)
+                    .collect()
+            },
+        )
+    }
+
+    /// Lints with `msrv` in the clippy config. The log is named after the msrv, so that the
+    /// jobs of several msrvs can be compared.
+    pub fn with_msrv(mut self, msrv: &str) -> Self {
+        self.name = format!("{}_msrv_{}", self.name, msrv);
+        self.msrv = Some(msrv.to_string());
+        self
+    }
+
+    pub fn with_stable(mut self, toolchain: &str) -> Self {
+        self.stable = Some(toolchain.to_string());
+        self
+    }
+
+    /// Runs the job, returning every failure it found. With the `FailFast` policy this returns
+    /// after the first failure.
+    ///
+    /// The log is only published to `logs/` once all checks ran, so that the directory never
+    /// contains a log whose checks are still running.
+    pub fn run(&self, clippy: &Checkout, options: &RunOptions) -> Vec<Failure> {
+        if options.sandbox {
+            if let Err(failure) = self.prefetch(clippy, &options.registries) {
+                return vec![failure];
+            }
+        }
+        let log_path = self.log_path();
+        let staged_path = publish::staged_path(&log_path);
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let (stdout, mut failures) = match self.lintcheck(clippy, options, &staged_path) {
+            Ok(result) => result,
+            Err(failures) => return failures,
+        };
+        if let Err(message) = slowest::report(&self.name, &self.config, started) {
+            failures.push(Failure::new(FailureKind::Environment, &self.name, message));
+        }
+        let mut parsed = match self
+            .write_metadata(&staged_path, options)
+            .and_then(|()| Log::load(&staged_path))
+        {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                failures.push(Failure::new(FailureKind::Environment, &self.name, message));
+                return failures;
+            }
+        };
+        if interrupt::requested() {
+            return self.publish_partial(&staged_path, &log_path, failures);
+        }
+        let suppressed = suppress::apply(&options.suppress, &mut parsed);
+        self.emit_events(clippy, &parsed, &stdout);
+        let unstable = if options.stability_check {
+            stability::check(clippy, self, &parsed, options).unwrap_or_else(|failure| {
+                failures.push(failure);
+                Vec::new()
+            })
+        } else {
+            Vec::new()
+        };
+        failures.extend(self.check_log(clippy, options, &parsed, &suppressed, &stdout, &unstable));
+        if !options.baseline_only {
+            let shared = lock::shared_files();
+            let appended = results::append(
+                &self.name,
+                options.metadata.clippy_sha.as_deref(),
+                &parsed,
+                &failures,
+                options.results_warnings,
+            );
+            drop(shared);
+            if let Err(message) = appended {
+                failures.push(Failure::new(FailureKind::Environment, &self.name, message));
+            }
+        }
+        if let Err(e) = fs::rename(&staged_path, &log_path) {
+            failures.push(Failure::new(
+                FailureKind::Environment,
+                &self.name,
+                format!("couldn't publish {}: {}", log_path, e),
+            ));
+        } else if options.gzip_logs {
+            match gzip::compress(Path::new(&log_path)) {
+                Ok(compressed) => {
+                    for failure in &mut failures {
+                        for log in &mut failure.logs {
+                            if *log == Path::new(&log_path) {
+                                *log = compressed.clone();
+                            }
+                        }
+                    }
+                }
+                Err(e) => failures.push(Failure::new(
+                    FailureKind::Environment,
+                    &self.name,
+                    format!("couldn't compress {}: {}", log_path, e),
+                )),
+            }
+        }
+        if let Some(extension) = options.log_format.extension() {
+            let path = format!("logs/{}_logs.{}", self.name, extension);
+            let mut converted = parsed.convert(options.log_format, &options.lints);
+            if options.snippets && options.log_format == LogFormat::Markdown {
+                converted.push_str(&snippet::section(clippy, &parsed.warnings));
+            }
+            if let Err(e) = publish::write(&path, converted) {
+                failures.push(Failure::new(
+                    FailureKind::Environment,
+                    &self.name,
+                    format!("couldn't write {}: {}", path, e),
+                ));
+            }
+        }
+        if options.format == Format::Parquet {
+            let path = format!("logs/{}_warnings.parquet", self.name);
+            if let Err(message) = parquet::write(&path, &parsed.warnings) {
+                failures.push(Failure::new(FailureKind::Environment, &self.name, message));
+            }
+        }
+        failures
+    }
+
+    /// Runs lintcheck on the config and writes the log to `log`. Returns the stdout of lintcheck
+    /// and the failures that didn't prevent a log.
+    ///
+    /// With a batch size, the crates are split into batches that are checked one after the
+    /// other, each writing `logs/<job>_batch<i>_logs.txt`. Their logs are merged, so that a batch
+    /// failing only loses the results of its own crates, which are then reported as skipped.
+    /// Finished batches are recorded in `logs/<job>_progress.json`, and with `--resume` the
+    /// batches recorded by a previous run are reused instead of being checked again. The crates
+    /// that took the longest in earlier runs are checked first, see `CratesConfig::batches`. With
+    /// a result cache, the crates whose results are cached aren't checked at all, and the others
+    /// are checked like a batch.
+    fn lintcheck(
+        &self,
+        clippy: &Checkout,
+        options: &RunOptions,
+        log: &Path,
+    ) -> Result<(String, Vec<Failure>), Vec<Failure>> {
+        let error = |message| vec![Failure::new(FailureKind::Environment, &self.name, message)];
+        let msrv_conf = self.msrv_conf(options).map_err(error)?;
+        let clippy_conf = msrv_conf.as_ref().or(options.clippy_conf.as_ref());
+        let clippy_conf_dir = clippy_conf.map(|conf| conf.dir.as_path());
+        let store = |config: &CratesConfig, log: &Log, stdout: &str| match &options.result_cache {
+            Some(cache) => cache
+                .store(config, log, stdout, clippy, clippy_conf)
+                .map(drop)
+                .map_err(|message| Failure::new(FailureKind::Environment, &self.name, message)),
+            None => Ok(()),
+        };
+        let progress_path = Progress::path(&self.name);
+        let progress_path = Path::new(&progress_path);
+        let mut progress = Progress::default();
+        let mut config = CratesConfig::load(&self.config).map_err(error)?;
+        if options.batch_size.is_some() {
+            if options.resume {
+                progress = Progress::load(progress_path).map_err(error)?;
+                progress.resume(&self.name, &mut config);
+                let resumed: usize = progress
+                    .batches
+                    .iter()
+                    .map(|batch| batch.config.crates.len())
+                    .sum();
+                println!(
+                    "[{}] resuming: {} crate(s) were checked by a previous run, {} remain",
+                    self.name,
+                    resumed,
+                    config.crates.len()
+                );
+            }
+            // Without --resume, forget the batches of earlier runs, whose logs the new
+            // batches overwrite.
+            progress.save(progress_path).map_err(error)?;
+        }
+        let restored = match &options.result_cache {
+            Some(cache) => Some(cache.restore(&mut config, clippy_conf).map_err(error)?)
+                .filter(|restored| restored.count > 0),
+            None => None,
+        };
+        if let Some(restored) = &restored {
+            println!(
+                "[{}] {} crate(s) restored from the result cache, {} remain",
+                self.name,
+                restored.count,
+                config.crates.len()
+            );
+        }
+        let batches = match options.batch_size {
+            Some(batch_size) => {
+                let history = {
+                    let _shared = lock::shared_files();
+                    History::load(Path::new(history::HISTORY_PATH)).map_err(error)?
+                };
+                config.batches(batch_size, |krate| history.duration(krate))
+            }
+            // The crates that aren't cached are checked as one batch.
+            None if restored.is_some() => config.batches(config.crates.len(), |_| None),
+            None => Vec::new(),
+        };
+        if batches.len() <= 1 && progress.batches.is_empty() && restored.is_none() {
+            let stdout = lintcheck::check(
+                clippy,
+                &self.config,
+                &self.name,
+                clippy_conf_dir,
+                log,
+                options,
+            )
+            .map_err(|failure| vec![failure])?;
+            let stored = match options.result_cache {
+                Some(_) => Log::load(log)
+                    .map_err(|message| Failure::new(FailureKind::Environment, &self.name, message))
+                    .and_then(|parsed| store(&config, &parsed, &stdout)),
+                None => Ok(()),
+            };
+            return Ok((stdout, stored.err().into_iter().collect()));
+        }
+
+        let mut stdout = String::new();
+        let mut failures = Vec::new();
+        let mut merged: Option<Log> = None;
+        let mut add = |log: Log| match &mut merged {
+            Some(merged) => merged.merge(log),
+            None => merged = Some(log),
+        };
+        for batch in &progress.batches {
+            stdout.push_str(&batch.stdout);
+            add(Log::load(progress::batch_log(&self.name, batch.index)).map_err(error)?);
+        }
+        if let Some(restored) = restored {
+            stdout.push_str(&restored.stdout);
+            add(restored.log);
+        }
+        let first = progress.next_index();
+        let count = batches.len();
+        for (i, batch) in batches.into_iter().enumerate() {
+            if interrupt::requested() {
+                failures.push(Failure::new(
+                    FailureKind::Interrupted,
+                    &self.name,
+                    format!("interrupted before batch {}/{}", i + 1, count),
+                ));
+                break;
+            }
+            let index = first + i;
+            let name = format!("{}_batch{}", self.name, index);
+            println!(
+                "[{}] checking batch {}/{} with {} crate(s)",
+                self.name,
+                i + 1,
+                count,
+                batch.crates.len()
+            );
+            let mut file = NamedTempFile::new()
+                .map_err(|e| error(format!("failed to create tempfile: {}", e)))?;
+            let text = toml::to_string(&batch)
+                .map_err(|e| error(format!("couldn't serialize config: {}", e)))?;
+            file.write_all(text.as_bytes())
+                .map_err(|e| error(format!("couldn't write to tempfile: {}", e)))?;
+            let batch_log = progress::batch_log(&self.name, index);
+            let checked = lintcheck::check(
+                clippy,
+                file.path(),
+                &name,
+                clippy_conf_dir,
+                Path::new(&batch_log),
+                options,
+            )
+            .and_then(|batch_stdout| {
+                let log = Log::load(&batch_log)
+                    .map_err(|message| Failure::new(FailureKind::Environment, &name, message))?;
+                Ok((batch_stdout, log))
+            });
+            match checked {
+                Ok((batch_stdout, log)) => {
+                    failures.extend(store(&batch, &log, &batch_stdout).err());
+                    stdout.push_str(&batch_stdout);
+                    add(log);
+                    if options.batch_size.is_some() {
+                        progress.batches.push(progress::Batch {
+                            index,
+                            config: batch,
+                            stdout: batch_stdout,
+                        });
+                        progress.save(progress_path).map_err(error)?;
+                    }
+                }
+                Err(failure) if failure.kind == FailureKind::Interrupted => {
+                    failures.push(failure);
+                    break;
+                }
+                Err(failure) => {
+                    failures.push(failure);
+                    if options.policy.stops(&mut failures) {
+                        return Err(failures);
+                    }
+                }
+            }
+        }
+        let merged = match merged {
+            Some(merged) => merged,
+            None => return Err(failures),
+        };
+        if let Err(e) = fs::write(log, merged.render()) {
+            failures.extend(error(format!("couldn't write {}: {}", log.display(), e)));
+            return Err(failures);
+        }
+        Ok((stdout, failures))
+    }
+
+    /// The clippy config of the run with the msrv of the job, if it has one.
+    pub fn msrv_conf(&self, options: &RunOptions) -> Result<Option<ClippyConf>, String> {
+        self.msrv
+            .as_ref()
+            .map(|msrv| ClippyConf::with_msrv(options.clippy_conf.as_ref(), msrv))
+            .transpose()
+    }
+
+    /// Adds the crates lintcheck linted to `logs/run.lock`.
+    fn record_lock(&self, clippy: &Checkout, stdout: &str) -> Result<(), Failure> {
+        let _shared = lock::shared_files();
+        let error = |message| Failure::new(FailureKind::Environment, &self.name, message);
+        let config = CratesConfig::load(&self.config).map_err(error)?;
+        let skipped = lintcheck::skipped_crates(&config, stdout, clippy);
+        let path = Path::new(run_lock::RUN_LOCK_PATH);
+        let mut lock = RunLock::load(path).map_err(error)?;
+        lock.record(&self.name, clippy, config.crates.values(), &skipped)
+            .map_err(error)?;
+        lock.save(path).map_err(error)
+    }
+
+    /// Adds the metadata of the run, the name and SHA-256 of the config and the fingerprint of
+    /// the effective config to the header of `log`.
+    fn write_metadata(&self, log: &Path, options: &RunOptions) -> Result<(), String> {
+        let config = fs::read(&self.config)
+            .map_err(|e| format!("couldn't read {}: {}", self.config.display(), e))?;
+        let mut header = options.metadata.header();
+        header.insert("config".to_string(), self.name.clone());
+        header.insert("config_sha256".to_string(), crates::sha256sum(&config)?);
+        header.insert(
+            fingerprint::HEADER_KEY.to_string(),
+            fingerprint::compute(self, options)?,
+        );
+        log::prepend_metadata(log, &header)
+            .map_err(|e| format!("couldn't write the header of {}: {}", log.display(), e))
+    }
+
+    /// Downloads everything lintcheck needs, so that it can run in the sandbox.
+    fn prefetch(&self, clippy: &Checkout, registries: &Registries) -> Result<(), Failure> {
+        let error = |message| {
+            Failure::new(FailureKind::Environment, &self.name, message).classify_network()
+        };
+        let config = CratesConfig::load(&self.config).map_err(error)?;
+        sandbox::prefetch(clippy, &config, registries).map_err(error)
+    }
+
+    fn check_log(
+        &self,
+        clippy: &Checkout,
+        options: &RunOptions,
+        parsed: &Log,
+        suppressed: &[usize],
+        stdout: &str,
+        unstable: &[Warning],
+    ) -> Vec<Failure> {
+        let reported = options.filter(parsed);
+
+        let mut summary = summary::render(&reported.warnings, &options.lints);
+        summary.push_str(&suppress::render(&options.suppress, suppressed));
+        print!("[{}] summary:\n{}", self.name, summary);
+        let mut failures = self.check_golden(parsed, options);
+        failures.extend(self.check_skipped(clippy, parsed, stdout, options));
+        failures.extend(self.check_soft_errors(stdout, options.soft_errors));
+        failures.extend(self.check_crate_expectations(parsed, &options.crate_expectations));
+        if let Err(failure) = self.write_group_counts(&reported, &options.lints) {
+            failures.push(failure);
+        }
+        if let Err(failure) = self.record_lock(clippy, stdout) {
+            failures.push(failure);
+        }
+        if !options.baseline_only {
+            if let Err(failure) = self.record_coverage(parsed) {
+                failures.push(failure);
+            }
+            if let Err(failure) = self.record_history(parsed, &summary, options) {
+                failures.push(failure);
+            }
+        }
+        if options.policy.stops(&mut failures) {
+            return failures;
+        }
+        if let Some(baseline) = &options.baseline {
+            failures.extend(self.compare_baseline(baseline, &reported, unstable, options));
+            if options.policy.stops(&mut failures) {
+                return failures;
+            }
+        }
+        if options.verify_fixes {
+            failures.extend(fix::verify(clippy, self));
+            if options.policy.stops(&mut failures) {
+                return failures;
+            }
+        }
+        failures.extend(features::check(clippy, self, parsed, &options.lints));
+        if options.policy.stops(&mut failures) {
+            return failures;
+        }
+        if let Some(toolchain) = &self.stable {
+            if let Err(failure) = stable::compare(clippy, self, toolchain, parsed, &options.lints) {
+                failures.push(failure);
+            }
+        }
+        failures
+    }
+
+    /// Publishes the log of the batches that finished before the run was interrupted, marked as
+    /// `partial` in its header, without checking it.
+    fn publish_partial(
+        &self,
+        staged_path: &Path,
+        log_path: &str,
+        mut failures: Vec<Failure>,
+    ) -> Vec<Failure> {
+        let partial = BTreeMap::from([("partial".to_string(), "interrupted".to_string())]);
+        let published = log::prepend_metadata(staged_path, &partial)
+            .and_then(|()| fs::rename(staged_path, log_path));
+        if let Err(e) = published {
+            failures.push(Failure::new(
+                FailureKind::Environment,
+                &self.name,
+                format!("couldn't publish the partial log {}: {}", log_path, e),
+            ));
+            return failures;
+        }
+        failures.retain(|failure| failure.kind != FailureKind::Interrupted);
+        failures.push(
+            Failure::new(
+                FailureKind::Interrupted,
+                &self.name,
+                "interrupted, the log only has the batches checked before and its checks \
+                 didn't run, continue with --resume",
+            )
+            .with_log(log_path),
+        );
+        failures
+    }
+
+    /// Emits a `crate-finished` event for every crate of the config and an `ice-detected` event
+    /// for every ICE in `log`.
+    fn emit_events(&self, clippy: &Checkout, log: &Log, stdout: &str) {
+        let config = match CratesConfig::load(&self.config) {
+            Ok(config) => config,
+            Err(_) => return,
+        };
+        let skipped = lintcheck::skipped_crates(&config, stdout, clippy);
+        for krate in config.krates() {
+            events::emit(Event::CrateFinished {
+                config: &self.name,
+                krate: &krate.name,
+                version: &krate.version,
+                warnings: log
+                    .warnings
+                    .iter()
+                    .filter(|warning| {
+                        warning.krate == krate.name && warning.version == krate.version
+                    })
+                    .count(),
+                skipped: skipped
+                    .iter()
+                    .find(|skipped| {
+                        skipped.krate.name == krate.name && skipped.krate.version == krate.version
+                    })
+                    .map(|skipped| skipped.reason),
+            });
+        }
+        for ice in &log.ices {
+            events::emit(Event::IceDetected {
+                config: &self.name,
+                krate: ice.krate.as_deref(),
+                message: &ice.message,
+            });
+        }
+    }
+
+    /// Path of the log this job produces.
+    pub fn log_path(&self) -> String {
+        format!("logs/{}_logs.txt", self.name)
+    }
+
+    /// Compares the log of a job that must pass with its golden file, if it has one, or replaces
+    /// the golden file with `--bless`. Jobs without a golden file are checked against their
+    /// expectation.
+    fn check_golden(&self, parsed: &Log, options: &RunOptions) -> Vec<Failure> {
+        let error = |message| vec![Failure::new(FailureKind::Environment, &self.name, message)];
+        if let Expect::Integration = self.expect {
+            return self.check_expectations(parsed, &options.lints);
+        }
+        let path = Golden::path(&self.name);
+        let actual = Golden::of(parsed);
+        if options.bless {
+            return match actual.save(&path) {
+                Ok(()) => {
+                    println!("[{}] blessed {}", self.name, path.display());
+                    Vec::new()
+                }
+                Err(message) => error(message),
+            };
+        }
+        let expected = match Golden::load(&path) {
+            Ok(Some(expected)) => expected,
+            Ok(None) => return self.check_expectations(parsed, &options.lints),
+            Err(message) => return error(message),
+        };
+        let sections = actual.diff(&expected);
+        let differences = structural_diff::count(&sections);
+        if differences == 0 {
+            return Vec::new();
+        }
+        let (expected_name, actual_name) = (path.display().to_string(), self.log_path());
+        let render = |max, color| {
+            structural_diff::render(&expected_name, &actual_name, &sections, max, color)
+        };
+        print!(
+            "[{}] the log differs from the golden file:\n{}",
+            self.name,
+            render(structural_diff::MAX_DIFFERENCES, structural_diff::color())
+        );
+        let diff_path = format!("logs/{}_golden_diff.txt", self.name);
+        if let Err(e) = publish::write(Path::new(&diff_path), render(usize::MAX, false)) {
+            return error(format!("couldn't write {}: {}", diff_path, e));
+        }
+        vec![Failure::new(
+            FailureKind::Regression,
+            &self.name,
+            format!(
+                "the log has {} difference(s) from {}, run with --bless if that is expected",
+                differences, expected_name
+            ),
+        )
+        .with_log(&diff_path)]
+    }
+
+    /// Checks the log against the expectation of the job. Warnings in the passes config are
+    /// reported with the pass implementing their lint and the warnings themselves, to find the
+    /// culprit faster. The failures show the lines of the log that broke the expectation, so
+    /// that they can be understood without the log.
+    fn check_expectations(&self, parsed: &Log, lints: &LintIndex) -> Vec<Failure> {
+        let log_path = self.log_path();
+        // The log is only published once it is checked.
+        let staged_path = publish::staged_path(&log_path);
+        let mut failures = Vec::new();
+        if let Expect::Passes = self.expect {
+            let mut per_crate = BTreeMap::<_, Vec<&Warning>>::new();
+            for warning in &parsed.warnings {
+                per_crate.entry(&warning.krate).or_default().push(warning);
+            }
+            for (krate, warnings) in per_crate {
+                let lint_names: BTreeSet<&str> = warnings
+                    .iter()
+                    .map(|warning| warning.lint.as_str())
+                    .collect();
+                let sources: Vec<_> = lint_names
+                    .iter()
+                    .map(|lint| format!("{} ({})", lint, lints.describe_source(lint)))
+                    .collect();
+                let mut message = format!(
+                    "{} clippy warning(s) in a config that must pass: {}",
+                    warnings.len(),
+                    sources.join(", ")
+                );
+                for warning in warnings.iter().take(MAX_LISTED_WARNINGS) {
+                    message.push_str(&format!("\n{}", warning));
+                }
+                if warnings.len() > MAX_LISTED_WARNINGS {
+                    message.push_str(&format!(
+                        "\n... and {} more",
+                        warnings.len() - MAX_LISTED_WARNINGS
+                    ));
+                }
+                failures.push(
+                    Failure::new(FailureKind::Regression, &self.name, message)
+                        .with_crate(krate)
+                        .with_log(&log_path),
+                );
+            }
+            if failures.is_empty() && parsed.unrecognized > 0 {
+                let message = match log::clippy_lines(&staged_path) {
+                    Ok((count, lines)) => format!(
+                        "log contains {} line(s) with `clippy::` in a config that must pass, \
+                         which couldn't be parsed as warnings:\n{}",
+                        count,
+                        lines.trim_end()
+                    ),
+                    Err(message) => format!(
+                        "log contains {} line(s) with `clippy::` in a config that must pass ({})",
+                        parsed.unrecognized, message
+                    ),
+                };
+                failures.push(
+                    Failure::new(FailureKind::Regression, &self.name, message).with_log(&log_path),
+                );
+            }
+        }
+        if !parsed.complete {
+            let message = match log::tail(&staged_path, LOG_TAIL) {
+                Ok(tail) => format!(
+                    "log is missing the ICE section, it ends with:\n{}",
+                    tail.trim_end()
+                ),
+                Err(message) => format!("log is missing the ICE section ({})", message),
+            };
+            failures.push(
+                Failure::new(FailureKind::Lintcheck, &self.name, message).with_log(&log_path),
+            );
+        }
+        for ice in &parsed.ices {
+            let mut failure = Failure::new(FailureKind::Ice, &self.name, ice.message.as_str())
+                .with_log(&log_path);
+            if let Some(krate) = &ice.krate {
+                failure = failure.with_crate(krate);
+            }
+            failures.push(failure);
+        }
+        failures
+    }
+
+    /// Lists the crates lintcheck didn't check and fails if there are more than
+    /// `max_skipped_crates` of them. Crates without any warnings or build output are listed as
+    /// needing attention and their compiler errors are written to
+    /// `logs/<job>_build_failures.md`.
+    fn check_skipped(
+        &self,
+        clippy: &Checkout,
+        log: &Log,
+        stdout: &str,
+        options: &RunOptions,
+    ) -> Vec<Failure> {
+        let config = match CratesConfig::load(&self.config) {
+            Ok(config) => config,
+            Err(message) => {
+                return vec![Failure::new(FailureKind::Environment, &self.name, message)]
+            }
+        };
+        let skipped = lintcheck::skipped_crates(&config, stdout, clippy);
+        if !skipped.is_empty() {
+            println!("[{}] not checked:", self.name);
+            for Skipped { krate, reason } in &skipped {
+                println!("    {} {}: {}", krate.name, krate.version, reason);
+            }
+        }
+        let silent = lintcheck::silent_crates(&config, log, stdout, clippy, &skipped);
+        if !silent.is_empty() {
+            println!(
+                "[{}] needs attention: no warnings and no build output, the crate probably \
+                 failed to compile:",
+                self.name
+            );
+            for krate in &silent {
+                println!("    {} {}", krate.name, krate.version);
+            }
+        }
+        let mut failures = Vec::new();
+        let mut broken: BTreeMap<String, String> = skipped
+            .iter()
+            .map(|Skipped { krate, reason }| {
+                (
+                    format!("{} {}", krate.name, krate.version),
+                    reason.to_string(),
+                )
+            })
+            .collect();
+        match self.write_build_failures(clippy, &silent, &options.stable_toolchain) {
+            Ok(build_failures) => broken.extend(build_failures.iter().map(|failure| {
+                (
+                    format!("{} {}", failure.krate.name, failure.krate.version),
+                    format!("doesn't compile, attributed to the {}", failure.cause),
+                )
+            })),
+            Err(failure) => failures.push(failure),
+        }
+        if !options.baseline_only {
+            if let Err(failure) = self.record_broken(&config, &broken) {
+                failures.push(failure);
+            }
+        }
+        match options.max_skipped_crates {
+            Some(max) if skipped.len() > max => failures.push(
+                Failure::new(
+                    FailureKind::Lintcheck,
+                    &self.name,
+                    format!(
+                        "{} crate(s) weren't checked, but at most {} may be skipped",
+                        skipped.len(),
+                        max
+                    ),
+                )
+                .with_log(format!("logs/{}_stderr.log", self.name)),
+            ),
+            _ => {}
+        }
+        failures
+    }
+
+    /// Lists the errors lintcheck reported on its `stdout` without failing, and fails with them
+    /// unless `soft_errors` says to only warn about them. The failure is a network one if all of
+    /// the errors are.
+    fn check_soft_errors(&self, stdout: &str, soft_errors: SoftErrors) -> Option<Failure> {
+        let errors = lintcheck::soft_errors(stdout);
+        if errors.is_empty() {
+            return None;
+        }
+        println!("[{}] lintcheck reported errors:", self.name);
+        for error in &errors {
+            println!("    {}", error);
+        }
+        let kind = if errors.iter().all(|error| failure::is_network_error(error)) {
+            FailureKind::Network
+        } else {
+            FailureKind::Lintcheck
+        };
+        (soft_errors == SoftErrors::Fail).then(|| {
+            Failure::new(
+                kind,
+                &self.name,
+                format!(
+                    "lintcheck reported {} error(s) on stdout, but exited successfully:\n{}",
+                    errors.len(),
+                    errors.join("\n")
+                ),
+            )
+            .with_log(format!("logs/{}_stderr.log", self.name))
+        })
+    }
+
+    /// Checks the per crate expectations of the wrapper config for the crates of this job.
+    fn check_crate_expectations(
+        &self,
+        log: &Log,
+        expectations: &CrateExpectations,
+    ) -> Vec<Failure> {
+        let config = match CratesConfig::load(&self.config) {
+            Ok(config) => config,
+            Err(message) => {
+                return vec![Failure::new(FailureKind::Environment, &self.name, message)]
+            }
+        };
+        let mut failures = Vec::new();
+        for krate in config.crates.values() {
+            let expectation = match expectations.get(&krate.name) {
+                Some(expectation) => expectation,
+                None => continue,
+            };
+            let warnings: Vec<_> = log
+                .warnings
+                .iter()
+                .filter(|warning| warning.krate == krate.name)
+                .collect();
+            let mut failure = |message| {
+                failures.push(
+                    Failure::new(FailureKind::Regression, &self.name, message)
+                        .with_crate(&krate.name)
+                        .with_log(self.log_path()),
+                )
+            };
+            match expectation.max_warnings {
+                Some(max) if warnings.len() > max => failure(format!(
+                    "{} warning(s), but at most {} are expected",
+                    warnings.len(),
+                    max
+                )),
+                _ => {}
+            }
+            if let Some(expected) = &expectation.lints {
+                let expected: BTreeSet<_> = expected
+                    .iter()
+                    .map(|lint| {
+                        if lint.starts_with("clippy::") {
+                            lint.clone()
+                        } else {
+                            format!("clippy::{}", lint)
+                        }
+                    })
+                    .collect();
+                let actual: BTreeSet<_> = warnings.iter().map(|w| w.lint.clone()).collect();
+                let section = Section {
+                    title: format!("lints of {}", krate.name),
+                    missing: expected.difference(&actual).cloned().collect(),
+                    unexpected: actual.difference(&expected).cloned().collect(),
+                };
+                if !section.missing.is_empty() || !section.unexpected.is_empty() {
+                    let diff = structural_diff::render(
+                        "expected lints",
+                        "actual lints",
+                        &[section],
+                        structural_diff::MAX_DIFFERENCES,
+                        false,
+                    );
+                    failure(format!(
+                        "the lints differ from the expected ones:\n{}",
+                        diff.trim_end()
+                    ));
+                }
+            }
+        }
+        failures
+    }
+
+    /// Records the `broken` crates of the run over `config` in the history, see
+    /// `config dead-crates`.
+    fn record_broken(
+        &self,
+        config: &CratesConfig,
+        broken: &BTreeMap<String, String>,
+    ) -> Result<(), Failure> {
+        let error = |message| Failure::new(FailureKind::Environment, &self.name, message);
+        let path = Path::new(history::HISTORY_PATH);
+        let _shared = lock::shared_files();
+        let mut history = History::load(path).map_err(error)?;
+        let checked = config
+            .krates()
+            .into_iter()
+            .map(|krate| format!("{} {}", krate.name, krate.version));
+        history.record_broken(&self.name, checked, broken);
+        history.save(path).map_err(error)
+    }
+
+    /// Writes the compiler errors of `krates` to `logs/<job>_build_failures.md` and returns the
+    /// crates that don't compile.
+    fn write_build_failures(
+        &self,
+        clippy: &Checkout,
+        krates: &[Krate],
+        toolchain: &str,
+    ) -> Result<Vec<BuildFailure>, Failure> {
+        let build_failures = build_failures::investigate(clippy, krates, toolchain)
+            .map_err(|message| Failure::new(FailureKind::Lintcheck, &self.name, message))?;
+        let path = format!("logs/{}_build_failures.md", self.name);
+        publish::write(
+            &path,
+            build_failures::render(&self.name, &build_failures, toolchain),
+        )
+        .map_err(|e| {
+            Failure::new(
+                FailureKind::Environment,
+                &self.name,
+                format!("couldn't write {}: {}", path, e),
+            )
+        })?;
+        Ok(build_failures)
+    }
+
+    /// Writes the number of warnings per lint group to `logs/<job>_groups.json`, for tracking
+    /// the groups over time.
+    fn write_group_counts(&self, log: &Log, lints: &LintIndex) -> Result<(), Failure> {
+        let path = format!("logs/{}_groups.json", self.name);
+        let json = serde_json::to_string_pretty(&summary::by_group(&log.warnings, lints))
+            .expect("group counts are serializable");
+        publish::write(&path, json + "\n").map_err(|e| {
+            Failure::new(
+                FailureKind::Environment,
+                &self.name,
+                format!("couldn't write {}: {}", path, e),
+            )
+        })
+    }
+
+    /// Adds the warnings of the log to the lint-to-crate mapping in `logs/coverage.json`.
+    fn record_coverage(&self, log: &Log) -> Result<(), Failure> {
+        let _shared = lock::shared_files();
+        let path = Path::new(coverage::COVERAGE_PATH);
+        let mut coverage = Coverage::load(path)
+            .map_err(|message| Failure::new(FailureKind::Environment, &self.name, message))?;
+        coverage.record(log);
+        coverage
+            .save(path)
+            .map_err(|message| Failure::new(FailureKind::Environment, &self.name, message))
+    }
+
+    /// Appends the counts of the log to `logs/history.json`, warns about the lints whose counts
+    /// rose steadily or whose findings churned over the last runs and writes the report with the
+    /// charts of the history.
+    fn record_history(
+        &self,
+        log: &Log,
+        summary: &str,
+        options: &RunOptions,
+    ) -> Result<(), Failure> {
+        let path = Path::new(history::HISTORY_PATH);
+        let _shared = lock::shared_files();
+        let mut history = History::load(path)
+            .map_err(|message| Failure::new(FailureKind::Environment, &self.name, message))?;
+        history.record(
+            &self.name,
+            log,
+            &options.lints,
+            options.metadata.clippy_sha.as_deref(),
+        );
+        for trend in history.trends(&self.name, options.trend_runs) {
+            let counts: Vec<_> = trend.counts.iter().map(usize::to_string).collect();
+            println!(
+                "[{}] warning: {} rose steadily over the last {} runs: {}",
+                self.name,
+                trend.lint,
+                options.trend_runs,
+                counts.join(" -> ")
+            );
+        }
+        let churning = history.churning(&self.name, options.trend_runs);
+        for churning in &churning {
+            println!(
+                "[{}] warning: findings of {} churned over the last {} runs: {} appeared and {} \
+                 disappeared with {:.1} on average, its spans may be unstable or nondeterministic",
+                self.name,
+                churning.lint,
+                options.trend_runs,
+                churning.appeared,
+                churning.disappeared,
+                churning.average
+            );
+        }
+        history
+            .save(path)
+            .and_then(|()| {
+                report::write(
+                    &self.name,
+                    summary,
+                    &log.warnings,
+                    history.runs(&self.name),
+                    &churning,
+                )
+            })
+            .map_err(|message| Failure::new(FailureKind::Environment, &self.name, message))
+    }
+
+    /// Number of warnings in the published log of the job that aren't in the baseline, `None` if
+    /// either log can't be read.
+    pub fn new_warnings(&self, baseline: &Path, options: &RunOptions) -> Option<usize> {
+        let read = |path: &Path| Log::load(path).ok().map(|log| options.filter(&log));
+        let old = read(&gzip::find(
+            baseline.join(format!("{}_logs.txt", self.name)),
+        ))?;
+        let new = read(&gzip::find(self.log_path()))?;
+        Some(Diff::new(&old.warnings, &new.warnings).added.len())
+    }
+
+    /// Diffs the log against `<baseline>/<job>_logs.txt[.gz]`, writes the result to
+    /// `logs/<job>_diff.txt` and checks the changes against the increase limit per lint and the
+    /// new warnings against the budgets per level. New warnings that are `unstable` according to
+    /// the stability check don't count against the budgets.
+    fn compare_baseline(
+        &self,
+        baseline: &Path,
+        log: &Log,
+        unstable: &[Warning],
+        options: &RunOptions,
+    ) -> Vec<Failure> {
+        let error = |message| vec![Failure::new(FailureKind::Environment, &self.name, message)];
+        let baseline_path = gzip::find(baseline.join(format!("{}_logs.txt", self.name)));
+        let diff_path = format!("logs/{}_diff.txt", self.name);
+        let diff = match diff::write(
+            &baseline_path,
+            &log.warnings,
+            |warning| options.includes(warning),
+            &options.lints,
+            Path::new(&diff_path),
+        ) {
+            Ok(diff) => diff,
+            Err(message) => return error(message),
+        };
+        println!(
+            "[{}] compared to {}: {} added, {} removed, {} reworded, see {}",
+            self.name,
+            baseline_path.display(),
+            diff.added.len(),
+            diff.removed,
+            diff.reworded,
+            diff_path
+        );
+        // Verdicts of earlier triages tell which of the new warnings are known.
+        if !diff.added.is_empty() {
+            match Verdicts::load(Path::new(triage::VERDICTS_PATH)) {
+                Ok(verdicts) if !verdicts.is_empty() => println!(
+                    "[{}] triage of the added warnings: {}",
+                    self.name,
+                    verdicts.describe(diff.added.iter().copied())
+                ),
+                Ok(_) => {}
+                Err(message) => println!("[{}] warning: {}", self.name, message),
+            }
+        }
+        let baseline_fingerprint = log::metadata(&baseline_path)
+            .ok()
+            .and_then(|mut header| header.remove(fingerprint::HEADER_KEY));
+        if let (Some(old), Some(new)) = (
+            baseline_fingerprint,
+            log.metadata.get(fingerprint::HEADER_KEY),
+        ) {
+            if old != *new {
+                println!(
+                    "[{}] note: the baseline has a different config fingerprint ({} vs. {}), \
+                     some of the changes may come from the config instead of clippy",
+                    self.name, old, new
+                );
+            }
+        }
+        let mut failures = Vec::new();
+        for (lint, (before, after)) in diff.count_changes(log) {
+            println!("    {} {} -> {}", lint, before, after);
+            let exceeded = options
+                .lint_increase_limit
+                .check(before, after)
+                .into_iter()
+                .chain(
+                    options
+                        .lint_policy
+                        .check(lint, before, after, &options.lints),
+                );
+            for exceeded in exceeded {
+                failures.push(
+                    Failure::new(
+                        FailureKind::Regression,
+                        &self.name,
+                        format!("{} {} -> {}: {}", lint, before, after, exceeded),
+                    )
+                    .with_log(&diff_path),
+                );
+            }
+        }
+        if !diff.added.is_empty() {
+            print!(
+                "[{}] new warnings:\n{}",
+                self.name,
+                summary::render(diff.added.iter().copied(), &options.lints)
+            );
+        }
+
+        let unstable: BTreeSet<String> = unstable.iter().map(Warning::to_string).collect();
+        let (flaky, added): (Vec<&Warning>, Vec<&Warning>) = diff
+            .added
+            .iter()
+            .partition(|warning| unstable.contains(&warning.to_string()));
+        if !flaky.is_empty() {
+            println!(
+                "[{}] {} of the new warnings are unstable and don't count against the budgets, \
+                 see logs/{}_unstable.txt",
+                self.name,
+                flaky.len(),
+                self.name
+            );
+        }
+        for (level, counts) in summary::by_level(added, &options.lints) {
+            let new: usize = counts.values().sum();
+            match options.new_warning_budget.get(level) {
+                Some(budget) if new > budget => failures.push(
+                    Failure::new(
+                        FailureKind::Regression,
+                        &self.name,
+                        format!(
+                            "{} new {} warning(s), but the budget is {}",
+                            new, level, budget
+                        ),
+                    )
+                    .with_log(&diff_path),
+                ),
+                _ => {}
+            }
+        }
+        failures
+    }
+}
+
+/// Options that apply to every job of a run.
+#[derive(Debug)]
+pub struct RunOptions {
+    pub policy: Policy,
+    /// Directory containing the logs to compare against
+    pub baseline: Option<PathBuf>,
+    /// Verify that the machine applicable suggestions converge
+    pub verify_fixes: bool,
+    /// Lint the crates a second time and report the findings that differ
+    pub stability_check: bool,
+    /// Lints of the checkout
+    pub lints: LintIndex,
+    /// Maximum number of new warnings compared to the baseline per level
+    pub new_warning_budget: LevelBudgets,
+    /// Maximum increase of the warnings per lint compared to the baseline
+    pub lint_increase_limit: IncreaseLimit,
+    /// Allowed change of the warnings per lint and group compared to the baseline
+    pub lint_policy: LintPolicy,
+    /// Lint groups included in summaries and diffs, all if empty
+    pub groups: Vec<String>,
+    /// Alternative registries of the wrapper config
+    pub registries: Registries,
+    /// Maximum number of crates lintcheck may skip, unlimited if `None`
+    pub max_skipped_crates: Option<usize>,
+    /// Toolchain crates that fail to build are tried with
+    pub stable_toolchain: String,
+    /// Run lintcheck in the sandbox
+    pub sandbox: bool,
+    /// Clippy config injected into lintcheck instead of the ones of the crates
+    pub clippy_conf: Option<ClippyConf>,
+    pub crate_expectations: CrateExpectations,
+    /// Rules whose warnings are dropped from the logs
+    pub suppress: Vec<Rule>,
+    /// Replace the golden files of the jobs that must pass instead of comparing with them
+    pub bless: bool,
+    /// Describes the run in the headers of the logs. Its commit of the clippy checkout is also
+    /// recorded in the history
+    pub metadata: RunMetadata,
+    /// Maximum number of crates lintcheck checks at once, all if `None`
+    pub batch_size: Option<usize>,
+    /// Shard of the crates this run checks, all if `None`. Baseline warnings of other crates
+    /// are ignored
+    pub shard: Option<Shard>,
+    /// Names of the crates the `--sample` of any job picked, all if `None`. Baseline warnings of
+    /// other crates are ignored
+    pub sample: Option<BTreeSet<String>>,
+    /// Names of the crates this run checks, all if empty. Baseline warnings of other crates are
+    /// ignored
+    pub crates: Vec<String>,
+    /// Reuse the batches a previous run finished
+    pub resume: bool,
+    /// Reuse the results of crates checked with the same clippy, see `result_cache`
+    pub result_cache: Option<ResultCache>,
+    /// Replace the published text logs by gzipped ones
+    pub gzip_logs: bool,
+    /// Format the warnings are written in, besides the text log
+    pub format: Format,
+    /// Format the log is additionally written to `logs/` in
+    pub log_format: LogFormat,
+    /// Add snippets of the code of the warnings to the Markdown logs
+    pub snippets: bool,
+    /// Append every warning to `logs/results.jsonl`, not only the record of the run
+    pub results_warnings: bool,
+    /// Number of runs in which lints with steadily rising counts are looked for
+    pub trend_runs: usize,
+    /// Whether errors lintcheck only reported on stdout fail the job
+    pub soft_errors: SoftErrors,
+    /// Only write the logs, which another run uses as its baseline. Nothing is recorded in the
+    /// history, the coverage or the results, as the run isn't one of its own
+    pub baseline_only: bool,
+}
+
+impl RunOptions {
+    /// Whether `warning` is of one of the selected lint groups and of a selected crate of the
+    /// shard, and isn't suppressed.
+    pub fn includes(&self, warning: &Warning) -> bool {
+        (self.groups.is_empty()
+            || self
+                .groups
+                .iter()
+                .any(|group| group == self.lints.group(&warning.lint)))
+            && self
+                .shard
+                .is_none_or(|shard| shard.contains(&warning.krate))
+            && self
+                .sample
+                .as_ref()
+                .is_none_or(|sample| sample.contains(&warning.krate))
+            && (self.crates.is_empty() || self.crates.contains(&warning.krate))
+            && !self.suppress.iter().any(|rule| rule.matches(warning))
+    }
+
+    /// Returns a copy of `log` with only the warnings of the selected lint groups.
+    fn filter(&self, log: &Log) -> Log {
+        Log {
+            metadata: log.metadata.clone(),
+            clippy_version: log.clippy_version.clone(),
+            warnings: log
+                .warnings
+                .iter()
+                .filter(|warning| self.includes(warning))
+                .cloned()
+                .collect(),
+            ices: log.ices.clone(),
+            complete: log.complete,
+            unrecognized: log.unrecognized,
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! The optional `policy.toml` with the change of the warnings of a lint compared to
+//! `--baseline` that is allowed, e.g. to let nursery lints churn while correctness lints may
+//! not change at all.
+//!
+//! ```toml
+//! # allowed change of lints without an entry, unlimited if missing
+//! default = 10
+//!
+//! [groups]
+//! correctness = 0
+//! nursery = "any"
+//!
+//! # entries of lints take precedence over the ones of their groups
+//! [lints]
+//! needless_return = 2
+//! ```
+//!
+//! The change is the difference between the number of warnings of the lint in the baseline and
+//! in the new log, in either direction.
+
+use crate::lints::LintIndex;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub const DEFAULT_PATH: &str = "policy.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintPolicy {
+    default: Option<Budget>,
+    #[serde(default)]
+    groups: BTreeMap<String, Budget>,
+    /// Keyed by the lint name, with or without the `clippy::` prefix
+    #[serde(default)]
+    lints: BTreeMap<String, Budget>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+enum Budget {
+    Limit(usize),
+    Unlimited(Unlimited),
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+enum Unlimited {
+    #[serde(rename = "any")]
+    Any,
+}
+
+impl LintPolicy {
+    /// Loads the policy from `path`. A missing file at the default location is treated as an
+    /// empty policy.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let default = PathBuf::from(DEFAULT_PATH);
+        let file = path.unwrap_or(&default);
+        match fs::read_to_string(file) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|e| format!("invalid lint policy {}: {}", file.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound && path.is_none() => Ok(Self::default()),
+            Err(e) => Err(format!("couldn't read {}: {}", file.display(), e)),
+        }
+    }
+
+    /// Describes how the change of `lint` from `before` to `after` warnings exceeds its budget,
+    /// if it does.
+    pub fn check(
+        &self,
+        lint: &str,
+        before: usize,
+        after: usize,
+        lints: &LintIndex,
+    ) -> Option<String> {
+        let name = lint.strip_prefix("clippy::").unwrap_or(lint);
+        let group = lints.group(lint);
+        let (budget, source) = match (
+            self.lints.get(name).or_else(|| self.lints.get(lint)),
+            self.groups.get(group),
+        ) {
+            (Some(budget), _) => (budget, "the budget of the lint".to_string()),
+            (None, Some(budget)) => (budget, format!("the budget of the {} group", group)),
+            (None, None) => (self.default.as_ref()?, "the default budget".to_string()),
+        };
+        let change = before.abs_diff(after);
+        match budget {
+            Budget::Limit(limit) if change > *limit => Some(format!(
+                "changed by {}, but {} allows {}",
+                change, source, limit
+            )),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,550 @@
+use crate::{
+    crates::{CratesConfig, Krate},
+    events::{self, Event},
+    failure::{Failure, FailureKind},
+    history::{self, History},
+    interrupt,
+    job::RunOptions,
+    lock,
+    log::Log,
+    sandbox,
+    wrapper_config::Checkout,
+};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Write},
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Directory, relative to the target dir of the checkout, lintcheck builds the crates in.
+const LINTCHECK_TARGET: &str = "lintcheck/shared_target_dir";
+/// Number of trailing stderr lines included in error messages.
+const STDERR_TAIL: usize = 20;
+/// Prefix of the lines the result cache adds to the stdout of lintcheck for the crates whose
+/// results it restored, which aren't linted and have no sources or build output.
+pub const CACHED: &str = "cached:";
+/// Parts of the lines lintcheck prints to stdout when something went wrong that it recovers
+/// from, e.g. by skipping the crate, while still exiting successfully. Matched case-insensitively.
+const SOFT_ERRORS: &[&str] = &[
+    "failed to download",
+    "failed to extract",
+    "failed to unpack",
+    "failed to clone",
+    "failed to fetch",
+    "failed to checkout",
+];
+
+/// What a run does about the soft errors lintcheck prints to stdout, see `soft_errors`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoftErrors {
+    /// Fail the job
+    Fail,
+    /// Only list them
+    Warn,
+}
+
+impl FromStr for SoftErrors {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(Self::Fail),
+            "warn" => Ok(Self::Warn),
+            err => Err(format!("Invalid option {}", err)),
+        }
+    }
+}
+
+/// The cargo alias upstream runs lintcheck with, which changed over time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EntryPoint {
+    /// `cargo dev-lintcheck`, writing `lintcheck-logs/<config>_logs.txt` in the checkout
+    DevLintcheck,
+    /// `cargo lintcheck`, writing `lintcheck-logs/<config>_logs.txt` in the target dir
+    Lintcheck,
+}
+
+impl FromStr for EntryPoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dev-lintcheck" => Ok(Self::DevLintcheck),
+            "lintcheck" => Ok(Self::Lintcheck),
+            err => Err(format!("Invalid option {}", err)),
+        }
+    }
+}
+
+impl EntryPoint {
+    /// The entry point of the checkout at `path`: `cargo lintcheck` if its cargo config has
+    /// that alias, `cargo dev-lintcheck` otherwise.
+    pub fn detect(path: &Path) -> Self {
+        let has_alias = ["config.toml", "config"].iter().any(|file| {
+            fs::read_to_string(path.join(".cargo").join(file))
+                .ok()
+                .and_then(|text| text.parse::<toml::Value>().ok())
+                .and_then(|config| config.get("alias")?.get("lintcheck").cloned())
+                .is_some()
+        });
+        if has_alias {
+            Self::Lintcheck
+        } else {
+            Self::DevLintcheck
+        }
+    }
+
+    pub fn alias(self) -> &'static str {
+        match self {
+            Self::DevLintcheck => "dev-lintcheck",
+            Self::Lintcheck => "lintcheck",
+        }
+    }
+
+    /// Where lintcheck writes the log of a config, see `Checkout::log_paths`. Newer versions
+    /// write it to the target dir, some older ones under the name of the default config,
+    /// `lintcheck_crates.toml`, and some only as Markdown.
+    pub fn log_paths(self) -> &'static [&'static str] {
+        match self {
+            Self::DevLintcheck => &[
+                "{checkout}/lintcheck-logs/{config}_logs.txt",
+                "{checkout}/lintcheck-logs/lintcheck_crates_logs.txt",
+                "{checkout}/lintcheck-logs/{config}_logs.md",
+            ],
+            Self::Lintcheck => &[
+                "{target}/lintcheck-logs/{config}_logs.txt",
+                "{target}/lintcheck-logs/lintcheck_crates_logs.txt",
+                "{target}/lintcheck-logs/{config}_logs.md",
+            ],
+        }
+    }
+}
+
+/// Runs lintcheck with the entry point of the `clippy` checkout on `config` and copies the
+/// resulting log, the first of the log paths of the checkout lintcheck wrote, to `log`. Returns
+/// the stdout of lintcheck.
+///
+/// The stderr of lintcheck is forwarded to our stderr and additionally written to
+/// `logs/<output>_stderr.log`. The registries of `options` are passed on to cargo and
+/// `clippy_conf` is exported as `CLIPPY_CONF_DIR`. In the sandbox of `options`, the crates must
+/// have been prefetched. The durations of the crates are recorded in the history, unless the run
+/// is `baseline_only`.
+pub fn check(
+    clippy: &Checkout,
+    config: &Path,
+    output: &str,
+    clippy_conf: Option<&Path>,
+    log: &Path,
+    options: &RunOptions,
+) -> Result<String, Failure> {
+    let stderr_path = PathBuf::from(format!("logs/{}_stderr.log", output));
+    let stderr_log = File::create(&stderr_path).map_err(|e| {
+        Failure::new(
+            FailureKind::Environment,
+            output,
+            format!("couldn't create {}: {}", stderr_path.display(), e),
+        )
+    })?;
+    let lintcheck_error = |message: String| {
+        Failure::new(FailureKind::Lintcheck, output, message)
+            .with_log(&stderr_path)
+            .classify_network()
+    };
+    let mut command = if options.sandbox {
+        sandbox::cargo(clippy, std::iter::once(config).chain(clippy_conf))
+    } else {
+        Command::new("cargo")
+    };
+    if let Some(toolchain) = &clippy.toolchain {
+        command.arg(format!("+{}", toolchain));
+    }
+    if let Some(target_dir) = &clippy.target_dir {
+        command.env("CARGO_TARGET_DIR", target_dir);
+    }
+    if let Some(dir) = clippy_conf {
+        command.env("CLIPPY_CONF_DIR", dir);
+    }
+    for (name, registry) in &options.registries {
+        let env = registry
+            .cargo_env(name)
+            .map_err(|message| Failure::new(FailureKind::Environment, output, message))?;
+        command.envs(env);
+    }
+    let entry_point = clippy.entry_point();
+    let stem = config.file_stem().unwrap().to_string_lossy();
+    let log_paths = clippy.log_paths(entry_point, &stem);
+    // Logs of earlier runs must not be mistaken for the log of this one.
+    remove_logs(&log_paths).map_err(lintcheck_error)?;
+    let mut child = command
+        .arg(entry_point.alias())
+        .env("LINTCHECK_TOML", config)
+        .current_dir(&clippy.path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .map_err(|e| lintcheck_error(format!("couldn't execute lintcheck tool: {}", e)))?;
+    let tracked = interrupt::track(&child);
+
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let tee = thread::spawn(move || tee(stderr, stderr_log));
+    let mut stdout = String::new();
+    let mut durations = BTreeMap::new();
+    let mut linted: Option<(String, Instant)> = None;
+    let mut read_error = None;
+    for line in BufReader::new(child.stdout.take().expect("stdout is piped")).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                read_error = Some(e);
+                break;
+            }
+        };
+        if let Some((krate, version)) = linting(&line) {
+            let now = Instant::now();
+            if let Some((previous, started)) = linted.replace((krate.to_string(), now)) {
+                durations.insert(previous, now - started);
+            }
+            events::emit(Event::CrateStarted {
+                config: output,
+                krate,
+                version,
+            });
+        }
+        if is_soft_error(&line) {
+            println!("[{}] lintcheck reported an error: {}", output, line);
+        }
+        stdout.push_str(&line);
+        stdout.push('\n');
+    }
+    if let Some(e) = read_error {
+        // The stderr thread only finishes once lintcheck and everything it started are gone.
+        kill(&mut child);
+        let _ = tee.join();
+        return Err(lintcheck_error(format!(
+            "couldn't read lintcheck stdout: {}",
+            e
+        )));
+    }
+    let status = child
+        .wait()
+        .map_err(|e| lintcheck_error(format!("couldn't wait for lintcheck: {}", e)))?;
+    if let Some((last, started)) = linted {
+        durations.insert(last, started.elapsed());
+    }
+    let tail = tee.join().expect("stderr thread panicked").map_err(|e| {
+        Failure::new(
+            FailureKind::Environment,
+            output,
+            format!("couldn't write {}: {}", stderr_path.display(), e),
+        )
+    })?;
+    drop(tracked);
+
+    if interrupt::requested() {
+        // Whatever lintcheck wrote before it was terminated is incomplete.
+        let _ = remove_logs(&log_paths);
+        return Err(Failure::new(
+            FailureKind::Interrupted,
+            output,
+            "interrupted, lintcheck was terminated",
+        )
+        .with_log(&stderr_path));
+    }
+    if !status.success() {
+        return Err(lintcheck_error(format!(
+            "cargo {} exited with {}\nstderr (last {} lines):\n{}",
+            entry_point.alias(),
+            status,
+            STDERR_TAIL,
+            tail.join("\n"),
+        )));
+    }
+    println!("lintcheck stdout: {}", stdout);
+    let lintcheck_log = match log_paths.iter().find(|path| path.exists()) {
+        Some(path) => path,
+        None => {
+            return Err(lintcheck_error(missing_log(
+                clippy,
+                entry_point,
+                &log_paths,
+                &stem,
+            )))
+        }
+    };
+    fs::copy(lintcheck_log, log)
+        .map_err(|e| lintcheck_error(format!("couldn't copy log file: {}", e)))?;
+    if !options.baseline_only {
+        record_durations(&durations)
+            .map_err(|message| Failure::new(FailureKind::Environment, output, message))?;
+    }
+    Ok(stdout)
+}
+
+/// Kills the process group `child` leads, which must have been spawned with `process_group(0)`,
+/// and reaps the child.
+fn kill(child: &mut Child) {
+    // SAFETY: `kill` has no memory safety requirements. A negative pid signals the process group.
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+/// Records the `durations` of the crates in the history. Lintcheck only reports when it starts
+/// linting a crate, so a crate is taken to take until the next one starts. That is exact if
+/// lintcheck lints one crate at a time, and still orders the crates roughly by their cost if it
+/// lints several in parallel.
+fn record_durations(durations: &BTreeMap<String, Duration>) -> Result<(), String> {
+    let path = Path::new(history::HISTORY_PATH);
+    let _shared = lock::shared_files();
+    let mut history = History::load(path)?;
+    history.record_durations(durations);
+    history.save(path)
+}
+
+/// Removes the builds of the crates lintcheck checked in the `clippy` checkout, so that the next
+/// run lints them again instead of cargo replaying their diagnostics. Downloads are kept.
+pub fn clean_builds(clippy: &Checkout) -> Result<(), String> {
+    let dir = clippy.target_dir().join(LINTCHECK_TARGET);
+    match fs::remove_dir_all(&dir) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            Err(format!("couldn't remove {}: {}", dir.display(), e))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Removes the files of `log_paths` that exist.
+fn remove_logs(log_paths: &[PathBuf]) -> Result<(), String> {
+    for path in log_paths {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                return Err(format!("couldn't remove {}: {}", path.display(), e))
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Describes the log lintcheck didn't write to any of the `log_paths`, listing the files that
+/// look like it in the places lintcheck wrote its logs to over time.
+fn missing_log(
+    clippy: &Checkout,
+    entry_point: EntryPoint,
+    log_paths: &[PathBuf],
+    stem: &str,
+) -> String {
+    let searched: Vec<String> = log_paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    let mut message = format!(
+        "lintcheck succeeded but didn't write any of {}",
+        searched.join(", ")
+    );
+    let dirs = [
+        clippy.path.join("lintcheck-logs"),
+        clippy.target_dir().join("lintcheck-logs"),
+        clippy.target_dir().join("lintcheck"),
+    ];
+    let mut candidates: Vec<PathBuf> = dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with(stem))
+        })
+        .collect();
+    candidates.sort();
+    if candidates.is_empty() {
+        message.push_str(", and no file that looks like its log");
+        return message;
+    }
+    message.push_str(", but these files look like its log:");
+    for candidate in &candidates {
+        message.push_str(&format!("\n    {}", candidate.display()));
+    }
+    let other = match entry_point {
+        EntryPoint::DevLintcheck => EntryPoint::Lintcheck,
+        EntryPoint::Lintcheck => EntryPoint::DevLintcheck,
+    };
+    if clippy
+        .log_paths(other, stem)
+        .iter()
+        .any(|path| candidates.contains(path))
+    {
+        message.push_str(&format!(
+            "\nthe checkout seems to use `cargo {}`, pass `--lintcheck {}` or set \
+             `lintcheck = \"{}\"` for the checkout in the wrapper config",
+            other.alias(),
+            other.alias(),
+            other.alias()
+        ));
+    } else {
+        message.push_str(
+            "\nlintcheck may have changed where or in which format it writes its logs, set \
+             `log-paths` for the checkout in the wrapper config",
+        );
+    }
+    message
+}
+
+/// A crate of the config that lintcheck didn't check, e.g. because it couldn't be downloaded or
+/// built. Such crates contribute no warnings, which would otherwise go unnoticed.
+#[derive(Debug)]
+pub struct Skipped {
+    pub krate: Krate,
+    pub reason: &'static str,
+}
+
+/// The name and version of the crate of a line of the stdout of lintcheck that reports that it
+/// started linting a crate, which lintcheck prints as
+/// `<index>/<total> <percentage>% Linting <name> <version>`.
+fn linting(line: &str) -> Option<(&str, &str)> {
+    let mut words = line
+        .split_whitespace()
+        .skip_while(|&word| word != "Linting");
+    words.next()?;
+    Some((words.next()?, words.next()?))
+}
+
+/// Whether `line` of the stdout of lintcheck reports an error lintcheck recovered from.
+fn is_soft_error(line: &str) -> bool {
+    let line = line.to_lowercase();
+    line.trim_start().starts_with("error:") || SOFT_ERRORS.iter().any(|error| line.contains(error))
+}
+
+/// The lines of the `stdout` of lintcheck that report errors lintcheck recovered from, which
+/// don't make it exit with an error. Crates it skipped because of them are only noticed if they
+/// are reported as skipped.
+pub fn soft_errors(stdout: &str) -> Vec<&str> {
+    stdout.lines().filter(|line| is_soft_error(line)).collect()
+}
+
+/// The crates of `stdout` whose results came from the result cache.
+fn cached(stdout: &str) -> BTreeSet<(&str, &str)> {
+    stdout
+        .lines()
+        .filter(|line| line.starts_with(CACHED))
+        .filter_map(linting)
+        .collect()
+}
+
+/// Finds the crates of `config` that lintcheck either didn't report as linted in its `stdout` or
+/// whose sources aren't in the `clippy` checkout.
+pub fn skipped_crates(config: &CratesConfig, stdout: &str, clippy: &Checkout) -> Vec<Skipped> {
+    let linted: BTreeSet<_> = stdout.lines().filter_map(linting).collect();
+    let cached = cached(stdout);
+    config
+        .krates()
+        .into_iter()
+        .filter_map(|krate| {
+            let key = (krate.name.as_str(), krate.version.as_str());
+            let reason = if !linted.contains(&key) {
+                "not linted by lintcheck"
+            } else if !cached.contains(&key) && !clippy.path.join(&krate.source_dir).exists() {
+                "sources missing"
+            } else {
+                return None;
+            };
+            Some(Skipped { krate, reason })
+        })
+        .collect()
+}
+
+/// Finds the crates of `config` that have no warnings in `log` and weren't built successfully
+/// either, which usually means that they silently failed to compile. Crates in `skipped` are
+/// left out, they are reported already, and so are the crates `stdout` reports as cached.
+pub fn silent_crates(
+    config: &CratesConfig,
+    log: &Log,
+    stdout: &str,
+    clippy: &Checkout,
+    skipped: &[Skipped],
+) -> Vec<Krate> {
+    let mut built = BTreeSet::new();
+    collect_built_crates(&clippy.target_dir().join(LINTCHECK_TARGET), 2, &mut built);
+    let cached = cached(stdout);
+    config
+        .krates()
+        .into_iter()
+        .filter(|krate| {
+            !built.contains(&krate.name)
+                && !cached.contains(&(krate.name.as_str(), krate.version.as_str()))
+                && !log
+                    .warnings
+                    .iter()
+                    .any(|warning| warning.krate == krate.name && warning.version == krate.version)
+                && !skipped.iter().any(|skipped| {
+                    skipped.krate.name == krate.name && skipped.krate.version == krate.version
+                })
+        })
+        .collect()
+}
+
+/// Collects the names of the packages cargo successfully built in `dir` or the target dirs up to
+/// `depth` levels below it, like the per thread target dirs of lintcheck. Cargo only writes the
+/// `lib-*` or `bin-*` fingerprint of a package once it compiled.
+fn collect_built_crates(dir: &Path, depth: usize, built: &mut BTreeSet<String>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if entry.file_name() != ".fingerprint" {
+            if depth > 0 {
+                collect_built_crates(&path, depth - 1, built);
+            }
+            continue;
+        }
+        for package in fs::read_dir(&path).into_iter().flatten().flatten() {
+            let dir_name = package.file_name().to_string_lossy().into_owned();
+            let compiled = fs::read_dir(package.path())
+                .into_iter()
+                .flatten()
+                .flatten()
+                .any(|file| {
+                    let file = file.file_name().to_string_lossy().into_owned();
+                    file.starts_with("lib-") || file.starts_with("bin-")
+                });
+            // Fingerprint directories are named `<package>-<hash>`.
+            if let (true, Some((name, _))) = (compiled, dir_name.rsplit_once('-')) {
+                built.insert(name.to_string());
+            }
+        }
+    }
+}
+
+/// Forwards `input` line by line to stderr and `log`, returning the last `STDERR_TAIL` lines.
+fn tee(input: impl Read, mut log: File) -> io::Result<Vec<String>> {
+    let mut tail = VecDeque::with_capacity(STDERR_TAIL + 1);
+    for line in BufReader::new(input).split(b'\n') {
+        let line = String::from_utf8_lossy(&line?).into_owned();
+        eprintln!("{}", line);
+        writeln!(log, "{}", line)?;
+        tail.push_back(line);
+        if tail.len() > STDERR_TAIL {
+            tail.pop_front();
+        }
+    }
+    Ok(tail.into())
+}
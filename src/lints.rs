@@ -0,0 +1,328 @@
+//! Metadata about the lints of a clippy checkout, read from the `lints.json` clippy generates for
+//! its website or, if that doesn't exist, from the `declare_clippy_lint!` declarations.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// Level a lint has when it isn't configured otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Deny,
+    Warn,
+    Allow,
+    /// The lint isn't declared in the checkout
+    Unknown,
+}
+
+impl Level {
+    fn parse(level: &str) -> Self {
+        match level {
+            "deny" => Self::Deny,
+            "warn" => Self::Warn,
+            "allow" => Self::Allow,
+            _ => Self::Unknown,
+        }
+    }
+
+    fn of_group(group: &str) -> Self {
+        match group {
+            "correctness" => Self::Deny,
+            "style" | "complexity" | "perf" | "suspicious" => Self::Warn,
+            _ => Self::Allow,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Deny => "deny",
+            Self::Warn => "warn",
+            Self::Allow => "allow",
+            Self::Unknown => "unknown",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Lint {
+    pub group: String,
+    pub level: Level,
+    /// `None` if the sources of the checkout couldn't be read
+    pub source: Option<LintSource>,
+}
+
+/// The kind of lint pass a lint is implemented in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    Early,
+    Late,
+}
+
+impl fmt::Display for Pass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Early => "early",
+            Self::Late => "late",
+        })
+    }
+}
+
+/// Where a lint is declared and implemented in the checkout.
+#[derive(Debug, Clone)]
+pub struct LintSource {
+    /// File with the `declare_clippy_lint!`, relative to the checkout
+    pub file: PathBuf,
+    pub line: usize,
+    /// The pass implemented in the declaring file and the line of its `impl`
+    pub pass: Option<(Pass, usize)>,
+    /// File named after the lint next to the declaring file, where lints of big modules like
+    /// `methods` are implemented
+    pub implementation: Option<PathBuf>,
+}
+
+impl fmt::Display for LintSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.pass {
+            Some((pass, line)) => write!(f, "{} pass at {}:{}", pass, self.file.display(), line)?,
+            None => write!(f, "declared at {}:{}", self.file.display(), self.line)?,
+        }
+        if let Some(implementation) = &self.implementation {
+            write!(f, ", implemented in {}", implementation.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Page of the clippy lint index, the lints are anchors on it.
+const LINT_DOCS: &str = "https://rust-lang.github.io/rust-clippy/master/index.html";
+
+/// Locations of the lint metadata clippy generates, relative to the checkout.
+const LINTS_JSON: &[&str] = &["util/gh-pages/lints.json", "lints.json"];
+
+/// A `declare_clippy_lint!` found in the sources.
+pub struct Declaration {
+    /// Lowercase name without the `clippy::` prefix
+    pub name: String,
+    pub group: String,
+    pub line: usize,
+}
+
+/// An entry of `lints.json`. Older versions don't have the `level`.
+#[derive(Debug, Deserialize)]
+struct LintMetadata {
+    id: String,
+    group: String,
+    level: Option<String>,
+}
+
+/// The lints of a checkout, keyed by their `clippy::`-prefixed name.
+#[derive(Debug, Default)]
+pub struct LintIndex {
+    lints: BTreeMap<String, Lint>,
+    /// The checkout the lints were collected from
+    clippy_path: PathBuf,
+}
+
+impl LintIndex {
+    /// Collects the lints of the checkout at `clippy_path`, from `lints.json` if it exists and
+    /// from the sources in `clippy_lints/src` otherwise. The sources are also used to locate the
+    /// implementation of every lint. Returns an empty index if neither can be read.
+    pub fn load(clippy_path: &Path) -> Self {
+        let mut index = Self {
+            clippy_path: clippy_path.to_path_buf(),
+            ..Self::default()
+        };
+        for path in LINTS_JSON {
+            let metadata = fs::read_to_string(clippy_path.join(path))
+                .ok()
+                .and_then(|json| serde_json::from_str::<Vec<LintMetadata>>(&json).ok());
+            if let Some(metadata) = metadata {
+                for lint in metadata {
+                    let level = lint
+                        .level
+                        .as_deref()
+                        .map_or_else(|| Level::of_group(&lint.group), Level::parse);
+                    index.lints.insert(
+                        format!("clippy::{}", lint.id),
+                        Lint {
+                            group: lint.group,
+                            level,
+                            source: None,
+                        },
+                    );
+                }
+                break;
+            }
+        }
+
+        let mut files = Vec::new();
+        collect_rust_files(&clippy_path.join("clippy_lints/src"), &mut files);
+        for file in files {
+            let source = match fs::read_to_string(&file) {
+                Ok(source) => source,
+                Err(_) => continue,
+            };
+            let pass = find_pass(&source);
+            for declaration in parse_declarations(&source) {
+                let Declaration { name, group, line } = declaration;
+                let lint = index
+                    .lints
+                    .entry(format!("clippy::{}", name))
+                    .or_insert_with(|| Lint {
+                        level: Level::of_group(&group),
+                        group,
+                        source: None,
+                    });
+                let implementation = file.with_file_name(format!("{}.rs", name));
+                lint.source = Some(LintSource {
+                    file: relative(&file, clippy_path),
+                    line,
+                    pass,
+                    implementation: if implementation.is_file() {
+                        Some(relative(&implementation, clippy_path))
+                    } else {
+                        None
+                    },
+                });
+            }
+        }
+        index
+    }
+
+    pub fn get(&self, lint: &str) -> Option<&Lint> {
+        self.lints.get(lint)
+    }
+
+    /// The lints of the index by name, in alphabetical order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Lint)> {
+        self.lints.iter().map(|(name, lint)| (name.as_str(), lint))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lints.is_empty()
+    }
+
+    pub fn level(&self, lint: &str) -> Level {
+        self.get(lint).map_or(Level::Unknown, |lint| lint.level)
+    }
+
+    /// Group of `lint`, or `unknown` if the lint isn't in the index.
+    pub fn group(&self, lint: &str) -> &str {
+        self.get(lint).map_or("unknown", |lint| &lint.group)
+    }
+
+    /// Markdown linking `lint` to its documentation and, if it is known, to the file of the
+    /// checkout that implements it.
+    pub fn markdown_link(&self, lint: &str) -> String {
+        let name = lint.strip_prefix("clippy::").unwrap_or(lint);
+        let mut link = format!("[`{}`]({}#{})", lint, LINT_DOCS, name);
+        if let Some(source) = self.get(lint).and_then(|lint| lint.source.as_ref()) {
+            let (file, line) = match (&source.implementation, source.pass) {
+                (Some(implementation), _) => (implementation, None),
+                (None, Some((_, line))) => (&source.file, Some(line)),
+                (None, None) => (&source.file, Some(source.line)),
+            };
+            let text = match line {
+                Some(line) => format!("{}:{}", file.display(), line),
+                None => file.display().to_string(),
+            };
+            link.push_str(&format!(
+                " ([{}](file://{}))",
+                text,
+                self.clippy_path.join(file).display()
+            ));
+        }
+        link
+    }
+
+    /// Where `lint` is implemented, or `unknown lint` if it isn't in the index.
+    pub fn describe_source(&self, lint: &str) -> String {
+        match self.get(lint) {
+            Some(Lint {
+                source: Some(source),
+                ..
+            }) => source.to_string(),
+            Some(_) => "source not found".to_string(),
+            None => "unknown lint".to_string(),
+        }
+    }
+}
+
+/// Parses every
+///
+/// ```text
+/// declare_clippy_lint! {
+///     /// docs
+///     pub LINT_NAME,
+///     group,
+///     "description"
+/// }
+/// ```
+///
+/// in `source`.
+pub fn parse_declarations(source: &str) -> Vec<Declaration> {
+    let mut declarations = Vec::new();
+    let mut lines = source.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        if !line.trim_start().starts_with("declare_clippy_lint!") {
+            continue;
+        }
+        let name = lines
+            .by_ref()
+            .find_map(|(_, line)| line.trim().strip_prefix("pub "));
+        let group = lines
+            .by_ref()
+            .map(|(_, line)| line.trim())
+            .find(|line| !line.is_empty());
+        if let (Some(name), Some(group)) = (name, group) {
+            declarations.push(Declaration {
+                name: name.trim_end_matches(',').to_lowercase(),
+                group: group.trim_end_matches(',').to_string(),
+                line: i + 1,
+            });
+        }
+    }
+    declarations
+}
+
+/// Finds the first `impl EarlyLintPass for ..` or `impl LateLintPass<'_> for ..` in `source`.
+fn find_pass(source: &str) -> Option<(Pass, usize)> {
+    source.lines().enumerate().find_map(|(i, line)| {
+        let line = line.trim_start();
+        if !line.starts_with("impl") || !line.contains(" for ") {
+            None
+        } else if line.contains("EarlyLintPass") {
+            Some((Pass::Early, i + 1))
+        } else if line.contains("LateLintPass") {
+            Some((Pass::Late, i + 1))
+        } else {
+            None
+        }
+    })
+}
+
+fn relative(path: &Path, base: &Path) -> PathBuf {
+    path.strip_prefix(base).unwrap_or(path).to_path_buf()
+}
+
+/// Adds the Rust files in `dir` and its subdirectories to `files`.
+pub fn collect_rust_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_rust_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
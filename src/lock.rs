@@ -0,0 +1,65 @@
+//! Advisory locks that keep simultaneous runs from interleaving their writes to `logs/` and to
+//! the `lintcheck-logs/` of the clippy checkout, and the lock that does the same for the jobs of
+//! a run that run concurrently.
+
+use std::{
+    fs::{self, File, TryLockError},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+/// Lock file guarding the logs of this repository.
+const LOGS_LOCK: &str = "logs/.lock";
+/// Lock file guarding the clippy checkout, relative to it. `target/` is ignored by git, so the
+/// lock doesn't make the checkout dirty.
+const CHECKOUT_LOCK: &str = "target/clippy-lintcheck.lock";
+
+/// Guards the files in `logs/` that every job of a run updates, like the history.
+static SHARED_FILES: Mutex<()> = Mutex::new(());
+
+/// Held locks, released when dropped.
+#[derive(Debug)]
+pub struct RunLock {
+    _files: Vec<File>,
+}
+
+/// Locks the logs and the clippy checkout at `clippy_path`, in that order so that runs waiting
+/// for each other can't deadlock. Fails if another run holds one of the locks, unless `wait` is
+/// set.
+pub fn acquire(clippy_path: &Path, wait: bool) -> Result<RunLock, String> {
+    let mut files = Vec::new();
+    for path in [PathBuf::from(LOGS_LOCK), clippy_path.join(CHECKOUT_LOCK)] {
+        files.push(lock(&path, wait)?);
+    }
+    Ok(RunLock { _files: files })
+}
+
+fn lock(path: &Path, wait: bool) -> Result<File, String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("couldn't create {}: {}", dir.display(), e))?;
+    }
+    let file =
+        File::create(path).map_err(|e| format!("couldn't create {}: {}", path.display(), e))?;
+    match file.try_lock() {
+        Ok(()) => return Ok(file),
+        Err(TryLockError::WouldBlock) if wait => {}
+        Err(TryLockError::WouldBlock) => {
+            return Err(format!(
+                "another run holds the lock {}, pass --wait-for-lock to queue behind it",
+                path.display()
+            ))
+        }
+        Err(TryLockError::Error(e)) => {
+            return Err(format!("couldn't lock {}: {}", path.display(), e))
+        }
+    }
+    println!("waiting for another run to release {}", path.display());
+    file.lock()
+        .map_err(|e| format!("couldn't lock {}: {}", path.display(), e))?;
+    Ok(file)
+}
+
+/// Locks the files in `logs/` that every job updates, until the guard is dropped.
+pub fn shared_files() -> MutexGuard<'static, ()> {
+    SHARED_FILES.lock().unwrap_or_else(PoisonError::into_inner)
+}
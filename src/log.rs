@@ -0,0 +1,759 @@
+//! Parser for the text logs written by lintcheck.
+//!
+//! A log looks like this:
+//!
+//! ```text
+//! # clippy_sha: e2753f9a7a1eb6b0d2c5ec36d4a1d1c1a1b0e1f2
+//! # config: integration
+//! clippy 0.1.52 (e2753f9a7 2021-02-16)
+//!
+//! cargo-0.49.0/src/bin/cargo/cli.rs:104:34 clippy::single_match_else "you seem to be ..."
+//!
+//!
+//!
+//!
+//! Stats
+//!
+//! clippy::single_match_else 1
+//! ICEs:
+//! cargo: 'thread 'rustc' panicked at ...'
+//! ```
+//!
+//! The `# key: value` lines of the header aren't written by lintcheck, but added by us to
+//! describe the run that produced the log.
+//!
+//! Lintcheck changed how it writes the warnings over time, see `Dialect`. The dialect of a log is
+//! detected from its first warning, so that logs of old and new clippy checkouts can be read and
+//! compared with each other. Logs rendered by us are always in the plain dialect.
+
+use crate::{gzip, lints::LintIndex, publish};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufWriter, Write},
+    num::NonZeroUsize,
+    path::Path,
+    str::FromStr,
+    thread,
+};
+
+/// Number of warning lines that are buffered and then parsed in parallel by `Log::read`, which
+/// bounds the memory used for the raw text of huge logs.
+const BATCH_LINES: usize = 1 << 16;
+/// Batches with fewer lines are parsed on the current thread.
+const PARALLEL_LINES: usize = 1 << 12;
+/// Prefix of the paths of the warnings of newer lintcheck versions, which are relative to the
+/// clippy checkout instead of the sources directory.
+const SOURCES_PREFIX: &str = "target/lintcheck/sources/";
+/// Number of lines shown before and after every line `clippy_lines` finds.
+const CONTEXT_LINES: usize = 2;
+/// Number of lines `clippy_lines` shows at most, the others are only counted.
+const MAX_CLIPPY_LINES: usize = 10;
+
+/// Format the warnings of a log are written in, besides the text log of lintcheck itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    /// `logs/<job>_warnings.parquet`
+    Parquet,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "parquet" => Ok(Self::Parquet),
+            err => Err(format!("Invalid format {}", err)),
+        }
+    }
+}
+
+/// Format the log of a job is written to `logs/` in, converted from the parsed log. The text log
+/// is always kept, because baselines and the subcommands read it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Only `logs/<job>_logs.txt`
+    Txt,
+    /// Also `logs/<job>_logs.json`
+    Json,
+    /// Also `logs/<job>_logs.md`
+    Markdown,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "txt" => Ok(Self::Txt),
+            "json" => Ok(Self::Json),
+            "markdown" => Ok(Self::Markdown),
+            err => Err(format!("Invalid log format {}", err)),
+        }
+    }
+}
+
+impl LogFormat {
+    /// Extension of the converted log, `None` for the text log.
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            Self::Txt => None,
+            Self::Json => Some("json"),
+            Self::Markdown => Some("md"),
+        }
+    }
+}
+
+/// The ways lintcheck wrote the warning section of its text log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// `crate-version/file:line:column clippy::lint "message"` lines followed by `Stats`
+    Plain,
+    /// The same with paths starting with `target/lintcheck/sources/`, followed by `Stats:`
+    Sources,
+    /// A table with a row per warning under `### Reports`, followed by `### Stats:`, e.g.
+    /// ``[`crate-version/file:line:column`](../target/lintcheck/sources/...) | `clippy::lint` |
+    /// "message"``
+    Markdown,
+}
+
+impl Dialect {
+    /// The dialect a line of the warning section gives away, `None` for lines that are the same
+    /// in all of them, e.g. empty ones.
+    fn detect(line: &str) -> Option<Self> {
+        if line.starts_with("### Reports") || line.starts_with("[`") || line.starts_with("| ") {
+            Some(Self::Markdown)
+        } else if line.starts_with(SOURCES_PREFIX) {
+            Some(Self::Sources)
+        } else if line.contains(" clippy::") {
+            Some(Self::Plain)
+        } else {
+            None
+        }
+    }
+
+    /// Parses a line of the warning section, `None` if it isn't a warning.
+    fn parse(self, line: &str) -> Option<Warning> {
+        match self {
+            Self::Plain => Warning::parse(line),
+            Self::Sources => Warning::parse(line.strip_prefix(SOURCES_PREFIX).unwrap_or(line)),
+            Self::Markdown => {
+                // The message is the last cell, which may contain ` | ` itself.
+                let (location, rest) = line.trim_start_matches('|').split_once(" | ")?;
+                let (lint, message) = rest.split_once(" | ")?;
+                let location = location.trim();
+                let location = match location.strip_prefix("[`") {
+                    Some(link) => link.split_once("`]")?.0,
+                    None => location.trim_matches('`'),
+                };
+                let location = location.strip_prefix(SOURCES_PREFIX).unwrap_or(location);
+                let lint = lint.trim().trim_matches('`').trim();
+                let message = message.trim().trim_end_matches('|').trim_end();
+                Warning::parse(&format!("{} {} {}", location, lint, message))
+            }
+        }
+    }
+}
+
+/// Whether `line` starts the stats section, which ends the warning section in all dialects.
+fn is_stats(line: &str) -> bool {
+    line.trim_start_matches("### ").trim_end_matches(':') == "Stats"
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Log {
+    /// The entries of the header, e.g. the commit of clippy and the hash of the config
+    pub metadata: BTreeMap<String, String>,
+    pub clippy_version: String,
+    pub warnings: Vec<Warning>,
+    pub ices: Vec<Ice>,
+    /// Whether the log ended with the (possibly empty) ICE section
+    pub complete: bool,
+    /// Number of lines in the warning section that mention `clippy::`, but couldn't be parsed
+    pub unrecognized: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Warning {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub version: String,
+    pub file: String,
+    pub line: usize,
+    pub column: Option<usize>,
+    pub lint: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Ice {
+    /// `None` if the crate couldn't be determined from the log
+    #[serde(rename = "crate")]
+    pub krate: Option<String>,
+    pub message: String,
+}
+
+impl Log {
+    /// Reads the log at `path`, which may be gzipped, see `read`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        gzip::open(path)
+            .and_then(Self::read)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))
+    }
+
+    /// Parses the log line by line, so that only the parsed warnings and not the whole text are
+    /// kept in memory. The warnings are parsed in batches, in parallel, in the dialect of the
+    /// first line that gives it away.
+    pub fn read(mut reader: impl BufRead) -> io::Result<Self> {
+        let mut log = Self::default();
+        let mut line = Vec::new();
+        let mut next_line = |line: &mut Vec<u8>| -> io::Result<Option<String>> {
+            line.clear();
+            if reader.read_until(b'\n', line)? == 0 {
+                return Ok(None);
+            }
+            Ok(Some(String::from_utf8_lossy(line).into_owned()))
+        };
+        let mut first = next_line(&mut line)?.unwrap_or_default();
+        while let Some(entry) = first.strip_prefix("# ") {
+            if let Some((key, value)) = entry.split_once(": ") {
+                log.metadata.insert(
+                    key.to_string(),
+                    value.trim_end_matches(&['\n', '\r'][..]).to_string(),
+                );
+            }
+            first = next_line(&mut line)?.unwrap_or_default();
+        }
+        log.clippy_version = first.trim_end_matches(&['\n', '\r'][..]).to_string();
+
+        let mut dialect = None;
+        let mut batch = Vec::with_capacity(BATCH_LINES);
+        while let Some(text) = next_line(&mut line)? {
+            let text = text.trim_end_matches(&['\n', '\r'][..]);
+            if is_stats(text) {
+                break;
+            }
+            dialect = dialect.or_else(|| Dialect::detect(text));
+            batch.push(text.to_string());
+            if batch.len() == BATCH_LINES {
+                log.parse_warnings(&batch, dialect.unwrap_or(Dialect::Plain));
+                batch.clear();
+            }
+        }
+        log.parse_warnings(&batch, dialect.unwrap_or(Dialect::Plain));
+
+        // The stats are recomputed from the warnings where needed, so we only look for the ICEs,
+        // which follow the last `ICEs:` line.
+        let mut ices = None;
+        while let Some(text) = next_line(&mut line)? {
+            if text.ends_with("ICEs:\n") {
+                ices = Some(String::new());
+            } else if let Some(ices) = &mut ices {
+                ices.push_str(&text);
+            }
+        }
+        if let Some(ices) = ices {
+            log.complete = true;
+            log.ices = Ice::parse_all(&ices);
+        }
+        Ok(log)
+    }
+
+    /// Parses the warnings of `lines`, spread over the available cores for big batches.
+    fn parse_warnings(&mut self, lines: &[String], dialect: Dialect) {
+        let parse = |lines: &[String]| {
+            let mut warnings = Vec::new();
+            let mut unrecognized = 0;
+            for line in lines {
+                match dialect.parse(line) {
+                    Some(warning) => warnings.push(warning),
+                    None if line.contains("clippy::") => unrecognized += 1,
+                    None => {}
+                }
+            }
+            (warnings, unrecognized)
+        };
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let parsed = if lines.len() < PARALLEL_LINES || threads == 1 {
+            vec![parse(lines)]
+        } else {
+            thread::scope(|scope| {
+                let handles: Vec<_> = lines
+                    .chunks(lines.len().div_ceil(threads))
+                    .map(|chunk| scope.spawn(move || parse(chunk)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("parsing warnings doesn't panic"))
+                    .collect()
+            })
+        };
+        for (warnings, unrecognized) in parsed {
+            self.warnings.extend(warnings);
+            self.unrecognized += unrecognized;
+        }
+    }
+
+    /// Adds the warnings and ICEs of `other`, a log of other crates, to the log.
+    pub fn merge(&mut self, other: Self) {
+        self.warnings.extend(other.warnings);
+        self.ices.extend(other.ices);
+        self.complete &= other.complete;
+        self.unrecognized += other.unrecognized;
+    }
+
+    /// Number of warnings per lint.
+    pub fn lint_counts(&self) -> BTreeMap<&str, usize> {
+        let mut counts = BTreeMap::new();
+        for warning in &self.warnings {
+            *counts.entry(warning.lint.as_str()).or_default() += 1;
+        }
+        counts
+    }
+
+    /// Renders the log in the format lintcheck writes.
+    pub fn render(&self) -> String {
+        let mut warnings: Vec<_> = self.warnings.iter().map(Warning::to_string).collect();
+        warnings.sort();
+        let mut stats: Vec<_> = self.lint_counts().into_iter().collect();
+        stats.sort_by_key(|&(lint, count)| (count, lint));
+
+        let mut text = header(&self.metadata);
+        text.push_str(&format!("{}\n\n", self.clippy_version));
+        for warning in warnings {
+            text.push_str(&warning);
+            text.push('\n');
+        }
+        text.push_str("\n\n\n\nStats\n\n");
+        for (lint, count) in stats {
+            text.push_str(&format!("{} {}\n", lint, count));
+        }
+        text.push_str("ICEs:\n");
+        for ice in &self.ices {
+            text.push_str(&format!(
+                "{}: '{}'",
+                ice.krate.as_deref().unwrap_or("unknown"),
+                ice.message
+            ));
+        }
+        text
+    }
+
+    /// Renders the log in `format`. Lints are linked to their documentation and implementation in
+    /// Markdown.
+    pub fn convert(&self, format: LogFormat, lints: &LintIndex) -> String {
+        match format {
+            LogFormat::Txt => self.render(),
+            LogFormat::Json => {
+                serde_json::to_string_pretty(self).expect("logs are serializable") + "\n"
+            }
+            LogFormat::Markdown => self.render_markdown(lints),
+        }
+    }
+
+    fn render_markdown(&self, lints: &LintIndex) -> String {
+        let cell = |text: &str| text.replace('|', "\\|").replace('\n', " ");
+        let mut warnings: Vec<_> = self.warnings.iter().collect();
+        warnings.sort_by(|a, b| {
+            (&a.krate, &a.version, &a.file, a.line).cmp(&(&b.krate, &b.version, &b.file, b.line))
+        });
+        let mut stats: Vec<_> = self.lint_counts().into_iter().collect();
+        stats.sort_by_key(|&(lint, count)| (std::cmp::Reverse(count), lint));
+
+        let mut text = format!("# {}\n\n", self.clippy_version);
+        for (key, value) in &self.metadata {
+            text.push_str(&format!("- {}: `{}`\n", key, value));
+        }
+        if !self.metadata.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(
+            "## Warnings\n\n| crate | location | lint | message |\n| --- | --- | --- | --- |\n",
+        );
+        for warning in warnings {
+            let location = match warning.column {
+                Some(column) => format!("{}:{}:{}", warning.file, warning.line, column),
+                None => format!("{}:{}", warning.file, warning.line),
+            };
+            text.push_str(&format!(
+                "| {} {} | `{}` | {} | {} |\n",
+                warning.krate,
+                warning.version,
+                location,
+                lints.markdown_link(&warning.lint),
+                cell(&warning.message)
+            ));
+        }
+        text.push_str("\n## Stats\n\n| lint | warnings |\n| --- | --- |\n");
+        for (lint, count) in stats {
+            text.push_str(&format!("| {} | {} |\n", lints.markdown_link(lint), count));
+        }
+        text.push_str("\n## ICEs\n\n");
+        if self.ices.is_empty() {
+            text.push_str("none\n");
+        }
+        for ice in &self.ices {
+            text.push_str(&format!(
+                "- {}: {}\n",
+                ice.krate.as_deref().unwrap_or("unknown"),
+                cell(&ice.message)
+            ));
+        }
+        text
+    }
+}
+
+fn header(metadata: &BTreeMap<String, String>) -> String {
+    metadata
+        .iter()
+        .map(|(key, value)| format!("# {}: {}\n", key, value))
+        .collect()
+}
+
+/// Reads the `# key: value` entries at the start of `reader`, leaving the line after them in
+/// `first`.
+fn read_header(
+    reader: &mut impl BufRead,
+    first: &mut String,
+) -> io::Result<BTreeMap<String, String>> {
+    let mut entries = BTreeMap::new();
+    loop {
+        first.clear();
+        reader.read_line(first)?;
+        match first
+            .strip_prefix("# ")
+            .and_then(|entry| entry.split_once(": "))
+        {
+            Some((key, value)) => {
+                entries.insert(key.to_string(), value.trim_end().to_string());
+            }
+            None => return Ok(entries),
+        }
+    }
+}
+
+/// The entries of the header of the log at `path`, which may be gzipped, without reading the
+/// warnings.
+pub fn metadata(path: &Path) -> io::Result<BTreeMap<String, String>> {
+    read_header(&mut gzip::open(path)?, &mut String::new())
+}
+
+/// Adds `metadata` to the header of the log at `path`, replacing entries with the same keys.
+pub fn prepend_metadata(path: &Path, metadata: &BTreeMap<String, String>) -> io::Result<()> {
+    let mut reader = io::BufReader::new(File::open(path)?);
+    let mut first = String::new();
+    let mut existing = read_header(&mut reader, &mut first)?;
+    existing.extend(metadata.clone());
+    let staged = publish::staged_path(path);
+    let mut out = BufWriter::new(File::create(&staged)?);
+    out.write_all(header(&existing).as_bytes())?;
+    out.write_all(first.as_bytes())?;
+    io::copy(&mut reader, &mut out)?;
+    out.flush()?;
+    drop(out);
+    fs::rename(staged, path)
+}
+
+/// The lines of the warnings section of the log at `path` that contain `clippy::`, numbered like
+/// `grep -n -C` with the lines around them, and their number. Meant for logs whose warnings
+/// couldn't be parsed, to show what lintcheck wrote instead.
+pub fn clippy_lines(path: &Path) -> Result<(usize, String), String> {
+    let error = |e: io::Error| format!("couldn't read {}: {}", path.display(), e);
+    let lines = gzip::open(path)
+        .map_err(error)?
+        .lines()
+        .take_while(|line| line.as_ref().map_or(true, |line| !is_stats(line)))
+        .collect::<io::Result<Vec<String>>>()
+        .map_err(error)?;
+    let found: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with("# ") && line.contains("clippy::"))
+        .map(|(i, _)| i)
+        .collect();
+    let mut text = String::new();
+    let mut shown_until = 0;
+    for &i in found.iter().take(MAX_CLIPPY_LINES) {
+        let start = i.saturating_sub(CONTEXT_LINES).max(shown_until);
+        if start > shown_until && !text.is_empty() {
+            text.push_str("--\n");
+        }
+        let end = (i + CONTEXT_LINES + 1).min(lines.len());
+        for (j, line) in lines.iter().enumerate().take(end).skip(start) {
+            let separator = if found.contains(&j) { ':' } else { '-' };
+            text.push_str(&format!("{}{}{}\n", j + 1, separator, line));
+        }
+        shown_until = end;
+    }
+    if found.len() > MAX_CLIPPY_LINES {
+        text.push_str(&format!(
+            "... and {} more line(s)\n",
+            found.len() - MAX_CLIPPY_LINES
+        ));
+    }
+    Ok((found.len(), text))
+}
+
+/// The last `count` lines of the log at `path`.
+pub fn tail(path: &Path, count: usize) -> Result<String, String> {
+    let mut tail = VecDeque::with_capacity(count + 1);
+    for line in gzip::open(path)
+        .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?
+        .lines()
+    {
+        tail.push_back(line.map_err(|e| format!("couldn't read {}: {}", path.display(), e))?);
+        if tail.len() > count {
+            tail.pop_front();
+        }
+    }
+    Ok(tail.into_iter().map(|line| line + "\n").collect())
+}
+
+/// The warnings of a log, read line by line as they are needed.
+pub fn warnings(reader: impl BufRead) -> impl Iterator<Item = io::Result<Warning>> {
+    let mut dialect = None;
+    reader
+        .split(b'\n')
+        .skip(1)
+        .map(|line| {
+            line.map(|line| {
+                String::from_utf8_lossy(&line)
+                    .trim_end_matches('\r')
+                    .to_string()
+            })
+        })
+        .take_while(|line| line.as_ref().map_or(true, |line| !is_stats(line)))
+        .filter_map(move |line| match line {
+            Ok(line) => {
+                dialect = dialect.or_else(|| Dialect::detect(&line));
+                dialect.unwrap_or(Dialect::Plain).parse(&line).map(Ok)
+            }
+            Err(e) => Some(Err(e)),
+        })
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}/{}:{}:",
+            self.krate, self.version, self.file, self.line
+        )?;
+        match self.column {
+            Some(column) => write!(f, "{}", column)?,
+            None => f.write_str("null")?,
+        }
+        write!(f, " {} \"{}\"", self.lint, self.message)
+    }
+}
+
+impl Warning {
+    /// Parses a line of the form `crate-version/file:line:column clippy::lint "message"`.
+    pub fn parse(line: &str) -> Option<Self> {
+        let (location, rest) = line.split_once(' ')?;
+        let (lint, message) = rest.split_once(' ')?;
+        if !lint.starts_with("clippy::") {
+            return None;
+        }
+        let message = message.strip_prefix('"')?.strip_suffix('"')?;
+
+        let (krate, path) = location.split_once('/')?;
+        let version_start = krate.rfind('-')?;
+        let mut position = path.rsplitn(3, ':');
+        let column = position.next()?.parse().ok();
+        let line = position.next()?.parse().ok()?;
+        let file = position.next()?;
+
+        Some(Self {
+            krate: krate[..version_start].to_string(),
+            version: krate[version_start + 1..].to_string(),
+            file: file.to_string(),
+            line,
+            column,
+            lint: lint.to_string(),
+            message: message.to_string(),
+        })
+    }
+}
+
+impl Ice {
+    /// Parses the ICE section, which lintcheck writes as `crate: 'message'` entries without any
+    /// separator between them.
+    fn parse_all(section: &str) -> Vec<Self> {
+        let mut ices = Vec::new();
+        let mut rest = section.trim();
+        while !rest.is_empty() {
+            let entry = rest
+                .split_once(':')
+                .filter(|(krate, _)| is_crate_name(krate))
+                .and_then(|(krate, rest)| Some((krate, rest.strip_prefix(" '")?)));
+            match entry {
+                Some((krate, message)) => {
+                    // The message ends at the next `'` that is followed by another entry or
+                    // the end of the section.
+                    let end = message
+                        .match_indices('\'')
+                        .map(|(i, _)| i)
+                        .find(|&i| {
+                            let next = message[i + 1..].trim_start();
+                            next.is_empty()
+                                || next.split_once(':').is_some_and(|(krate, rest)| {
+                                    is_crate_name(krate) && rest.starts_with(" '")
+                                })
+                        })
+                        .unwrap_or(message.len());
+                    ices.push(Self {
+                        krate: Some(krate.to_string()),
+                        message: message[..end].to_string(),
+                    });
+                    rest = message.get(end + 1..).unwrap_or_default().trim_start();
+                }
+                None => {
+                    ices.push(Self {
+                        krate: None,
+                        message: rest.to_string(),
+                    });
+                    break;
+                }
+            }
+        }
+        ices
+    }
+}
+
+fn is_crate_name(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Dialect, Log};
+    use std::{fs, path::PathBuf};
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/log")
+            .join(name)
+    }
+
+    fn dialect(name: &str) -> Option<Dialect> {
+        let text = fs::read_to_string(fixture(name)).unwrap();
+        text.lines().find_map(Dialect::detect)
+    }
+
+    /// The warnings of `log`, rendered in the plain dialect.
+    fn warnings(log: &Log) -> Vec<String> {
+        log.warnings.iter().map(ToString::to_string).collect()
+    }
+
+    fn ices(log: &Log) -> Vec<(Option<&str>, &str)> {
+        log.ices
+            .iter()
+            .map(|ice| (ice.krate.as_deref(), ice.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(dialect("plain_logs.txt"), Some(Dialect::Plain));
+        let log = Log::load(fixture("plain_logs.txt")).unwrap();
+        assert_eq!(
+            log.metadata.get("config").map(String::as_str),
+            Some("integration")
+        );
+        assert_eq!(log.clippy_version, "clippy 0.1.52 (e2753f9a7 2021-02-16)");
+        assert_eq!(
+            warnings(&log),
+            [
+                "cargo-0.49.0/src/bin/cargo/cli.rs:104:34 clippy::single_match_else \"you seem to \
+                 be trying to use `match` for destructuring a single pattern. Consider using \
+                 `if let`\"",
+                "cargo-0.49.0/src/cargo/core/compiler/build_config.rs:171:5 \
+                 clippy::must_use_candidate \"this method could have a `#[must_use]` attribute\"",
+                "rayon-1.5.0/src/iter/mod.rs:2350:null clippy::missing_panics_doc \"docs for \
+                 function which may panic missing `# Panics` section\"",
+            ]
+        );
+        assert_eq!(
+            ices(&log),
+            [(
+                Some("syn"),
+                "thread 'rustc' panicked at 'index out of bounds', clippy_lints/src/loops.rs:42:9"
+            )]
+        );
+        assert!(log.complete);
+        assert_eq!(log.unrecognized, 0);
+    }
+
+    #[test]
+    fn sources() {
+        assert_eq!(dialect("sources_logs.txt"), Some(Dialect::Sources));
+        let log = Log::load(fixture("sources_logs.txt")).unwrap();
+        assert!(log.metadata.is_empty());
+        assert_eq!(
+            warnings(&log),
+            [
+                "anyhow-1.0.38/src/error.rs:350:5 clippy::missing_errors_doc \"docs for function \
+                 returning `Result` missing `# Errors` section\"",
+                "anyhow-1.0.38/src/lib.rs:1:null clippy::cargo_common_metadata \"package `anyhow` \
+                 is missing `package.keywords` metadata\"",
+                "regex-1.3.2/src/re_bytes.rs:1051:9 clippy::match_same_arms \"this match arm has \
+                 an identical body to another arm\"",
+            ]
+        );
+        assert!(log.ices.is_empty());
+        assert!(log.complete);
+        assert_eq!(log.unrecognized, 0);
+    }
+
+    #[test]
+    fn markdown() {
+        assert_eq!(dialect("markdown_logs.txt"), Some(Dialect::Markdown));
+        let log = Log::load(fixture("markdown_logs.txt")).unwrap();
+        assert_eq!(
+            warnings(&log),
+            [
+                "serde-1.0.147/src/de/mod.rs:1234:17 clippy::needless_lifetimes \"explicit \
+                 lifetimes given in parameter types where they could be elided (or replaced with \
+                 `'_` if needed by type declaration)\"",
+                "tokio-1.21.2/src/runtime/mod.rs:88:1 clippy::doc_markdown \"item in \
+                 documentation is missing backticks: `a | b`\"",
+            ]
+        );
+        assert_eq!(
+            ices(&log),
+            [
+                (
+                    Some("tokio"),
+                    "thread 'rustc' panicked at 'called `Option::unwrap()` on a `None` value'"
+                ),
+                (
+                    Some("futures-util"),
+                    "error: internal compiler error: unexpected panic"
+                ),
+            ]
+        );
+        assert!(log.complete);
+        assert_eq!(log.unrecognized, 0);
+    }
+
+    #[test]
+    fn truncated() {
+        for name in ["plain_logs.txt", "sources_logs.txt", "markdown_logs.txt"] {
+            let text = fs::read_to_string(fixture(name)).unwrap();
+            let stats = text.find("Stats").unwrap();
+            let full = Log::read(text.as_bytes()).unwrap();
+            let log = Log::read(&text.as_bytes()[..stats]).unwrap();
+            assert!(!log.complete, "{}", name);
+            assert_eq!(warnings(&log), warnings(&full), "{}", name);
+            assert!(log.ices.is_empty(), "{}", name);
+        }
+    }
+}
@@ -1,30 +1,604 @@
+use badge::Badge;
+use clippy_conf::ClippyConf;
+use coverage::Coverage;
+use crates::CratesConfig;
+use events::Event;
+use failure::{ErrorFormat, Failure, FailureKind};
+use fp_report::ReportFormat;
+use github::{State, Statuses};
+use job::{Expect, Job, Policy, RunOptions};
+use lint_policy::LintPolicy;
+use lintcheck::SoftErrors;
+use lints::LintIndex;
+use log::Log;
+use merge::Merged;
+use metadata::RunMetadata;
+use pattern::Regex;
+use plan::PlanFormat;
+use result_cache::ResultCache;
+use run_lock::RunLock;
+use run_summary::RunSummary;
+use sample::Sample;
+use shard::Shard;
 use std::{
+    convert::TryFrom,
+    ffi::OsString,
     fs,
-    io::Write,
+    io::{self, Write},
     path::{Path, PathBuf},
-    process::{Command, Stdio},
+    process,
     str::FromStr,
+    time::{Duration, Instant},
 };
 use structopt::StructOpt;
-use tempfile::NamedTempFile;
+use triage::Verdicts;
+use wrapper_config::{Checkout, Registries, WrapperConfig};
 
-#[derive(Debug, StructOpt)]
+mod actions;
+mod badge;
+mod build;
+mod build_failures;
+mod chart;
+mod ci;
+mod ci_format;
+mod clippy;
+mod clippy_conf;
+mod code_search;
+mod container;
+mod coverage;
+mod crates;
+mod crates_io;
+mod dead_crates;
+mod diff;
+mod disk;
+mod distribute;
+mod events;
+mod failure;
+mod features;
+mod fingerprint;
+mod fix;
+mod fp_report;
+mod full;
+mod github;
+mod glob;
+mod golden;
+mod gzip;
+mod history;
+mod hooks;
+mod ice_report;
+mod interrupt;
+mod job;
+mod lint_policy;
+mod lintcheck;
+mod lints;
+mod lock;
+mod log;
+mod merge;
+mod metadata;
+mod msrv;
+mod pages;
+mod parquet;
+mod pattern;
+mod plan;
+mod plugin;
+mod priority;
+mod progress;
+mod publish;
+mod remote;
+mod report;
+mod result_cache;
+mod results;
+mod run_lock;
+mod run_summary;
+mod sample;
+mod sandbox;
+mod shard;
+mod slowest;
+mod snippet;
+mod stability;
+mod stable;
+mod status;
+mod structural_diff;
+mod summary;
+mod suppress;
+mod triage;
+mod update;
+mod versions;
+mod workspace;
+mod wrapper_config;
+
+/// Where the evaluate subcommand keeps the logs of the merge-base.
+const EVALUATE_BASE_DIR: &str = "logs/evaluate/base";
+
+#[derive(Debug, Clone, StructOpt)]
 #[structopt(
     name = "clippy-lintcheck",
-    about = "Run the clippy-lintcheck tool on the configurations"
+    about = "Run the clippy-lintcheck tool on the configurations",
+    after_help = failure::EXIT_CODES_HELP
 )]
 struct Opt {
-    /// Check all configuration files. Available options: "all", "passes", "integration", "ci"
-    #[structopt(long, required = true)]
-    mode: Mode,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+    /// Check all configuration files. Available options: "all", "passes", "integration", "ci",
+    /// "stable", "msrv", "top", "full". "top" checks the most downloaded crates of crates.io, see
+    /// --top-crates. "full" checks several thousand of them in resumable batches and writes a
+    /// regression report against --baseline to logs/full_regressions.md, see --full-crates.
+    /// Required unless a subcommand is given
+    #[structopt(long)]
+    mode: Option<Mode>,
+    /// Abort all pending work on the first failure
+    #[structopt(long, conflicts_with = "keep-going")]
+    fail_fast: bool,
+    /// Run every configuration and report all failures at the end
+    #[structopt(long)]
+    keep_going: bool,
+    /// Format of the failure report. Available options: "human", "json"
+    #[structopt(long, default_value = "human")]
+    error_format: ErrorFormat,
+    /// Git ref the configs are diffed against in "ci" mode. Defaults to the target branch from
+    /// `GITHUB_BASE_REF` or `CI_MERGE_REQUEST_TARGET_BRANCH_NAME`, or "origin/main"
+    #[structopt(long)]
+    base_ref: Option<String>,
+    /// Git ref of the clippy checkout that lints are compared against in "ci" mode. The crates
+    /// of config/new_lints.toml are added to the integration config if the checkout adds lints.
+    /// The evaluate subcommand runs on the merge-base with it
+    #[structopt(long, default_value = "origin/master")]
+    clippy_base_ref: String,
+    /// Fetch and check out this ref in the clippy checkout before the run [default:
+    /// origin/master]
+    #[structopt(long)]
+    update_clippy: Option<Option<String>>,
+    /// Fetch and check out this pull request of the origin of the clippy checkout, e.g.
+    /// rust-lang/rust-clippy, before the run. Its number is recorded in the run metadata
+    #[structopt(long, conflicts_with = "update-clippy")]
+    clippy_pr: Option<u64>,
+    /// Which ref of --clippy-pr is checked out, "head" for its last commit or "merge" for it
+    /// merged into its base branch. Available options: "head", "merge"
+    #[structopt(long, default_value = "head")]
+    clippy_pr_ref: update::PrRef,
+    /// Update the clippy checkout with --update-clippy or --clippy-pr even if it has
+    /// uncommitted changes
+    #[structopt(long)]
+    allow_dirty: bool,
+    /// Wait for other runs to finish instead of failing when they hold the locks on the logs or
+    /// the clippy checkout
+    #[structopt(long)]
+    wait_for_lock: bool,
+    /// Name of the clippy checkout registered in the wrapper config to run lintcheck in
+    #[structopt(long)]
+    clippy: Option<String>,
+    /// Run lintcheck in the clippy checkout at this path instead, with the toolchain and cargo
+    /// alias of the checkout of --clippy and the target dir inside of it
+    #[structopt(long, parse(from_os_str))]
+    clippy_path: Option<PathBuf>,
+    /// Cargo alias lintcheck is run with, overriding the one of the wrapper config and the one
+    /// detected from the checkout. Available options: "dev-lintcheck", "lintcheck"
+    #[structopt(long)]
+    lintcheck: Option<lintcheck::EntryPoint>,
+    /// Path of the wrapper config [default: clippy-lintcheck.toml]
+    #[structopt(long, parse(from_os_str))]
+    wrapper_config: Option<PathBuf>,
+    /// Toolchain whose clippy the integration config is compared against in "stable" mode and
+    /// that crates failing to build are tried with
+    #[structopt(long, default_value = "stable")]
+    stable_toolchain: String,
+    /// Apply the machine applicable suggestions to every crate and fail if the lints still fire
+    /// on the fixed code
+    #[structopt(long)]
+    verify_fixes: bool,
+    /// Lint the crates of every config a second time with the same clippy and report the
+    /// findings that differ between the two runs in logs/<config>_unstable.txt. Unstable new
+    /// warnings don't count against the new warning budgets
+    #[structopt(long)]
+    stability_check: bool,
+    /// Write the parsed logs of the configs that must pass to their golden files
+    /// config/<config>.golden.json instead of comparing them with the golden files
+    #[structopt(long)]
+    bless: bool,
+    /// Directory with logs of a previous run to diff the new logs against
+    #[structopt(long, parse(from_os_str))]
+    baseline: Option<PathBuf>,
+    /// Lint policy with the allowed change of the warnings per lint and group compared to
+    /// --baseline [default: policy.toml]
+    #[structopt(long, parse(from_os_str))]
+    policy: Option<PathBuf>,
+    /// Only include these lint groups in summaries and diffs, e.g. "style,perf"
+    #[structopt(long, use_delimiter = true)]
+    groups: Vec<String>,
+    /// Config used instead of config/integration.toml, either a path or an http(s) URL
+    #[structopt(long)]
+    config: Option<String>,
+    /// SHA-256 the config downloaded from the `--config` URL must have
+    #[structopt(long, requires = "config")]
+    config_sha256: Option<String>,
+    /// Fail if lintcheck couldn't check more than this many crates, e.g. because they failed to
+    /// download or build
+    #[structopt(long)]
+    max_skipped_crates: Option<usize>,
+    /// What to do about errors lintcheck prints to stdout but recovers from, e.g. a crate that
+    /// failed to download, while exiting successfully. Available options: "fail", "warn"
+    #[structopt(long, default_value = "fail")]
+    soft_errors: SoftErrors,
+    /// Run lintcheck without network access and with a read-only filesystem outside of the
+    /// clippy checkout, using bubblewrap. The crates are downloaded before
+    #[structopt(long)]
+    sandbox: bool,
+    /// The msrvs the integration config is linted with in "msrv" mode, e.g. "1.56,1.63,1.70".
+    /// Lints whose warnings differ between them are written to logs/<config>_msrv.txt
+    #[structopt(long, use_delimiter = true)]
+    msrv: Vec<String>,
+    /// Lint with this clippy.toml, or the clippy.toml in this directory, instead of the ones of
+    /// the crates, e.g. to see how a configuration value changes the results
+    #[structopt(long, parse(from_os_str))]
+    clippy_conf: Option<PathBuf>,
+    /// Delete the least recently used build artifacts of lintcheck after the run until they take
+    /// at most this many GB
+    #[structopt(long)]
+    prune_target: Option<f64>,
+    /// Number of runs, including this one, over which lints with steadily rising warning counts
+    /// or a high churn of their findings are warned about. The counts of every run are kept in
+    /// logs/history.json
+    #[structopt(long, default_value = "5")]
+    trend_runs: usize,
+    /// Check the crates of a config in batches of this many crates, so that a failing batch
+    /// doesn't lose the results of the others. Each batch writes logs/<config>_batch<i>_logs.txt
+    #[structopt(long)]
+    batch_size: Option<usize>,
+    /// Continue an interrupted batched run: the batches it finished, which are recorded in
+    /// logs/<config>_progress.json, aren't checked again and their logs are reused. Requires
+    /// --batch-size, except in "full" mode, which is always batched
+    #[structopt(long)]
+    resume: bool,
+    /// Reuse the results of crates that were checked with the same clippy commit, toolchain and
+    /// clippy config, which are kept in this directory [default: target/result-cache]. Not used
+    /// if the clippy checkout has uncommitted changes. Always used in "full" mode
+    #[structopt(long)]
+    result_cache: Option<Option<PathBuf>>,
+    /// Only check the crates of this shard, e.g. "2/8" for the second of eight CI jobs. Crates
+    /// are assigned to shards by their name. The jobs and failures of the shard are written to
+    /// logs/shard.json for the `aggregate` subcommand
+    #[structopt(long)]
+    shard: Option<Shard>,
+    /// Only check a random sample of this many crates of every config, picked before the shard.
+    /// The seed of the sample is recorded in logs/run_metadata.json
+    #[structopt(long)]
+    sample: Option<usize>,
+    /// Seed of --sample, to check a sample of an earlier run again [default: random]
+    #[structopt(long, requires = "sample")]
+    seed: Option<u64>,
+    /// Number of the most downloaded crates of crates.io "top" mode checks
+    #[structopt(long, default_value = "1000")]
+    top_crates: usize,
+    /// Number of the most downloaded crates of crates.io "full" mode checks
+    #[structopt(long, default_value = "5000")]
+    full_crates: usize,
+    /// Only check the crates with these names, e.g. "serde,rand" for the CI job of a matrix of
+    /// crates. Configs without any of them check no crates
+    #[structopt(long, use_delimiter = true)]
+    crates: Vec<String>,
+    /// Gzip the logs, writing logs/<config>_logs.txt.gz instead of logs/<config>_logs.txt.
+    /// Gzipped logs are read wherever logs are read, e.g. in --baseline
+    #[structopt(long)]
+    gzip_logs: bool,
+    /// Format the warnings are written in, besides the text logs. Available options: "text",
+    /// "parquet", which writes logs/<config>_warnings.parquet
+    #[structopt(long, default_value = "text")]
+    format: log::Format,
+    /// Format the logs are written to logs/ in. Available options: "txt", "json", which also
+    /// writes logs/<config>_logs.json, "markdown", which also writes logs/<config>_logs.md. The
+    /// text logs are always written, because baselines are read from them
+    #[structopt(long, default_value = "txt")]
+    log_format: log::LogFormat,
+    /// Add the lines of code every warning points at to the Markdown logs of --log-format
+    /// markdown, read from the crate sources lintcheck extracted
+    #[structopt(long)]
+    snippets: bool,
+    /// Append a record of every warning to logs/results.jsonl, in addition to the record of
+    /// each run
+    #[structopt(long)]
+    results_warnings: bool,
+    /// Also write the shields.io badge of the run, which is always written to logs/badge.json,
+    /// to this file
+    #[structopt(long, parse(from_os_str))]
+    badge_output: Option<PathBuf>,
+    /// Don't build clippy before the jobs, e.g. because it is already built. Lintcheck still
+    /// builds it if it is out of date
+    #[structopt(long)]
+    skip_build: bool,
+    /// Run in a container of this Docker or Podman image, which must have a toolchain that can
+    /// build clippy. The working directory and the clippy checkout are mounted at the same paths
+    #[structopt(long)]
+    in_container: Option<String>,
+    /// Container engine --in-container uses. Available options: "docker", "podman" [default:
+    /// docker if it is installed, else podman]
+    #[structopt(long, requires = "in-container")]
+    container_engine: Option<container::Engine>,
+    /// Run on this machine over SSH, e.g. "user@build-server". The working directory and the
+    /// clippy checkout are synced to it with rsync and logs/ is synced back after the run. Paths
+    /// in the arguments must be relative and inside of the working directory
+    #[structopt(long, conflicts_with = "in-container")]
+    remote: Option<String>,
+    /// Directory on the --remote machine the run happens in, relative to the home directory. The
+    /// workers of --distributed get "<dir>-<i>"
+    #[structopt(long, default_value = "clippy-lintcheck")]
+    remote_dir: String,
+    /// Split the crates into shards and run them on the `workers` of the wrapper config over SSH,
+    /// like with --remote. The logs of the shards are collected in logs/workers/ and aggregated
+    /// into logs/
+    #[structopt(long, conflicts_with_all = &["shard", "remote", "in-container"])]
+    distributed: bool,
+    /// Number of shards --distributed splits the crates into. Workers take the next shard once
+    /// they are done [default: twice the number of workers]
+    #[structopt(long, requires = "distributed")]
+    distributed_shards: Option<usize>,
+    /// Run with this niceness, from 0 for the normal CPU priority to 19 for the lowest. Lintcheck
+    /// and everything else the run starts inherit it
+    #[structopt(long)]
+    nice: Option<i32>,
+    /// Run with the lowest CPU priority, unless --nice is given, and the idle IO priority, so that
+    /// the machine stays usable during the run
+    #[structopt(long)]
+    low_priority: bool,
+    /// Run the jobs of the mode, e.g. passes and integration in "all" mode, concurrently, each
+    /// with its own target dir in <target>/concurrent/. Every job runs to its end, even with
+    /// --fail-fast
+    #[structopt(long)]
+    concurrent: bool,
+    /// POST the ICEs of the run, each ICE of a crate once, as JSON to this URL
+    #[structopt(long)]
+    report_ices: Option<String>,
+    /// Wrap the output of every config in a collapsible section and annotate the build with the
+    /// failures, using the service messages of a CI system. Available options: "buildkite",
+    /// "teamcity", "azure". Sections are only written if the configs don't run --concurrent
+    #[structopt(long)]
+    ci_format: Option<ci_format::CiFormat>,
+    /// Set the commit statuses lintcheck/<config> on the tested clippy commit in this GitHub
+    /// repository, e.g. "rust-lang/rust-clippy", with the token in `GITHUB_TOKEN`
+    #[structopt(long)]
+    github_status: Option<String>,
+    /// URL of the uploaded report the commit statuses link to
+    #[structopt(long, requires = "github-status")]
+    report_url: Option<String>,
+    /// Stream newline-delimited JSON events to this file, or to this file descriptor if it is a
+    /// number, while the run progresses: run-started, crate-started, crate-finished,
+    /// ice-detected and run-finished
+    #[structopt(long)]
+    events: Option<String>,
+    /// Write a JSON summary of the run to this file: its status and exit code, the warning and
+    /// ICE counts, new warnings and duration of every config, the files written to logs/ and the
+    /// failures. Written even if the run fails
+    #[structopt(long, parse(from_os_str))]
+    summary_out: Option<PathBuf>,
+    /// Write the failure report to this file instead of stderr, gzipped if it ends with .gz
+    #[structopt(long, parse(from_os_str))]
+    error_output: Option<PathBuf>,
+    /// Set by `evaluate` for the run of the merge-base, see `RunOptions::baseline_only`
+    #[structopt(skip)]
+    baseline_only: bool,
 }
 
-#[derive(Debug, StructOpt)]
+#[derive(Debug, Clone, StructOpt)]
+enum Command {
+    /// Inspect which crates triggered which lints in previous runs
+    Coverage(CoverageCommand),
+    /// Maintain the crate configs
+    Config(ConfigCommand),
+    /// Push the reports and charts of the last run to the pages branch of the wrapper config and
+    /// add the run to its index
+    Publish,
+    /// Work with logs of previous runs
+    Logs(LogsCommand),
+    /// Print the crates --mode would check as a matrix of CI jobs, one per crate, to be run
+    /// with --crates <crate>, or one per shard, to be run with --shard <shard>
+    Plan {
+        /// Format of the matrix. Available options: "github-matrix"
+        #[structopt(long, default_value = "github-matrix")]
+        format: PlanFormat,
+        /// Split the crates into this many shards instead of one job per crate
+        #[structopt(long)]
+        shards: Option<usize>,
+        /// Write the matrix to this file instead of stdout
+        #[structopt(long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Run --mode on the merge-base of a clippy pull request or ref with --clippy-base-ref and
+    /// on its head, and report the warnings the change adds and removes. The logs of the
+    /// merge-base are kept in logs/evaluate/base
+    Evaluate {
+        /// Number of the pull request, which is fetched from the origin of the clippy checkout
+        #[structopt(long, required_unless = "git-ref")]
+        pr: Option<u64>,
+        /// Git ref of the clippy checkout to evaluate instead of a pull request, e.g. a branch
+        #[structopt(long = "ref", conflicts_with = "pr")]
+        git_ref: Option<String>,
+    },
+    /// Print the phase, the progress of every config and the estimated remaining time of the
+    /// run in progress
+    Status {
+        /// The socket the run serves its status on
+        #[structopt(long, parse(from_os_str), default_value = status::SOCKET_PATH)]
+        socket: PathBuf,
+    },
+    /// Walk the new findings of a config compared to --baseline, or all of its findings without
+    /// a baseline, and give each a verdict and a note. Findings that have a verdict are skipped
+    Triage {
+        /// The config whose log in logs/ is triaged, e.g. "integration"
+        config: String,
+        /// Where the verdicts are kept between runs
+        #[structopt(long, parse(from_os_str), default_value = triage::VERDICTS_PATH)]
+        verdicts: PathBuf,
+        /// Also show the findings that have a verdict, to revise it
+        #[structopt(long)]
+        all: bool,
+    },
+    /// Report the verdicts of triage per lint, with the false positive rate and examples of
+    /// false positives, e.g. for a clippy issue about moving a lint to another group
+    TriageReport {
+        /// Format of the report. Available options: "markdown", "json"
+        #[structopt(long, default_value = "markdown")]
+        format: ReportFormat,
+        /// Only report these lints, e.g. "clippy::needless_return"
+        #[structopt(long)]
+        lint: Vec<String>,
+        /// Maximum number of false positives shown per lint
+        #[structopt(long, default_value = "5")]
+        examples: usize,
+        /// Where the verdicts are kept
+        #[structopt(long, parse(from_os_str), default_value = triage::VERDICTS_PATH)]
+        verdicts: PathBuf,
+        /// Write the report to this file instead of stdout
+        #[structopt(long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Run `clippy-lintcheck-<name>` from the PATH for any other subcommand `<name>`, with the
+    /// global options in its environment, see `plugin`
+    #[structopt(external_subcommand)]
+    External(Vec<OsString>),
+    /// Merge the logs of all shards of a sharded run into logs/ and report the failures of all
+    /// shards. With "--mode full --baseline <dir>" the regression report of the full run is
+    /// written as well
+    Aggregate {
+        /// The logs directories of the shards
+        #[structopt(parse(from_os_str), required = true)]
+        dirs: Vec<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, StructOpt)]
+enum ConfigCommand {
+    /// Bump every crate that pins a single crates.io version to its latest release that isn't
+    /// yanked
+    UpdateVersions {
+        /// Path of the config
+        #[structopt(long, parse(from_os_str), default_value = "config/integration.toml")]
+        config: PathBuf,
+        /// Only show the changes
+        #[structopt(long)]
+        dry_run: bool,
+    },
+    /// Fail if the config pins crates.io versions that were yanked, suggesting replacements
+    CheckYanked {
+        /// Path of the config
+        #[structopt(long, parse(from_os_str), default_value = "config/integration.toml")]
+        config: PathBuf,
+    },
+    /// Propose to remove the crates of a config that were broken, i.e. didn't download or
+    /// compile, in the last runs of its job, or to bump them to a newer release on crates.io.
+    /// Writes a diff of the config and a commit message justifying the changes to logs/
+    DeadCrates {
+        /// Path of the config
+        #[structopt(long, parse(from_os_str), default_value = "config/integration.toml")]
+        config: PathBuf,
+        /// Name of the job that runs the config [default: the name of the config file]
+        #[structopt(long)]
+        job: Option<String>,
+        /// Number of consecutive runs a crate must have been broken in
+        #[structopt(long, default_value = "3")]
+        runs: usize,
+    },
+    /// Generate a config of the dependencies of a cargo workspace, pinned to the versions of its
+    /// lock file. Dev-dependencies and crates of other registries than crates.io are left out
+    FromWorkspace {
+        /// The directory of the workspace or its Cargo.toml
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+        /// Only include dependencies up to this many levels below the workspace members, 1 for
+        /// the direct dependencies [default: all]
+        #[structopt(long)]
+        depth: Option<usize>,
+        /// Write the config to this file instead of stdout
+        #[structopt(long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Generate a config of the most downloaded crates on crates.io that depend on a crate, at
+    /// their latest version that does, to test lints about the API of that crate
+    ReverseDeps {
+        /// Name of the crate on crates.io
+        #[structopt(name = "crate")]
+        krate: String,
+        /// Number of dependent crates
+        #[structopt(long, default_value = "100")]
+        top: usize,
+        /// Write the config to this file instead of stdout
+        #[structopt(long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, StructOpt)]
+enum LogsCommand {
+    /// Print the number of warnings per lint and per crate and the ICEs of a log
+    Summary {
+        /// The log, which may be gzipped
+        #[structopt(parse(from_os_str))]
+        log: PathBuf,
+    },
+    /// Merge logs, e.g. of the shards of a run or of several configs, into one set of warnings
+    /// and ICEs without duplicates that lists the logs every finding occurs in
+    Merge {
+        /// The logs to merge, which may be gzipped
+        #[structopt(parse(from_os_str), required = true)]
+        logs: Vec<PathBuf>,
+        /// Where the merged findings are written to as JSON
+        #[structopt(long, parse(from_os_str), default_value = merge::MERGED_PATH)]
+        output: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, StructOpt)]
+enum CoverageCommand {
+    /// Propose crates to add to a config for a lint that few of its crates trigger
+    Suggest {
+        /// Name of the lint, with or without the `clippy::` prefix
+        #[structopt(long)]
+        lint: String,
+        /// Name of the configuration in `config/` whose coverage is checked
+        #[structopt(long, default_value = "integration")]
+        config: String,
+    },
+    /// Record the warnings of existing lintcheck logs, e.g. of older runs
+    Learn {
+        #[structopt(parse(from_os_str), required = true)]
+        logs: Vec<PathBuf>,
+    },
+    /// Propose crates for a lint by searching the crate sources on this machine for lines
+    /// matching a pattern of the code the lint triggers on, besides the crates that triggered it
+    /// before
+    Search {
+        /// Name of the lint, with or without the `clippy::` prefix
+        #[structopt(long)]
+        lint: String,
+        /// Regular expression matching the lines of code the lint triggers on, e.g.
+        /// "\.iter\(\)\.count\(\)"
+        #[structopt(long)]
+        pattern: String,
+        /// Name of the configuration in `config/` whose crates aren't proposed
+        #[structopt(long, default_value = "integration")]
+        config: String,
+        /// Also search these directories of extracted crates, named `<name>-<version>`, besides
+        /// the lintcheck sources of the checkout and the registry sources of cargo
+        #[structopt(long, parse(from_os_str))]
+        sources: Vec<PathBuf>,
+    },
+    /// List the lints of the clippy checkout that no crate triggered in the recorded runs, by
+    /// group, restricted to --groups if given
+    Gaps {
+        /// Also write the list to this file
+        #[structopt(long, parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, StructOpt)]
 enum Mode {
     All,
     Passes,
     Integration,
     CI,
+    Stable,
+    Msrv,
+    Top,
+    Full,
 }
 
 impl FromStr for Mode {
@@ -36,104 +610,1212 @@ impl FromStr for Mode {
             "passes" => Ok(Self::Passes),
             "integration" => Ok(Self::Integration),
             "ci" => Ok(Self::CI),
+            "stable" => Ok(Self::Stable),
+            "msrv" => Ok(Self::Msrv),
+            "top" => Ok(Self::Top),
+            "full" => Ok(Self::Full),
             err => Err(format!("Invalid option {}", err)),
         }
     }
 }
 
-fn check(clippy_path: &Path, config: &Path, output: Option<&str>) {
-    let lintcheck_output = Command::new("cargo")
-        .arg("dev-lintcheck")
-        .env("LINTCHECK_TOML", config)
-        .current_dir(clippy_path)
-        .output()
-        .expect("couldn't execute lintcheck tool");
-    if !lintcheck_output.status.success() {
-        panic!(
-            "cargo dev-lintcheck exited with {}\nstderr:\n{:?}",
-            lintcheck_output.status,
-            String::from_utf8_lossy(&lintcheck_output.stderr),
+/// The clippy checkout of `--clippy`, or the copy of it at `--clippy-path`.
+fn checkout(opt: &Opt, config: &WrapperConfig) -> Result<Checkout, String> {
+    let clippy = config.checkout(opt.clippy.as_deref())?;
+    match &opt.clippy_path {
+        Some(path) => clippy.at(path),
+        None => Ok(clippy),
+    }
+}
+
+/// The integration job over `--config` if given, named after the file of the config.
+fn integration_job(opt: &Opt, registries: &Registries) -> Result<Job, Failure> {
+    match &opt.config {
+        Some(location) => {
+            let name = location
+                .rsplit('/')
+                .next()
+                .and_then(|file| file.rsplit_once('.'))
+                .map(|(name, _)| name)
+                .filter(|name| !name.is_empty())
+                .unwrap_or("config");
+            Job::from_config(
+                name,
+                location,
+                opt.config_sha256.as_deref(),
+                Expect::Integration,
+                registries,
+            )
+        }
+        None => Job::new("integration", Expect::Integration, registries),
+    }
+}
+
+/// The sample of `--sample`. `main` draws the seed if `--seed` isn't given.
+fn sample(opt: &Opt) -> Option<Sample> {
+    opt.sample.map(|size| Sample {
+        size,
+        seed: opt.seed.expect("the seed is drawn in main"),
+    })
+}
+
+/// The jobs of `mode`, restricted to the crates of `--sample`, `--shard` and `--crates`.
+fn jobs(
+    opt: &Opt,
+    mode: &Mode,
+    clippy: &Checkout,
+    registries: &Registries,
+) -> Result<Vec<Job>, Failure> {
+    let jobs = match mode {
+        Mode::All => vec![
+            integration_job(opt, registries),
+            Job::new("passes", Expect::Passes, registries),
+        ]
+        .into_iter()
+        .collect(),
+        Mode::Passes => Job::new("passes", Expect::Passes, registries).map(|job| vec![job]),
+        Mode::Integration => integration_job(opt, registries).map(|job| vec![job]),
+        Mode::Stable => {
+            integration_job(opt, registries).map(|job| vec![job.with_stable(&opt.stable_toolchain)])
+        }
+        Mode::Msrv if opt.msrv.is_empty() => Err(Failure::new(
+            FailureKind::Environment,
+            "setup",
+            "--mode msrv requires --msrv",
+        )),
+        Mode::Top => Job::top("top", opt.top_crates, registries).map(|job| vec![job]),
+        Mode::Full => Job::top(full::JOB_NAME, opt.full_crates, registries).map(|job| vec![job]),
+        Mode::Msrv => opt
+            .msrv
+            .iter()
+            .map(|msrv| integration_job(opt, registries).map(|job| job.with_msrv(msrv)))
+            .collect(),
+        Mode::CI => ci::resolve_base_ref(opt.base_ref.as_deref())
+            .map_err(|message| Failure::new(FailureKind::Environment, "ci", message))
+            .and_then(|base_ref| {
+                let new_lints = ci::new_lints(clippy, &opt.clippy_base_ref)
+                    .and_then(|new_lints| ci::new_lints_config(&new_lints))
+                    .map_err(|message| Failure::new(FailureKind::Environment, "ci", message))?;
+                Ok(vec![
+                    Job::ci(
+                        "passes",
+                        Expect::Passes,
+                        &base_ref,
+                        &CratesConfig::default(),
+                        registries,
+                    )?,
+                    Job::ci(
+                        "integration",
+                        Expect::Integration,
+                        &base_ref,
+                        &new_lints,
+                        registries,
+                    )?,
+                ])
+            }),
+    };
+    let jobs = jobs.and_then(|jobs| match sample(opt) {
+        Some(sample) => jobs
+            .into_iter()
+            .map(|job| job.with_sample(sample, registries))
+            .collect(),
+        None => Ok(jobs),
+    });
+    let jobs = jobs.and_then(|jobs| match opt.shard {
+        Some(shard) => jobs
+            .into_iter()
+            .map(|job| job.with_shard(shard, registries))
+            .collect(),
+        None => Ok(jobs),
+    });
+    jobs.and_then(|jobs| match opt.crates.as_slice() {
+        [] => Ok(jobs),
+        names => jobs
+            .into_iter()
+            .map(|job| job.with_crates(names, registries))
+            .collect(),
+    })
+}
+
+fn run_coverage(opt: &Opt, command: &CoverageCommand) -> Result<(), Failure> {
+    let error = |message| Failure::new(FailureKind::Environment, "coverage", message);
+    let path = Path::new(coverage::COVERAGE_PATH);
+    let mut coverage = Coverage::load(path).map_err(error)?;
+    match command {
+        CoverageCommand::Suggest { lint, config } => {
+            let config =
+                CratesConfig::resolve(&format!("config/{}.toml", config), None).map_err(error)?;
+            print!("{}", coverage.suggest(lint, &config));
+            Ok(())
+        }
+        CoverageCommand::Learn { logs } => {
+            for log in logs {
+                coverage.record(&Log::load(log).map_err(error)?);
+            }
+            coverage.save(path).map_err(error)
+        }
+        CoverageCommand::Search {
+            lint,
+            pattern,
+            config,
+            sources,
+        } => {
+            let pattern = Regex::try_from(pattern.clone()).map_err(error)?;
+            let config =
+                CratesConfig::resolve(&format!("config/{}.toml", config), None).map_err(error)?;
+            print!("{}", coverage.suggest(lint, &config));
+            let mut roots = sources.clone();
+            if let Ok(clippy) = WrapperConfig::load(opt.wrapper_config.as_deref())
+                .and_then(|wrapper_config| checkout(opt, &wrapper_config))
+            {
+                roots.extend(code_search::default_roots(&clippy.path));
+            }
+            let candidates = code_search::search(&roots, &pattern, |name| {
+                config.crates.values().any(|source| source.name == name)
+            });
+            if candidates.is_empty() {
+                println!("no other crate on this machine matches `{}`", pattern);
+            } else {
+                println!(
+                    "{} other crate(s) on this machine match `{}`:",
+                    candidates.len(),
+                    pattern
+                );
+                for candidate in candidates.iter().take(coverage::MAX_SUGGESTIONS) {
+                    println!(
+                        "    {} {}: {} line(s), e.g. {}",
+                        candidate.name, candidate.version, candidate.matches, candidate.example
+                    );
+                }
+            }
+            Ok(())
+        }
+        CoverageCommand::Gaps { output } => {
+            let wrapper_config =
+                WrapperConfig::load(opt.wrapper_config.as_deref()).map_err(error)?;
+            let clippy = checkout(opt, &wrapper_config).map_err(error)?;
+            let lints = LintIndex::load(&clippy.path);
+            if lints.is_empty() {
+                return Err(error(format!(
+                    "found no lints in the clippy checkout at {}",
+                    clippy.path.display()
+                )));
+            }
+            let gaps = coverage.gaps(&lints, |group| {
+                opt.groups.is_empty() || opt.groups.iter().any(|included| included == group)
+            });
+            print!("{}", gaps);
+            match output {
+                Some(output) => publish::write(output, gaps)
+                    .map_err(|e| error(format!("couldn't write {}: {}", output.display(), e))),
+                None => Ok(()),
+            }
+        }
+    }
+}
+
+fn run_logs(command: &LogsCommand) -> Result<(), Failure> {
+    let error = |message| Failure::new(FailureKind::Environment, "logs", message);
+    match command {
+        LogsCommand::Summary { log } => {
+            print!("{}", summary::counts(&Log::load(log).map_err(error)?));
+            Ok(())
+        }
+        LogsCommand::Merge { logs, output } => {
+            let mut merged = Merged::new();
+            for path in logs {
+                merged.add(&path.display().to_string(), Log::load(path).map_err(error)?);
+            }
+            merged.write(output).map_err(error)?;
+            println!(
+                "merged {} log(s) into {} warning(s) and {} ICE(s), {} of them in several logs",
+                logs.len(),
+                merged.warnings.len(),
+                merged.ices.len(),
+                merged.duplicates()
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Writes the generated `config` with the comment `header` to `output`, or prints it.
+fn write_config(config: &CratesConfig, header: &str, output: Option<&Path>) -> Result<(), String> {
+    let text = config.render(header);
+    match output {
+        Some(output) => {
+            publish::write(output, text)
+                .map_err(|e| format!("couldn't write {}: {}", output.display(), e))?;
+            println!(
+                "wrote {} crate(s) to {}",
+                config.crates.len(),
+                output.display()
+            );
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+fn run_config(command: &ConfigCommand) -> Vec<Failure> {
+    let error =
+        |message| Failure::new(FailureKind::Environment, "config", message).classify_network();
+    match command {
+        ConfigCommand::UpdateVersions { config, dry_run } => versions::update(config, *dry_run)
+            .err()
+            .map(error)
+            .into_iter()
+            .collect(),
+        ConfigCommand::DeadCrates { config, job, runs } => {
+            let job = job.clone().unwrap_or_else(|| {
+                config
+                    .file_stem()
+                    .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+            });
+            match dead_crates::propose(config, &job, *runs) {
+                Ok(0) => {
+                    println!(
+                        "no crate of {} was broken in the last {} run(s) of {}",
+                        config.display(),
+                        runs,
+                        job
+                    );
+                    Vec::new()
+                }
+                Ok(changed) => {
+                    print!(
+                        "{}",
+                        fs::read_to_string(dead_crates::MESSAGE_PATH).unwrap_or_default()
+                    );
+                    println!(
+                        "changed {} crate(s), apply with `git apply {} && git commit -F {} {}`",
+                        changed,
+                        dead_crates::DIFF_PATH,
+                        dead_crates::MESSAGE_PATH,
+                        config.display()
+                    );
+                    Vec::new()
+                }
+                Err(message) => vec![error(message)],
+            }
+        }
+        ConfigCommand::FromWorkspace {
+            path,
+            depth,
+            output,
+        } => {
+            let generate = || {
+                let config = workspace::config(path, *depth)?;
+                let header = format!(
+                    "Generated by `clippy-lintcheck config from-workspace {}`",
+                    path.display()
+                );
+                write_config(&config, &header, output.as_deref())
+            };
+            generate().err().map(error).into_iter().collect()
+        }
+        ConfigCommand::ReverseDeps { krate, top, output } => {
+            let generate = || {
+                let config =
+                    CratesConfig::from_releases(crates_io::reverse_dependencies(krate, *top)?);
+                if config.crates.is_empty() {
+                    return Err(format!("no crate on crates.io depends on {}", krate));
+                }
+                let header = format!(
+                    "Generated by `clippy-lintcheck config reverse-deps {} --top {}`",
+                    krate, top
+                );
+                write_config(&config, &header, output.as_deref())
+            };
+            generate().err().map(error).into_iter().collect()
+        }
+        ConfigCommand::CheckYanked { config } => match versions::yanked(config) {
+            Ok(yanked) => {
+                if yanked.is_empty() {
+                    println!("no pinned version of {} is yanked", config.display());
+                }
+                yanked
+                    .into_iter()
+                    .map(|(key, message)| {
+                        Failure::new(FailureKind::Environment, "config", message).with_crate(&key)
+                    })
+                    .collect()
+            }
+            Err(message) => vec![error(message)],
+        },
+    }
+}
+
+fn run_publish(opt: &Opt) -> Result<(), Failure> {
+    let error = |message| Failure::new(FailureKind::Environment, "publish", message);
+    let config = WrapperConfig::load(opt.wrapper_config.as_deref()).map_err(error)?;
+    let run = pages::publish(&config.pages).map_err(|message| error(message).classify_network())?;
+    println!("published run {} to {}", run, config.pages.branch);
+    Ok(())
+}
+
+fn run_triage(opt: &Opt, config: &str, verdicts: &Path, all: bool) -> Result<(), Failure> {
+    let error = |message| Failure::new(FailureKind::Environment, "triage", message);
+    // The snippets are only shown if the sources of the crates are in the checkout.
+    let clippy = WrapperConfig::load(opt.wrapper_config.as_deref())
+        .and_then(|wrapper_config| checkout(opt, &wrapper_config))
+        .ok();
+    triage::run(
+        config,
+        opt.baseline.as_deref(),
+        verdicts,
+        all,
+        clippy.as_ref(),
+        io::stdin().lock(),
+    )
+    .map_err(error)
+}
+
+fn run_triage_report(
+    opt: &Opt,
+    format: ReportFormat,
+    lints: &[String],
+    examples: usize,
+    verdicts: &Path,
+    output: Option<&Path>,
+) -> Result<(), Failure> {
+    let error = |message| Failure::new(FailureKind::Environment, "triage-report", message);
+    let verdicts = Verdicts::load(verdicts).map_err(error)?;
+    // The groups of the lints are only known with a checkout.
+    let index = WrapperConfig::load(opt.wrapper_config.as_deref())
+        .and_then(|wrapper_config| checkout(opt, &wrapper_config))
+        .map_or_else(
+            |_| LintIndex::default(),
+            |clippy| LintIndex::load(&clippy.path),
         );
+    let report = fp_report::render(&verdicts, lints, examples, &index, format);
+    fp_report::write(report, output).map_err(error)
+}
+
+fn run_plan(
+    opt: &Opt,
+    mode: &Mode,
+    format: PlanFormat,
+    shards: Option<usize>,
+    output: Option<&Path>,
+) -> Result<(), Failure> {
+    let error = |message| Failure::new(FailureKind::Environment, "plan", message);
+    let config = WrapperConfig::load(opt.wrapper_config.as_deref()).map_err(error)?;
+    let clippy = checkout(opt, &config).map_err(error)?;
+    let jobs = jobs(opt, mode, &clippy, &config.registries)?;
+    plan::matrix(&jobs, shards, format)
+        .and_then(|matrix| plan::write(matrix, output))
+        .map_err(error)
+}
+
+/// Runs `mode` on the merge-base of the pull request `pr` or `git_ref` and on its head, with
+/// the logs of the merge-base as the baseline of the head. The clippy checkout is put back on
+/// the branch or commit it was on.
+fn run_evaluate(
+    opt: &Opt,
+    mode: &Mode,
+    pr: Option<u64>,
+    git_ref: Option<&str>,
+    summary: &mut RunSummary,
+) -> Vec<Failure> {
+    let error = |message| vec![Failure::new(FailureKind::Environment, "evaluate", message)];
+    let clippy = match WrapperConfig::load(opt.wrapper_config.as_deref())
+        .and_then(|config| checkout(opt, &config))
+    {
+        Ok(clippy) => clippy,
+        Err(message) => return error(message),
+    };
+    match update::is_dirty(&clippy) {
+        Ok(false) => {}
+        Ok(true) => {
+            return error(format!(
+                "{} has uncommitted changes, commit or stash them first",
+                clippy.path.display()
+            ))
+        }
+        Err(message) => return error(message),
+    }
+    let revisions = (|| {
+        let head = match (pr, git_ref) {
+            (Some(pr), _) => update::fetch_pr(&clippy, pr, update::PrRef::Head)?,
+            (None, Some(git_ref)) => {
+                update::fetch(&clippy, git_ref)?;
+                update::resolve(&clippy, git_ref)?
+            }
+            (None, None) => return Err("either --pr or --ref is required".to_string()),
+        };
+        update::fetch(&clippy, &opt.clippy_base_ref)?;
+        let base = update::merge_base(&clippy, &head, &opt.clippy_base_ref)?;
+        Ok((base, head))
+    })();
+    let (original, revisions) =
+        match revisions.and_then(|revisions| Ok((update::current(&clippy)?, revisions))) {
+            Ok(resolved) => resolved,
+            Err(message) => return error(message),
+        };
+    let mut failures = evaluate(opt, mode, &clippy, &revisions, summary);
+    if let Err(message) = update::check_out(&clippy, &original) {
+        failures.extend(error(message));
     }
+    let (base, head) = revisions;
     println!(
-        "lintcheck stdout: {}",
-        String::from_utf8_lossy(&lintcheck_output.stdout)
+        "evaluated {} against its merge-base {} with {}",
+        head, base, opt.clippy_base_ref
     );
-    fs::copy(
-        clippy_path.join(format!(
-            "lintcheck-logs/{}_logs.txt",
-            config.file_stem().unwrap().to_string_lossy()
-        )),
-        format!(
-            "logs/{}_logs.txt",
-            output.unwrap_or(&config.file_stem().unwrap().to_string_lossy())
-        ),
+    failures
+}
+
+/// Runs `mode` on the `(base, head)` commits of the checkout, see `run_evaluate`.
+fn evaluate(
+    opt: &Opt,
+    mode: &Mode,
+    clippy: &Checkout,
+    (base, head): &(String, String),
+    summary: &mut RunSummary,
+) -> Vec<Failure> {
+    let error = |message| vec![Failure::new(FailureKind::Environment, "evaluate", message)];
+    // The run of the merge-base only provides the baseline, it doesn't report anywhere or count
+    // as a run in the history, and neither run may resume the progress of the other one.
+    let base_opt = Opt {
+        command: None,
+        update_clippy: None,
+        clippy_pr: None,
+        baseline: None,
+        resume: false,
+        github_status: None,
+        report_ices: None,
+        badge_output: None,
+        summary_out: None,
+        events: None,
+        baseline_only: true,
+        ..opt.clone()
+    };
+    println!("running on the merge-base {}", base);
+    if let Err(message) = update::check_out(clippy, base) {
+        return error(message);
+    }
+    let base_failures = run(&base_opt, mode, &mut RunSummary::default());
+    if interrupt::requested() {
+        return base_failures;
+    }
+    // Regressions and ICEs on the merge-base are where clippy stood before the change, but
+    // without the logs there is nothing to compare against.
+    let broken: Vec<Failure> = base_failures
+        .into_iter()
+        .filter(|failure| !matches!(failure.kind, FailureKind::Regression | FailureKind::Ice))
+        .collect();
+    if !broken.is_empty() {
+        return broken;
+    }
+    let base_logs = Path::new(EVALUATE_BASE_DIR);
+    if let Err(message) = copy_logs(Path::new("logs"), base_logs) {
+        return error(message);
+    }
+    println!("running on the head {}", head);
+    if let Err(message) = update::check_out(clippy, head) {
+        return error(message);
+    }
+    let head_opt = Opt {
+        command: None,
+        update_clippy: None,
+        clippy_pr: None,
+        baseline: Some(base_logs.to_path_buf()),
+        resume: false,
+        ..opt.clone()
+    };
+    run(&head_opt, mode, summary)
+}
+
+/// Copies the logs of the configs in `from` to `to`, replacing what `to` had.
+fn copy_logs(from: &Path, to: &Path) -> Result<(), String> {
+    if let Err(e) = fs::remove_dir_all(to) {
+        if e.kind() != io::ErrorKind::NotFound {
+            return Err(format!("couldn't remove {}: {}", to.display(), e));
+        }
+    }
+    fs::create_dir_all(to).map_err(|e| format!("couldn't create {}: {}", to.display(), e))?;
+    let entries =
+        fs::read_dir(from).map_err(|e| format!("couldn't read {}: {}", from.display(), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| format!("couldn't read {}: {}", from.display(), e))?
+            .path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if name.ends_with("_logs.txt") || name.ends_with("_logs.txt.gz") {
+            fs::copy(&path, to.join(name))
+                .map_err(|e| format!("couldn't copy {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+/// `args` with the seed drawn for `--sample`, so that the run elsewhere checks the same sample.
+fn with_seed(opt: &Opt, mut args: Vec<OsString>) -> Vec<OsString> {
+    if let (Some(_), Some(seed)) = (opt.sample, opt.seed) {
+        if !args
+            .iter()
+            .any(|arg| arg.to_string_lossy().starts_with("--seed"))
+        {
+            args.push("--seed".into());
+            args.push(seed.to_string().into());
+        }
+    }
+    args
+}
+
+/// Runs this invocation in the container of `--in-container`, returning its exit code.
+fn run_in_container(opt: &Opt) -> Result<i32, Failure> {
+    let error = |message| Failure::new(FailureKind::Environment, "container", message);
+    let image = opt.in_container.as_deref().expect("--in-container is set");
+    let config = WrapperConfig::load(opt.wrapper_config.as_deref()).map_err(error)?;
+    let clippy = checkout(opt, &config).map_err(error)?;
+    let engine = opt
+        .container_engine
+        .unwrap_or_else(container::Engine::detect);
+    container::run(
+        engine,
+        image,
+        &clippy,
+        with_seed(opt, container::forwarded_args(container::OPTIONS)),
     )
-    .expect("couldn't copy log file");
+    .map_err(error)
 }
 
-fn check_integration(clippy_path: &Path) {
-    check(
-        clippy_path,
-        &PathBuf::from("../config/integration.toml"),
-        None,
+/// Runs this invocation on the machine of `--remote`, returning its exit code.
+fn run_remote(opt: &Opt) -> Result<i32, Failure> {
+    let error = |message| Failure::new(FailureKind::Environment, "remote", message);
+    let host = opt.remote.as_deref().expect("--remote is set");
+    let config = WrapperConfig::load(opt.wrapper_config.as_deref()).map_err(error)?;
+    let clippy = checkout(opt, &config).map_err(error)?;
+    remote::run(
+        host,
+        &opt.remote_dir,
+        &clippy,
+        with_seed(opt, remote::forwarded_args()),
+    )
+    .map_err(|message| error(message).classify_network())
+}
+
+/// Runs this invocation on the workers of the wrapper config and aggregates their results.
+fn run_distributed(opt: &Opt) -> Vec<Failure> {
+    let error = |message| Failure::new(FailureKind::Environment, "distributed", message);
+    let config = match WrapperConfig::load(opt.wrapper_config.as_deref()) {
+        Ok(config) => config,
+        Err(message) => return vec![error(message)],
+    };
+    let clippy = match checkout(opt, &config) {
+        Ok(clippy) => clippy,
+        Err(message) => return vec![error(message)],
+    };
+    let shards = opt.distributed_shards.unwrap_or(config.workers.len() * 2);
+    let mut failures = distribute::run(
+        &config.workers,
+        &opt.remote_dir,
+        shards,
+        &clippy,
+        &with_seed(opt, distribute::forwarded_args()),
     );
-    let log_integration =
-        fs::read_to_string("logs/integration_logs.txt").expect("couldn't read log file");
-    assert!(log_integration.ends_with("ICEs:\n"));
+    failures.extend(full_report(opt, &LintIndex::load(&clippy.path)));
+    failures
+}
+
+/// Aggregates the shards in `dirs` into logs/, see `shard::aggregate`.
+fn run_aggregate(opt: &Opt, dirs: &[PathBuf]) -> Vec<Failure> {
+    let mut failures = shard::aggregate(dirs);
+    let lints = WrapperConfig::load(opt.wrapper_config.as_deref())
+        .and_then(|config| checkout(opt, &config))
+        .map_or_else(
+            |_| LintIndex::default(),
+            |clippy| LintIndex::load(&clippy.path),
+        );
+    failures.extend(full_report(opt, &lints));
+    failures
 }
 
-fn check_passes(clippy_path: &Path) {
-    check(clippy_path, &PathBuf::from("../config/passes.toml"), None);
-    let log_passes = fs::read_to_string("logs/passes_logs.txt").expect("couldn't read log file");
-    assert!(!log_passes.contains("clippy::") && log_passes.ends_with("ICEs:\n"));
+/// Runs the external subcommand of `args` in place of this process, see `plugin`. Only returns
+/// if that fails.
+fn run_external(opt: &Opt, args: &[OsString]) -> Failure {
+    let wrapper_config = opt
+        .wrapper_config
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(wrapper_config::DEFAULT_PATH));
+    let mut env = vec![
+        ("CLIPPY_LINTCHECK_WRAPPER_CONFIG", wrapper_config.into()),
+        ("CLIPPY_LINTCHECK_LOGS", "logs".into()),
+    ];
+    if let Ok(exe) = std::env::current_exe() {
+        env.push(("CLIPPY_LINTCHECK", exe.into()));
+    }
+    let clippy = WrapperConfig::load(opt.wrapper_config.as_deref())
+        .and_then(|config| checkout(opt, &config));
+    if let Ok(clippy) = clippy {
+        env.push(("CLIPPY_LINTCHECK_CLIPPY", clippy.path.into()));
+    }
+    if let Some(baseline) = &opt.baseline {
+        env.push(("CLIPPY_LINTCHECK_BASELINE", baseline.into()));
+    }
+    if let Some(config) = &opt.config {
+        env.push(("CLIPPY_LINTCHECK_CONFIG", config.into()));
+    }
+    Failure::new(FailureKind::Environment, "plugin", plugin::exec(args, &env))
 }
 
-fn check_ci(clippy_path: &Path) {
-    let file = create_temp_config("passes");
-    check(clippy_path, file.path(), Some("ci_passes"));
-    let log_passes = fs::read_to_string("logs/ci_passes_logs.txt").expect("couldn't read log file");
-    assert!(!log_passes.contains("clippy::") && log_passes.ends_with("ICEs:\n"));
+/// The regression report of a full run whose shards were aggregated into logs/, if the run is
+/// one with --baseline. The lint groups and suppressions aren't applied to aggregated logs.
+fn full_report(opt: &Opt, lints: &LintIndex) -> Option<Failure> {
+    let baseline = opt
+        .baseline
+        .as_deref()
+        .filter(|_| matches!(opt.mode, Some(Mode::Full)))?;
+    full::report(baseline, lints, |_| true)
+        .err()
+        .map(|message| Failure::new(FailureKind::Environment, "full", message))
+}
 
-    let file = create_temp_config("integration");
-    check(clippy_path, file.path(), Some("ci_integration"));
-    let log_integration =
-        fs::read_to_string("logs/ci_integration_logs.txt").expect("couldn't read log file");
-    assert!(log_integration.ends_with("ICEs:\n"));
+/// Runs `job`, reporting its start and end as events.
+fn run_job(job: &Job, clippy: &Checkout, options: &RunOptions) -> Vec<Failure> {
+    events::emit(Event::ConfigStarted {
+        config: &job.name,
+        crates: job.crate_count(),
+    });
+    let failures = job.run(clippy, options);
+    events::emit(Event::ConfigFinished {
+        config: &job.name,
+        failures: failures.len(),
+    });
+    failures
 }
 
-fn create_temp_config(name: &str) -> NamedTempFile {
-    let mut tempfile = NamedTempFile::new().expect("failed to create tempfile");
-    writeln!(tempfile, "[crates]").expect("couldn't write to tempfile");
-    let diff = Command::new("git")
-        .arg("diff")
-        .args(&["origin/main", "--", &format!("config/{}.toml", name)])
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("couldn't execute git diff");
-    let grep = Command::new("grep")
-        .args(&["-E", r"^\+\w+"])
-        .stdin(diff.stdout.expect("failed to process git diff output"))
-        .output()
-        .expect("couldn't execute grep");
-    let stdout = String::from_utf8_lossy(&grep.stdout);
-    for l in stdout.lines().map(|l| &l[1..]) {
-        writeln!(tempfile, "{}", l).expect("couldn't write to tempfile");
+fn run(opt: &Opt, mode: &Mode, summary: &mut RunSummary) -> Vec<Failure> {
+    interrupt::install();
+    let lowered = opt
+        .nice
+        .or_else(|| opt.low_priority.then_some(priority::LOWEST))
+        .map_or(Ok(()), priority::set_nice)
+        .and_then(|()| {
+            if opt.low_priority {
+                priority::set_idle_io()
+            } else {
+                Ok(())
+            }
+        });
+    if let Err(message) = lowered {
+        return vec![Failure::new(FailureKind::Environment, "setup", message)];
+    }
+    if let Some(target) = &opt.events {
+        if let Err(message) = events::open(target) {
+            return vec![Failure::new(FailureKind::Environment, "setup", message)];
+        }
+    }
+    let (config, clippy) =
+        match WrapperConfig::load(opt.wrapper_config.as_deref()).and_then(|config| {
+            let mut clippy = checkout(opt, &config)?;
+            clippy.lintcheck = opt.lintcheck.or(clippy.lintcheck);
+            Ok((config, clippy))
+        }) {
+            Ok(loaded) => loaded,
+            Err(message) => return vec![Failure::new(FailureKind::Environment, "setup", message)],
+        };
+    let _lock = match lock::acquire(&clippy.path, opt.wait_for_lock) {
+        Ok(lock) => lock,
+        Err(message) => return vec![Failure::new(FailureKind::Environment, "setup", message)],
+    };
+    // Without the socket the run works all the same, it just can't be looked into.
+    let _status = status::serve(Path::new(status::SOCKET_PATH))
+        .map_err(|message| println!("the status of the run isn't available: {}", message))
+        .ok();
+    let pr_ref = opt
+        .clippy_pr
+        .map(|pr| update::pr_ref(pr, opt.clippy_pr_ref));
+    let updated_to = opt
+        .update_clippy
+        .as_ref()
+        .map(|git_ref| git_ref.as_deref().unwrap_or(update::DEFAULT_REF))
+        .or(pr_ref.as_deref());
+    let updated = match (opt.clippy_pr, updated_to) {
+        (Some(pr), _) => Some(update::update_to_pr(
+            &clippy,
+            pr,
+            opt.clippy_pr_ref,
+            opt.allow_dirty,
+        )),
+        (None, Some(git_ref)) => Some(update::update(&clippy, git_ref, opt.allow_dirty)),
+        (None, None) => None,
+    };
+    let clippy_sha = match updated {
+        Some(Ok(sha)) => Some(sha),
+        Some(Err(message)) => {
+            return vec![Failure::new(FailureKind::Environment, "setup", message).classify_network()]
+        }
+        None => update::head_sha(&clippy).ok(),
+    };
+    let full = matches!(mode, Mode::Full);
+    let batch_size = opt.batch_size.or(full.then_some(full::BATCH_SIZE));
+    if opt.resume && batch_size.is_none() {
+        return vec![Failure::new(
+            FailureKind::Environment,
+            "setup",
+            "--resume requires --batch-size",
+        )];
+    }
+    let result_cache_dir = match &opt.result_cache {
+        Some(dir) => Some(dir.as_deref()),
+        None if full => Some(None),
+        None => None,
+    };
+    let result_cache = result_cache_dir.and_then(|dir| {
+        let dir = dir.unwrap_or_else(|| Path::new(result_cache::DEFAULT_DIR));
+        match (&clippy_sha, update::is_dirty(&clippy)) {
+            (Some(sha), Ok(false)) => Some(ResultCache::new(dir, &clippy, sha)),
+            (Some(_), Ok(true)) => {
+                println!("not using the result cache, the clippy checkout has uncommitted changes");
+                None
+            }
+            _ => {
+                println!(
+                    "not using the result cache, the commit of the clippy checkout is unknown"
+                );
+                None
+            }
+        }
+    });
+    let metadata = RunMetadata::new(&clippy, clippy_sha, updated_to, opt.clippy_pr, sample(opt));
+    if let Err(message) = metadata
+        .write()
+        .and_then(|()| RunLock::default().save(Path::new(run_lock::RUN_LOCK_PATH)))
+    {
+        return vec![Failure::new(FailureKind::Environment, "setup", message)];
+    }
+    let clippy_conf = match opt.clippy_conf.as_deref().map(ClippyConf::new).transpose() {
+        Ok(clippy_conf) => clippy_conf,
+        Err(message) => return vec![Failure::new(FailureKind::Environment, "setup", message)],
+    };
+    let lint_policy = match LintPolicy::load(opt.policy.as_deref()) {
+        Ok(lint_policy) => lint_policy,
+        Err(message) => return vec![Failure::new(FailureKind::Environment, "setup", message)],
+    };
+    let mut options = RunOptions {
+        policy: Policy::new(opt.fail_fast, opt.keep_going),
+        baseline: opt.baseline.clone(),
+        verify_fixes: opt.verify_fixes,
+        stability_check: opt.stability_check,
+        lints: LintIndex::load(&clippy.path),
+        new_warning_budget: config.new_warning_budget,
+        lint_increase_limit: config.lint_increase_limit,
+        lint_policy,
+        groups: opt.groups.clone(),
+        registries: config.registries,
+        max_skipped_crates: opt.max_skipped_crates,
+        soft_errors: opt.soft_errors,
+        stable_toolchain: opt.stable_toolchain.clone(),
+        sandbox: opt.sandbox,
+        clippy_conf,
+        crate_expectations: config.crate_expectations,
+        suppress: config.suppress,
+        bless: opt.bless,
+        metadata,
+        trend_runs: opt.trend_runs,
+        batch_size,
+        shard: opt.shard,
+        sample: None,
+        crates: opt.crates.clone(),
+        resume: opt.resume,
+        result_cache,
+        gzip_logs: opt.gzip_logs,
+        format: opt.format,
+        log_format: opt.log_format,
+        snippets: opt.snippets,
+        results_warnings: opt.results_warnings,
+        baseline_only: opt.baseline_only,
+    };
+    let jobs: Vec<Job> = match jobs(opt, mode, &clippy, &options.registries) {
+        Ok(jobs) => jobs,
+        Err(failure) => return vec![failure],
+    };
+    if opt.sample.is_some() {
+        // The configs of the jobs were restricted to their samples, see `Job::with_sample`.
+        options.sample = Some(jobs.iter().flat_map(Job::crate_names).collect());
+    }
+
+    if !opt.skip_build {
+        status::set_phase("building clippy");
+        if let Err(message) = build::build(&clippy) {
+            return vec![Failure::new(FailureKind::Lintcheck, "build", message).classify_network()];
+        }
+    }
+
+    let statuses = match &opt.github_status {
+        Some(repo) => match options
+            .metadata
+            .clippy_sha
+            .as_deref()
+            .ok_or_else(|| {
+                "commit statuses require the clippy checkout to be a git repository".to_string()
+            })
+            .and_then(|sha| Statuses::new(repo, sha, opt.report_url.as_deref()))
+        {
+            Ok(statuses) => Some(statuses),
+            Err(message) => return vec![Failure::new(FailureKind::Environment, "github", message)],
+        },
+        None => None,
+    };
+    let mut failures = Vec::new();
+    let mut set_status = |job: &Job, state, description: &str| {
+        if let Some(statuses) = &statuses {
+            if let Err(message) = statuses.set(&job.name, state, description) {
+                failures.push(
+                    Failure::new(FailureKind::Environment, "github", message).classify_network(),
+                );
+            }
+        }
+    };
+    events::emit(Event::RunStarted {
+        configs: jobs.iter().map(|job| job.name.as_str()).collect(),
+    });
+    for job in &jobs {
+        set_status(job, State::Pending, "running");
+    }
+
+    status::set_phase("linting");
+    let (results, durations): (Vec<Vec<Failure>>, Vec<Duration>) = if opt.concurrent {
+        // Each job builds in its own target dir, so that cargo doesn't serialize them on the lock
+        // of a shared one. The files in logs/ that every job updates are guarded by
+        // `lock::shared_files`.
+        let checkouts: Vec<Checkout> = jobs
+            .iter()
+            .map(|job| Checkout {
+                target_dir: Some(clippy.target_dir().join("concurrent").join(&job.name)),
+                ..clippy.clone()
+            })
+            .collect();
+        if !opt.skip_build {
+            for checkout in &checkouts {
+                let target_dir = checkout.target_dir();
+                if let Err(message) = build::seed(&clippy, &target_dir) {
+                    return vec![Failure::new(FailureKind::Environment, "build", message)];
+                }
+            }
+        }
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = jobs
+                .iter()
+                .zip(&checkouts)
+                .map(|(job, clippy)| {
+                    let options = &options;
+                    scope.spawn(move || {
+                        let start = Instant::now();
+                        (run_job(job, clippy, options), start.elapsed())
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .zip(&jobs)
+                .map(|(handle, job)| {
+                    handle.join().unwrap_or_else(|_| {
+                        let panicked =
+                            Failure::new(FailureKind::Environment, &job.name, "the job panicked");
+                        (vec![panicked], Duration::ZERO)
+                    })
+                })
+                .collect()
+        })
+    } else {
+        let mut results = Vec::new();
+        let mut durations = Vec::new();
+        for job in &jobs {
+            if interrupt::requested() {
+                break;
+            }
+            if let Some(ci_format) = opt.ci_format {
+                println!("{}", ci_format.start(&job.name));
+            }
+            let start = Instant::now();
+            let job_failures = run_job(job, &clippy, &options);
+            durations.push(start.elapsed());
+            let failed = !job_failures.is_empty();
+            if let Some(end) = opt
+                .ci_format
+                .and_then(|ci_format| ci_format.end(&job.name, failed))
+            {
+                println!("{}", end);
+            }
+            results.push(job_failures);
+            if failed && options.policy != Policy::KeepGoing {
+                break;
+            }
+        }
+        (results, durations)
+    };
+    status::set_phase("reporting");
+    for job in jobs.iter().skip(results.len()) {
+        set_status(job, State::Error, "not run because of an earlier failure");
+    }
+    for (job, job_failures) in jobs.iter().zip(&results) {
+        let log = Log::load(gzip::find(job.log_path())).ok();
+        let (state, description) = github::outcome(log.as_ref(), job_failures);
+        set_status(job, state, &description);
+    }
+    summary.record(&jobs, &results, &durations, &options);
+    failures.extend(results.into_iter().flatten());
+
+    if let Some(url) = &opt.report_ices {
+        let logs: Vec<Log> = jobs
+            .iter()
+            .filter_map(|job| Log::load(gzip::find(job.log_path())).ok())
+            .collect();
+        let uploaded = ice_report::records(&logs, &options.metadata).and_then(|records| {
+            if !records.is_empty() {
+                ice_report::upload(url, &records)?;
+                println!("reported {} ICE(s) to {}", records.len(), url);
+            }
+            Ok(())
+        });
+        if let Err(message) = uploaded {
+            failures.push(
+                Failure::new(FailureKind::Environment, "ice-report", message).classify_network(),
+            );
+        }
+    }
+
+    if let Mode::Msrv = mode {
+        if let Err(message) = msrv::report(&jobs) {
+            failures.push(Failure::new(FailureKind::Environment, "msrv", message));
+        }
+    }
+    if let Mode::Full = mode {
+        match (&options.baseline, opt.shard) {
+            (Some(_), Some(_)) => {
+                println!("the regression report of a sharded full run is written by `aggregate`")
+            }
+            (Some(baseline), None) => {
+                let report = full::report(baseline, &options.lints, |warning| {
+                    options.includes(warning)
+                });
+                if let Err(message) = report {
+                    failures.push(Failure::new(FailureKind::Environment, "full", message));
+                }
+            }
+            (None, _) => println!("no regression report of the full run without --baseline"),
+        }
+    }
+
+    let new_warnings = options.baseline.as_ref().map(|baseline| {
+        jobs.iter()
+            .filter_map(|job| job.new_warnings(baseline, &options))
+            .sum()
+    });
+    if !opt.baseline_only {
+        let badge = Badge::new(new_warnings, &failures);
+        if let Err(message) = badge.write(opt.badge_output.as_deref()) {
+            failures.push(Failure::new(FailureKind::Environment, "badge", message));
+        }
+    }
+
+    if let Some(shard) = opt.shard {
+        let manifest = shard::Manifest {
+            shard: shard.to_string(),
+            jobs: jobs.iter().map(|job| job.name.clone()).collect(),
+            failures: std::mem::take(&mut failures),
+        };
+        let written = manifest.write();
+        failures = manifest.failures;
+        if let Err(message) = written {
+            failures.push(Failure::new(FailureKind::Environment, "shard", message));
+        }
     }
 
-    tempfile
+    print!("{}", disk::report(&clippy));
+    if let Some(max_gigabytes) = opt.prune_target {
+        match disk::prune(&clippy, (max_gigabytes * 1e9) as u64) {
+            Ok(freed) => println!("pruned {} of build artifacts", disk::gigabytes(freed)),
+            Err(message) => failures.push(Failure::new(FailureKind::Environment, "prune", message)),
+        }
+    }
+    if !opt.baseline_only {
+        if let Err(message) = actions::write(&jobs, new_warnings, &failures) {
+            failures.push(Failure::new(FailureKind::Environment, "actions", message));
+        }
+    }
+    if let Some(ci_format) = opt.ci_format {
+        for failure in &failures {
+            println!("{}", ci_format.annotate(failure));
+        }
+    }
+    failures
 }
 
 fn main() {
-    let opt: Opt = Opt::from_args();
-    let clippy_path = PathBuf::from("rust-clippy").canonicalize().unwrap();
-    match opt.mode {
-        Mode::All => {
-            check_integration(&clippy_path);
-            check_passes(&clippy_path);
+    let mut opt = match Opt::from_iter_safe(std::env::args_os()) {
+        Ok(opt) => opt,
+        Err(e) if e.use_stderr() => {
+            eprintln!("{}", e.message);
+            process::exit(FailureKind::Environment.exit_code());
+        }
+        Err(e) => e.exit(),
+    };
+    // Drawn once, so that all jobs and the metadata agree on the sample.
+    if opt.sample.is_some() && opt.seed.is_none() {
+        opt.seed = Some(sample::random_seed());
+    }
+    let start = Instant::now();
+    let mut summary = RunSummary::default();
+    // Runs handed to a container, a remote machine or workers run the hooks where they happen.
+    let runs_here = matches!(
+        (&opt.command, &opt.mode),
+        (None | Some(Command::Evaluate { .. }), Some(_))
+    ) && !opt.distributed
+        && opt.remote.is_none()
+        && opt.in_container.is_none();
+    // An invalid wrapper config is reported by the run.
+    let hooks = if runs_here {
+        WrapperConfig::load(opt.wrapper_config.as_deref())
+            .map(|config| config.hooks)
+            .unwrap_or_default()
+    } else {
+        Default::default()
+    };
+    let summary_path = opt
+        .summary_out
+        .clone()
+        .or_else(|| (!hooks.is_empty()).then(|| PathBuf::from(run_summary::DEFAULT_PATH)));
+    let pre_run = summary_path
+        .as_deref()
+        .map_or(Ok(()), |path| {
+            hooks::run("pre-run", &hooks.pre_run, path, None)
+        })
+        .err()
+        .map(|message| Failure::new(FailureKind::Environment, "pre-run", message));
+    let mut failures = match (&opt.command, &opt.mode) {
+        _ if pre_run.is_some() => pre_run.into_iter().collect(),
+        _ if opt.distributed => run_distributed(&opt),
+        _ if opt.remote.is_some() => match run_remote(&opt) {
+            Ok(code) => process::exit(code),
+            Err(failure) => vec![failure],
+        },
+        _ if opt.in_container.is_some() => match run_in_container(&opt) {
+            Ok(code) => process::exit(code),
+            Err(failure) => vec![failure],
+        },
+        (Some(Command::Coverage(command)), _) => {
+            run_coverage(&opt, command).err().into_iter().collect()
+        }
+        (Some(Command::Config(command)), _) => run_config(command),
+        (Some(Command::Logs(command)), _) => run_logs(command).err().into_iter().collect(),
+        (Some(Command::Publish), _) => run_publish(&opt).err().into_iter().collect(),
+        (Some(Command::Aggregate { dirs }), _) => run_aggregate(&opt, dirs),
+        (Some(Command::External(args)), _) => vec![run_external(&opt, args)],
+        (
+            Some(Command::Triage {
+                config,
+                verdicts,
+                all,
+            }),
+            _,
+        ) => run_triage(&opt, config, verdicts, *all)
+            .err()
+            .into_iter()
+            .collect(),
+        (
+            Some(Command::TriageReport {
+                format,
+                lint,
+                examples,
+                verdicts,
+                output,
+            }),
+            _,
+        ) => run_triage_report(&opt, *format, lint, *examples, verdicts, output.as_deref())
+            .err()
+            .into_iter()
+            .collect(),
+        (Some(Command::Status { socket }), _) => match status::query(socket) {
+            Ok(report) => {
+                print!("{}", report);
+                Vec::new()
+            }
+            Err(message) => vec![Failure::new(FailureKind::Environment, "status", message)],
+        },
+        (
+            Some(Command::Plan {
+                format,
+                shards,
+                output,
+            }),
+            Some(mode),
+        ) => run_plan(&opt, mode, *format, *shards, output.as_deref())
+            .err()
+            .into_iter()
+            .collect(),
+        (Some(Command::Evaluate { pr, git_ref }), Some(mode)) => {
+            run_evaluate(&opt, mode, *pr, git_ref.as_deref(), &mut summary)
+        }
+        (Some(Command::Evaluate { .. }), None) => vec![Failure::new(
+            FailureKind::Environment,
+            "evaluate",
+            "evaluate requires --mode",
+        )],
+        (Some(Command::Plan { .. }), None) => vec![Failure::new(
+            FailureKind::Environment,
+            "plan",
+            "plan requires --mode",
+        )],
+        (None, Some(mode)) => run(&opt, mode, &mut summary),
+        (None, None) => vec![Failure::new(
+            FailureKind::Environment,
+            "setup",
+            "either --mode or a subcommand is required",
+        )],
+    };
+    if interrupt::requested()
+        && !failures
+            .iter()
+            .any(|failure| failure.kind == FailureKind::Interrupted)
+    {
+        failures.push(Failure::new(
+            FailureKind::Interrupted,
+            "run",
+            "interrupted by SIGINT or SIGTERM",
+        ));
+    }
+    events::emit(Event::RunFinished {
+        exit_code: failure::exit_code(&failures),
+        failures: failures.len(),
+    });
+    if let Some(path) = &summary_path {
+        summary.finish(&failures, start.elapsed());
+        if let Err(message) = summary.write(path) {
+            failures.push(Failure::new(FailureKind::Environment, "summary", message));
+        }
+        let exit_code = Some(failure::exit_code(&failures));
+        let failed = !failures.is_empty();
+        if let Err(message) = hooks::run("post-run", &hooks.post_run, path, exit_code) {
+            failures.push(Failure::new(FailureKind::Environment, "post-run", message));
+        }
+        if failed {
+            if let Err(message) = hooks::run("on-failure", &hooks.on_failure, path, exit_code) {
+                failures.push(Failure::new(
+                    FailureKind::Environment,
+                    "on-failure",
+                    message,
+                ));
+            }
+        }
+    }
+
+    if !failures.is_empty() || opt.error_format == ErrorFormat::Json {
+        let report = failure::render(&failures, opt.error_format);
+        let written = match &opt.error_output {
+            Some(path) => gzip::write(path, report),
+            None => io::stderr().write_all(report.as_bytes()),
+        };
+        if let Err(e) = written {
+            eprintln!("couldn't write failure report: {}", e);
+            process::exit(FailureKind::Environment.exit_code());
         }
-        Mode::Passes => check_passes(&clippy_path),
-        Mode::Integration => check_integration(&clippy_path),
-        Mode::CI => check_ci(&clippy_path),
     }
+    process::exit(failure::exit_code(&failures));
 }
@@ -0,0 +1,132 @@
+//! Merging several logs, e.g. the logs of the shards of a run or of the passes and integration
+//! configs, into one set of warnings and ICEs without duplicates that records which logs every
+//! finding came from.
+
+use crate::{
+    log::{Ice, Log, Warning},
+    publish,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+/// Default output of `logs merge`.
+pub const MERGED_PATH: &str = "logs/merged.json";
+
+#[derive(Debug, Default, Serialize)]
+pub struct Merged {
+    /// The merged logs, in the order they were added
+    pub sources: Vec<Source>,
+    pub warnings: Vec<Finding<Warning>>,
+    pub ices: Vec<Finding<Ice>>,
+    /// Whether every merged log was complete
+    #[serde(skip)]
+    complete: bool,
+    #[serde(skip)]
+    unrecognized: usize,
+    /// Index of every finding by its rendering, to find duplicates
+    #[serde(skip)]
+    seen: BTreeMap<String, usize>,
+}
+
+/// A merged log.
+#[derive(Debug, Serialize)]
+pub struct Source {
+    /// The name the findings refer to the log by, usually its path
+    pub label: String,
+    pub clippy_version: String,
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// A warning or ICE, with the labels of the logs it occurs in.
+#[derive(Debug, Serialize)]
+pub struct Finding<T> {
+    #[serde(flatten)]
+    pub finding: T,
+    pub sources: BTreeSet<String>,
+}
+
+impl Merged {
+    pub fn new() -> Self {
+        Self {
+            complete: true,
+            ..Self::default()
+        }
+    }
+
+    /// Adds the findings of `log`, referring to it as `label`. Findings that an added log already
+    /// had only get `label` added to their sources.
+    pub fn add(&mut self, label: &str, log: Log) {
+        self.sources.push(Source {
+            label: label.to_string(),
+            clippy_version: log.clippy_version,
+            metadata: log.metadata,
+        });
+        self.complete &= log.complete;
+        self.unrecognized += log.unrecognized;
+        for warning in log.warnings {
+            let key = format!("warning {}", warning);
+            Self::insert(&mut self.seen, &mut self.warnings, key, warning, label);
+        }
+        for ice in log.ices {
+            let key = format!(
+                "ice {}: {}",
+                ice.krate.as_deref().unwrap_or("unknown"),
+                ice.message
+            );
+            Self::insert(&mut self.seen, &mut self.ices, key, ice, label);
+        }
+    }
+
+    fn insert<T>(
+        seen: &mut BTreeMap<String, usize>,
+        findings: &mut Vec<Finding<T>>,
+        key: String,
+        finding: T,
+        label: &str,
+    ) {
+        let index = *seen.entry(key).or_insert_with(|| {
+            findings.push(Finding {
+                finding,
+                sources: BTreeSet::new(),
+            });
+            findings.len() - 1
+        });
+        findings[index].sources.insert(label.to_string());
+    }
+
+    /// Number of findings that occur in more than one log.
+    pub fn duplicates(&self) -> usize {
+        let warnings = self.warnings.iter().map(|warning| warning.sources.len());
+        let ices = self.ices.iter().map(|ice| ice.sources.len());
+        warnings.chain(ices).filter(|&sources| sources > 1).count()
+    }
+
+    /// The merged findings as one log, with the header and clippy version of the first log.
+    pub fn log(&self) -> Log {
+        let first = self.sources.first();
+        Log {
+            metadata: first
+                .map(|source| source.metadata.clone())
+                .unwrap_or_default(),
+            clippy_version: first
+                .map(|source| source.clippy_version.clone())
+                .unwrap_or_default(),
+            warnings: self
+                .warnings
+                .iter()
+                .map(|warning| warning.finding.clone())
+                .collect(),
+            ices: self.ices.iter().map(|ice| ice.finding.clone()).collect(),
+            complete: self.complete,
+            unrecognized: self.unrecognized,
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).expect("merged logs are serializable") + "\n";
+        publish::write(path, json).map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+    }
+}
@@ -0,0 +1,111 @@
+//! Metadata describing a run, written next to the logs and into their headers so that they can
+//! be traced back to the clippy they were produced with.
+
+use crate::{publish, sample::Sample, wrapper_config::Checkout};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const METADATA_PATH: &str = "logs/run_metadata.json";
+
+#[derive(Debug, Serialize)]
+pub struct RunMetadata {
+    pub clippy_path: PathBuf,
+    /// `None` if the checkout isn't a git repository
+    pub clippy_sha: Option<String>,
+    /// The ref passed to `--update-clippy`, or the ref of the pull request of `--clippy-pr`
+    pub updated_to: Option<String>,
+    /// The number of the pull request checked out with `--clippy-pr`
+    pub clippy_pr: Option<u64>,
+    /// The sample of the crates of `--sample`
+    pub sample: Option<Sample>,
+    /// `rustc --version` of the toolchain of the checkout
+    pub rustc_version: Option<String>,
+    /// Target triple of the host
+    pub host: Option<String>,
+    /// Version of clippy-lintcheck
+    pub wrapper_version: &'static str,
+    /// Start of the run in seconds since the Unix epoch
+    pub time: u64,
+}
+
+impl RunMetadata {
+    /// The metadata of a run in `clippy`, which is at `clippy_sha` after updating it to
+    /// `updated_to`, if it was updated, which is a ref of the pull request `clippy_pr` if given.
+    /// Its jobs only check the crates of `sample` if given.
+    pub fn new(
+        clippy: &Checkout,
+        clippy_sha: Option<String>,
+        updated_to: Option<&str>,
+        clippy_pr: Option<u64>,
+        sample: Option<Sample>,
+    ) -> Self {
+        let mut rustc = Command::new("rustc");
+        if let Some(toolchain) = &clippy.toolchain {
+            rustc.arg(format!("+{}", toolchain));
+        }
+        let verbose_version = rustc
+            .arg("--version")
+            .arg("--verbose")
+            .current_dir(&clippy.path)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default();
+        let mut lines = verbose_version.lines();
+        Self {
+            clippy_path: clippy.path.clone(),
+            clippy_sha,
+            updated_to: updated_to.map(str::to_string),
+            clippy_pr,
+            sample,
+            rustc_version: lines.next().map(str::to_string),
+            host: lines
+                .find_map(|line| line.strip_prefix("host: "))
+                .map(str::to_string),
+            wrapper_version: env!("CARGO_PKG_VERSION"),
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+        }
+    }
+
+    pub fn write(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).expect("metadata is serializable");
+        publish::write(METADATA_PATH, json + "\n")
+            .map_err(|e| format!("couldn't write {}: {}", METADATA_PATH, e))
+    }
+
+    /// The entries of the header of a log of the run, see `Log::metadata`.
+    pub fn header(&self) -> BTreeMap<String, String> {
+        let mut header = BTreeMap::new();
+        header.insert(
+            "wrapper_version".to_string(),
+            self.wrapper_version.to_string(),
+        );
+        header.insert("time".to_string(), self.time.to_string());
+        if let Some(pr) = self.clippy_pr {
+            header.insert("clippy_pr".to_string(), pr.to_string());
+        }
+        if let Some(sample) = self.sample {
+            header.insert("sample_size".to_string(), sample.size.to_string());
+            header.insert("sample_seed".to_string(), sample.seed.to_string());
+        }
+        let optional = [
+            ("clippy_sha", &self.clippy_sha),
+            ("rustc_version", &self.rustc_version),
+            ("host", &self.host),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                header.insert(key.to_string(), value.clone());
+            }
+        }
+        header
+    }
+}
@@ -0,0 +1,82 @@
+//! Comparison of the runs of the msrv matrix, which lints the corpus once per msrv, to find the
+//! lints whose warnings depend on the msrv.
+
+use crate::{gzip, job::Job, log::Log, publish};
+use std::{collections::BTreeMap, path::Path};
+
+/// Compares the logs of `jobs`, one per msrv, and writes the lints whose counts differ between
+/// the msrvs to `logs/<config>_msrv.txt`. Jobs that produced no log are left out.
+pub fn report(jobs: &[Job]) -> Result<(), String> {
+    let mut msrvs = Vec::new();
+    let mut counts = BTreeMap::<String, BTreeMap<&str, usize>>::new();
+    let mut config = None;
+    for job in jobs {
+        let msrv = match &job.msrv {
+            Some(msrv) => msrv,
+            None => continue,
+        };
+        let log = match Log::load(gzip::find(job.log_path())) {
+            Ok(log) => log,
+            Err(message) => {
+                eprintln!(
+                    "[{}] left out of the msrv comparison: {}",
+                    job.name, message
+                );
+                continue;
+            }
+        };
+        config.get_or_insert_with(|| {
+            job.name
+                .strip_suffix(&format!("_msrv_{}", msrv))
+                .unwrap_or(&job.name)
+                .to_string()
+        });
+        for (lint, count) in log.lint_counts() {
+            counts
+                .entry(lint.to_string())
+                .or_default()
+                .insert(msrv, count);
+        }
+        msrvs.push(msrv.as_str());
+    }
+    let config = match config {
+        Some(config) => config,
+        None => return Ok(()),
+    };
+
+    let sensitive: Vec<_> = counts
+        .iter()
+        .filter(|(_, per_msrv)| {
+            let mut all = msrvs.iter().map(|msrv| per_msrv.get(msrv).unwrap_or(&0));
+            let first = all.next();
+            all.any(|count| Some(count) != first)
+        })
+        .collect();
+    let mut text = format!(
+        "{} of {} lint(s) are msrv sensitive, warnings per msrv:\n",
+        sensitive.len(),
+        counts.len()
+    );
+    if !sensitive.is_empty() {
+        let width = sensitive
+            .iter()
+            .map(|(lint, _)| lint.len())
+            .max()
+            .unwrap_or_default();
+        text.push_str(&format!("{:width$}", "", width = width));
+        for msrv in &msrvs {
+            text.push_str(&format!(" {:>8}", msrv));
+        }
+        text.push('\n');
+        for (lint, per_msrv) in sensitive {
+            text.push_str(&format!("{:width$}", lint, width = width));
+            for msrv in &msrvs {
+                text.push_str(&format!(" {:>8}", per_msrv.get(msrv).unwrap_or(&0)));
+            }
+            text.push('\n');
+        }
+    }
+    print!("[{}] {}", config, text);
+    let path = format!("logs/{}_msrv.txt", config);
+    publish::write(Path::new(&path), text).map_err(|e| format!("couldn't write {}: {}", path, e))
+}
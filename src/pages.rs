@@ -0,0 +1,158 @@
+//! Publication of the reports of a run to a GitHub Pages branch, so that the results of nightly
+//! runs are one URL away.
+//!
+//! Every run gets its own `runs/<date>/` directory on the branch with the reports, charts and
+//! metadata of the run. `index.md` links to all of them, newest first, and the `badge.json` at
+//! the root is the one of the latest run.
+
+use crate::{badge, metadata, wrapper_config::Pages};
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output},
+};
+
+/// Directory on the branch the runs are published to.
+const RUNS_DIR: &str = "runs";
+
+/// Copies the reports in `logs/` to a new run directory on the pages branch, regenerates the
+/// index and pushes the branch. Returns the name of the run directory.
+pub fn publish(pages: &Pages) -> Result<String, String> {
+    let repository = match &pages.repository {
+        Some(repository) => repository.clone(),
+        None => git(None, &["remote", "get-url", "origin"])?,
+    };
+    let dir = tempfile::tempdir().map_err(|e| format!("couldn't create a temp dir: {}", e))?;
+    let worktree = dir.path();
+    git(Some(worktree), &["init", "--quiet"])?;
+    let fetched = Command::new("git")
+        .arg("-C")
+        .arg(worktree)
+        .args(["fetch", "--quiet", "--depth=1", &repository, &pages.branch])
+        .output()
+        .map_err(|e| format!("couldn't execute git fetch: {}", e))?;
+    if fetched.status.success() {
+        git(
+            Some(worktree),
+            &["checkout", "--quiet", "-b", &pages.branch, "FETCH_HEAD"],
+        )?;
+    } else {
+        println!(
+            "branch {} doesn't exist in {} yet, creating it",
+            pages.branch, repository
+        );
+        git(
+            Some(worktree),
+            &["checkout", "--quiet", "--orphan", &pages.branch],
+        )?;
+    }
+
+    let date = run_output(
+        Command::new("date").args(["-u", "+%Y-%m-%d-%H%M%S"]),
+        "date",
+    )?;
+    let run = String::from_utf8_lossy(&date.stdout).trim().to_string();
+    let run_dir = worktree.join(RUNS_DIR).join(&run);
+    fs::create_dir_all(&run_dir)
+        .map_err(|e| format!("couldn't create {}: {}", run_dir.display(), e))?;
+    let entries =
+        fs::read_dir("logs").map_err(|e| format!("couldn't read the logs directory: {}", e))?;
+    let mut reports = Vec::new();
+    for entry in entries.flatten() {
+        let file = entry.file_name().to_string_lossy().into_owned();
+        let published = file.ends_with("_report.md")
+            || file.ends_with(".svg")
+            || [badge::BADGE_PATH, metadata::METADATA_PATH]
+                .iter()
+                .any(|path| path.strip_prefix("logs/") == Some(file.as_str()));
+        if !published || file.starts_with('.') {
+            continue;
+        }
+        fs::copy(entry.path(), run_dir.join(&file))
+            .map_err(|e| format!("couldn't copy {}: {}", entry.path().display(), e))?;
+        if file.ends_with("_report.md") {
+            reports.push(file);
+        }
+    }
+    if reports.is_empty() {
+        return Err("logs/ contains no reports, run lintcheck first".to_string());
+    }
+    let badge = Path::new(badge::BADGE_PATH);
+    if badge.is_file() {
+        fs::copy(badge, worktree.join("badge.json"))
+            .map_err(|e| format!("couldn't copy {}: {}", badge.display(), e))?;
+    }
+    write_index(worktree)?;
+
+    git(Some(worktree), &["add", "--all"])?;
+    git(
+        Some(worktree),
+        &[
+            "commit",
+            "--quiet",
+            "-m",
+            &format!("Publish lintcheck run {}", run),
+        ],
+    )?;
+    git(
+        Some(worktree),
+        &[
+            "push",
+            "--quiet",
+            &repository,
+            &format!("HEAD:refs/heads/{}", pages.branch),
+        ],
+    )?;
+    Ok(run)
+}
+
+/// Writes `index.md` with links to the reports of every published run, newest first.
+fn write_index(worktree: &Path) -> Result<(), String> {
+    let runs_dir = worktree.join(RUNS_DIR);
+    let mut runs: Vec<_> = fs::read_dir(&runs_dir)
+        .map_err(|e| format!("couldn't read {}: {}", runs_dir.display(), e))?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    runs.sort_unstable_by(|a, b| b.cmp(a));
+    let mut index = String::from("# lintcheck runs\n\n");
+    for run in runs {
+        let mut reports: Vec<_> = fs::read_dir(runs_dir.join(&run))
+            .map_err(|e| format!("couldn't read run {}: {}", run, e))?
+            .flatten()
+            .filter_map(|entry| {
+                let file = entry.file_name().to_string_lossy().into_owned();
+                let job = file.strip_suffix("_report.md")?.to_string();
+                Some(format!("[{}]({}/{}/{})", job, RUNS_DIR, run, file))
+            })
+            .collect();
+        reports.sort();
+        index.push_str(&format!("- {}: {}\n", run, reports.join(", ")));
+    }
+    let path = worktree.join("index.md");
+    fs::write(&path, index).map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+}
+
+/// Runs git in `dir`, or the current directory if `None`, returning its trimmed stdout.
+fn git(dir: Option<&Path>, args: &[&str]) -> Result<String, String> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.arg("-C").arg(dir);
+    }
+    let output = run_output(command.args(args), &format!("git {}", args[0]))?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn run_output(command: &mut Command, what: &str) -> Result<Output, String> {
+    let output = command
+        .output()
+        .map_err(|e| format!("couldn't execute {}: {}", what, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            what,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output)
+}
@@ -0,0 +1,309 @@
+//! Export of the warnings of a log as an uncompressed [Parquet](https://parquet.apache.org/docs/)
+//! file, which DuckDB, Polars and friends load far more efficiently than the text logs.
+//!
+//! Only what's needed for the warnings is implemented: a single row group with one PLAIN encoded
+//! data page per column, whose metadata is written with the Thrift compact protocol.
+
+use crate::{log::Warning, publish};
+use std::path::Path;
+
+/// Physical types.
+const INT64: i32 = 2;
+const BYTE_ARRAY: i32 = 6;
+/// Repetition types.
+const REQUIRED: i32 = 0;
+const OPTIONAL: i32 = 1;
+/// The `UTF8` converted type, which marks byte arrays as strings.
+const UTF8: i32 = 0;
+/// Encodings.
+const PLAIN: i32 = 0;
+const RLE: i32 = 3;
+
+/// Thrift compact protocol types.
+const T_I32: u8 = 5;
+const T_I64: u8 = 6;
+const T_BINARY: u8 = 8;
+const T_LIST: u8 = 9;
+const T_STRUCT: u8 = 12;
+
+const MAGIC: &[u8] = b"PAR1";
+
+enum Values<'a> {
+    Strings(Vec<&'a str>),
+    Ints(Vec<i64>),
+    OptionalInts(Vec<Option<i64>>),
+}
+
+struct Column<'a> {
+    name: &'static str,
+    values: Values<'a>,
+}
+
+/// Writes `warnings` to the Parquet file at `path`, one row per warning.
+pub fn write(path: impl AsRef<Path>, warnings: &[Warning]) -> Result<(), String> {
+    let path = path.as_ref();
+    let strings =
+        |field: fn(&Warning) -> &str| Values::Strings(warnings.iter().map(field).collect());
+    let columns = [
+        Column {
+            name: "crate",
+            values: strings(|warning| &warning.krate),
+        },
+        Column {
+            name: "version",
+            values: strings(|warning| &warning.version),
+        },
+        Column {
+            name: "file",
+            values: strings(|warning| &warning.file),
+        },
+        Column {
+            name: "line",
+            values: Values::Ints(warnings.iter().map(|warning| warning.line as i64).collect()),
+        },
+        Column {
+            name: "column",
+            values: Values::OptionalInts(
+                warnings
+                    .iter()
+                    .map(|warning| warning.column.map(|column| column as i64))
+                    .collect(),
+            ),
+        },
+        Column {
+            name: "lint",
+            values: strings(|warning| &warning.lint),
+        },
+        Column {
+            name: "message",
+            values: strings(|warning| &warning.message),
+        },
+    ];
+    publish::write(path, encode(&columns, warnings.len()))
+        .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+}
+
+fn encode(columns: &[Column<'_>], rows: usize) -> Vec<u8> {
+    let mut file = MAGIC.to_vec();
+    let mut chunks = Vec::new();
+    for column in columns {
+        let offset = file.len();
+        let page = column.page(rows);
+        let mut header = Compact::default();
+        header.i32(1, 0); // DATA_PAGE
+        header.i32(2, page.len() as i32);
+        header.i32(3, page.len() as i32);
+        header.begin_struct(5);
+        header.i32(1, rows as i32);
+        header.i32(2, PLAIN);
+        header.i32(3, RLE);
+        header.i32(4, RLE);
+        header.end_struct();
+        header.stop();
+        file.extend_from_slice(&header.buf);
+        file.extend_from_slice(&page);
+        chunks.push((offset, file.len() - offset));
+    }
+
+    let mut meta = Compact::default();
+    meta.i32(1, 1);
+    meta.list(2, T_STRUCT, columns.len() + 1);
+    meta.begin_element();
+    meta.binary(4, b"warning");
+    meta.i32(5, columns.len() as i32);
+    meta.end_element();
+    for column in columns {
+        meta.begin_element();
+        match column.values {
+            Values::Strings(_) => {
+                meta.i32(1, BYTE_ARRAY);
+                meta.i32(3, REQUIRED);
+                meta.binary(4, column.name.as_bytes());
+                meta.i32(6, UTF8);
+            }
+            Values::Ints(_) => {
+                meta.i32(1, INT64);
+                meta.i32(3, REQUIRED);
+                meta.binary(4, column.name.as_bytes());
+            }
+            Values::OptionalInts(_) => {
+                meta.i32(1, INT64);
+                meta.i32(3, OPTIONAL);
+                meta.binary(4, column.name.as_bytes());
+            }
+        }
+        meta.end_element();
+    }
+    meta.i64(3, rows as i64);
+    meta.list(4, T_STRUCT, 1);
+    meta.begin_element();
+    meta.list(1, T_STRUCT, columns.len());
+    for (column, &(offset, size)) in columns.iter().zip(&chunks) {
+        meta.begin_element();
+        meta.i64(2, offset as i64);
+        meta.begin_struct(3);
+        meta.i32(
+            1,
+            match column.values {
+                Values::Strings(_) => BYTE_ARRAY,
+                Values::Ints(_) | Values::OptionalInts(_) => INT64,
+            },
+        );
+        meta.list(2, T_I32, 2);
+        meta.element_i32(PLAIN);
+        meta.element_i32(RLE);
+        meta.list(3, T_BINARY, 1);
+        meta.element_binary(column.name.as_bytes());
+        meta.i32(4, 0); // UNCOMPRESSED
+        meta.i64(5, rows as i64);
+        meta.i64(6, size as i64);
+        meta.i64(7, size as i64);
+        meta.i64(9, offset as i64);
+        meta.end_struct();
+        meta.end_element();
+    }
+    let total: usize = chunks.iter().map(|&(_, size)| size).sum();
+    meta.i64(2, total as i64);
+    meta.i64(3, rows as i64);
+    meta.end_element();
+    meta.binary(6, b"clippy-lintcheck");
+    meta.stop();
+
+    file.extend_from_slice(&meta.buf);
+    file.extend_from_slice(&(meta.buf.len() as u32).to_le_bytes());
+    file.extend_from_slice(MAGIC);
+    file
+}
+
+impl Column<'_> {
+    /// The data of the single data page of the column.
+    fn page(&self, rows: usize) -> Vec<u8> {
+        let mut page = Vec::new();
+        match &self.values {
+            Values::Strings(values) => {
+                for value in values {
+                    page.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                    page.extend_from_slice(value.as_bytes());
+                }
+            }
+            Values::Ints(values) => {
+                for value in values {
+                    page.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            Values::OptionalInts(values) => {
+                // Definition levels with a bit width of 1 in the RLE hybrid encoding, as runs of
+                // equal levels, prefixed by their length.
+                let mut levels = Vec::new();
+                let mut i = 0;
+                while i < rows {
+                    let defined = values[i].is_some();
+                    let run = values[i..]
+                        .iter()
+                        .take_while(|value| value.is_some() == defined)
+                        .count();
+                    varint(&mut levels, (run as u64) << 1);
+                    levels.push(defined as u8);
+                    i += run;
+                }
+                page.extend_from_slice(&(levels.len() as u32).to_le_bytes());
+                page.extend_from_slice(&levels);
+                for value in values.iter().flatten() {
+                    page.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+        page
+    }
+}
+
+/// Writer of the Thrift compact protocol, tracking the last field id of every open struct.
+#[derive(Default)]
+struct Compact {
+    buf: Vec<u8>,
+    last_ids: Vec<i16>,
+    last_id: i16,
+}
+
+impl Compact {
+    fn field(&mut self, id: i16, kind: u8) {
+        let delta = id - self.last_id;
+        if 0 < delta && delta <= 15 {
+            self.buf.push((delta as u8) << 4 | kind);
+        } else {
+            self.buf.push(kind);
+            varint(&mut self.buf, zigzag(i64::from(id)));
+        }
+        self.last_id = id;
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, T_I32);
+        varint(&mut self.buf, zigzag(i64::from(value)));
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, T_I64);
+        varint(&mut self.buf, zigzag(value));
+    }
+
+    fn binary(&mut self, id: i16, value: &[u8]) {
+        self.field(id, T_BINARY);
+        self.element_binary(value);
+    }
+
+    fn list(&mut self, id: i16, kind: u8, len: usize) {
+        self.field(id, T_LIST);
+        if len < 15 {
+            self.buf.push((len as u8) << 4 | kind);
+        } else {
+            self.buf.push(0xf0 | kind);
+            varint(&mut self.buf, len as u64);
+        }
+    }
+
+    fn element_i32(&mut self, value: i32) {
+        varint(&mut self.buf, zigzag(i64::from(value)));
+    }
+
+    fn element_binary(&mut self, value: &[u8]) {
+        varint(&mut self.buf, value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    fn begin_struct(&mut self, id: i16) {
+        self.field(id, T_STRUCT);
+        self.begin_element();
+    }
+
+    fn end_struct(&mut self) {
+        self.end_element();
+    }
+
+    /// Starts a struct that is an element of a list and has no field header.
+    fn begin_element(&mut self) {
+        self.last_ids.push(self.last_id);
+        self.last_id = 0;
+    }
+
+    fn end_element(&mut self) {
+        self.stop();
+        self.last_id = self.last_ids.pop().unwrap_or_default();
+    }
+
+    fn stop(&mut self) {
+        self.buf.push(0);
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
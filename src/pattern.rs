@@ -0,0 +1,519 @@
+//! A small regular expression engine for the patterns of the wrapper config, supporting
+//! literals, `.`, character classes like `[a-z_]` and `[^/]`, the escapes `\d`, `\w` and `\s`,
+//! the anchors `^` and `$`, groups, alternatives with `|` and the greedy quantifiers `*`, `+`,
+//! `?` and `{n,m}`.
+//!
+//! Patterns are compiled to a program that is run as a Thompson NFA: all the ways the pattern
+//! can match are followed at once, one character at a time, so that matching takes time linear
+//! in the length of the text and doesn't recurse, however long the line is.
+
+use serde::Deserialize;
+use std::{convert::TryFrom, fmt};
+
+#[derive(Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Regex {
+    source: String,
+    program: Vec<Inst>,
+}
+
+/// Largest bound of a `{n,m}` repetition, which is compiled to that many copies of the repeated
+/// pattern.
+const MAX_REPETITION: usize = 1000;
+
+/// An instruction of a compiled pattern.
+#[derive(Debug, Clone)]
+enum Inst {
+    /// Consumes this character
+    Char(char),
+    /// Consumes any character
+    Any,
+    /// Consumes a character of the class, or of none of its items if it's negated
+    Class {
+        items: Vec<ClassItem>,
+        negated: bool,
+    },
+    /// Continues at the start of the text only
+    Start,
+    /// Continues at the end of the text only
+    End,
+    /// Continues at both instructions
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        items: Vec<ClassItem>,
+        negated: bool,
+    },
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit,
+    Word,
+    Space,
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            Self::Range(start, end) => (start..=end).contains(&c),
+            Self::Digit => c.is_ascii_digit(),
+            Self::Word => c.is_alphanumeric() || c == '_',
+            Self::Space => c.is_whitespace(),
+        }
+    }
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Regex({:?})", self.source)
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl TryFrom<String> for Regex {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+        };
+        let alternatives = parser
+            .alternatives()
+            .map_err(|e| format!("invalid pattern `{}`: {}", source, e))?;
+        if parser.pos < parser.chars.len() {
+            return Err(format!("invalid pattern `{}`: unmatched `)`", source));
+        }
+        let mut program = Vec::new();
+        compile_alternatives(&alternatives, &mut program);
+        program.push(Inst::Match);
+        Ok(Self { source, program })
+    }
+}
+
+impl Regex {
+    /// Whether the pattern matches anywhere in `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        // The instructions the matches in progress are at, before and after the next character.
+        let mut current = Vec::new();
+        let mut next = Vec::new();
+        // The position each instruction was last added at, so that every instruction is only
+        // followed once per position, which also ends loops that consume nothing.
+        let mut added = vec![usize::MAX; self.program.len()];
+        let mut stack = Vec::new();
+        for pos in 0..=chars.len() {
+            // A match can start at every position.
+            if self.add(0, pos, chars.len(), &mut current, &mut added, &mut stack) {
+                return true;
+            }
+            let c = chars.get(pos);
+            for &pc in &current {
+                let consumes = match &self.program[pc] {
+                    Inst::Char(expected) => c == Some(expected),
+                    Inst::Any => c.is_some(),
+                    Inst::Class { items, negated } => {
+                        c.is_some_and(|&c| items.iter().any(|item| item.matches(c)) != *negated)
+                    }
+                    _ => false,
+                };
+                if consumes
+                    && self.add(
+                        pc + 1,
+                        pos + 1,
+                        chars.len(),
+                        &mut next,
+                        &mut added,
+                        &mut stack,
+                    )
+                {
+                    return true;
+                }
+            }
+            current.clear();
+            std::mem::swap(&mut current, &mut next);
+        }
+        false
+    }
+
+    /// Adds the instructions that consume characters reachable from `pc` at `pos` to `threads`,
+    /// following jumps, splits and anchors. Returns whether the pattern matches there.
+    fn add(
+        &self,
+        pc: usize,
+        pos: usize,
+        len: usize,
+        threads: &mut Vec<usize>,
+        added: &mut [usize],
+        stack: &mut Vec<usize>,
+    ) -> bool {
+        stack.push(pc);
+        while let Some(pc) = stack.pop() {
+            if added[pc] == pos {
+                continue;
+            }
+            added[pc] = pos;
+            match self.program[pc] {
+                Inst::Jump(target) => stack.push(target),
+                Inst::Split(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                Inst::Start if pos == 0 => stack.push(pc + 1),
+                Inst::End if pos == len => stack.push(pc + 1),
+                Inst::Start | Inst::End => {}
+                Inst::Match => {
+                    stack.clear();
+                    return true;
+                }
+                Inst::Char(_) | Inst::Any | Inst::Class { .. } => threads.push(pc),
+            }
+        }
+        false
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            self.pos += 1;
+            let node = match c {
+                '.' => Node::Any,
+                '^' => Node::Start,
+                '$' => Node::End,
+                '(' => {
+                    // Non-capturing groups are the same as groups here.
+                    if self.chars[self.pos..].starts_with(&['?', ':']) {
+                        self.pos += 2;
+                    }
+                    let alternatives = self.alternatives()?;
+                    if self.next() != Some(')') {
+                        return Err("unclosed `(`".to_string());
+                    }
+                    Node::Group(alternatives)
+                }
+                '[' => self.class()?,
+                '\\' => match self.escape()? {
+                    Ok(item) => Node::Class {
+                        items: vec![item],
+                        negated: false,
+                    },
+                    Err(c) => Node::Char(c),
+                },
+                '*' | '+' | '?' | '{' => return Err(format!("`{}` repeats nothing", c)),
+                c => Node::Char(c),
+            };
+            let node = self.quantifier(node)?;
+            nodes.push(node);
+        }
+        Ok(nodes)
+    }
+
+    fn quantifier(&mut self, node: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                let end = self.chars[self.pos..]
+                    .iter()
+                    .position(|&c| c == '}')
+                    .ok_or("unclosed `{`")?;
+                let text: String = self.chars[self.pos + 1..self.pos + end].iter().collect();
+                let invalid = || format!("invalid repetition `{{{}}}`", text);
+                let parse = |bound: &str| bound.trim().parse::<usize>().map_err(|_| invalid());
+                let bounds = match text.split_once(',') {
+                    Some((min, "")) => (parse(min)?, None),
+                    Some((min, max)) => (parse(min)?, Some(parse(max)?)),
+                    None => (parse(&text)?, Some(parse(&text)?)),
+                };
+                match bounds {
+                    (min, Some(max)) if min > max => return Err(invalid()),
+                    (min, max) if min.max(max.unwrap_or(0)) > MAX_REPETITION => {
+                        return Err(format!("repetitions are limited to {}", MAX_REPETITION))
+                    }
+                    _ => {}
+                }
+                self.pos += end;
+                bounds
+            }
+            _ => return Ok(node),
+        };
+        self.pos += 1;
+        if let Node::Start | Node::End = node {
+            return Err("anchors can't be repeated".to_string());
+        }
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+
+    /// Parses the escape after a `\`, returning either a class or a literal character.
+    fn escape(&mut self) -> Result<Result<ClassItem, char>, String> {
+        Ok(match self.next().ok_or("trailing `\\`")? {
+            'd' => Ok(ClassItem::Digit),
+            'w' => Ok(ClassItem::Word),
+            's' => Ok(ClassItem::Space),
+            'n' => Err('\n'),
+            't' => Err('\t'),
+            c if c.is_alphanumeric() => return Err(format!("unsupported escape `\\{}`", c)),
+            c => Err(c),
+        })
+    }
+
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.next().ok_or("unclosed `[`")?;
+            let start = match c {
+                ']' if !first => break,
+                '\\' => match self.escape()? {
+                    Ok(item) => {
+                        items.push(item);
+                        first = false;
+                        continue;
+                    }
+                    Err(c) => c,
+                },
+                c => c,
+            };
+            first = false;
+            let is_range = self.peek() == Some('-')
+                && self.chars.get(self.pos + 1).is_some_and(|&end| end != ']');
+            if is_range {
+                self.pos += 1;
+                let end = match self.next().ok_or("unclosed `[`")? {
+                    '\\' => self.escape()?.err().ok_or("a class can't end a range")?,
+                    end => end,
+                };
+                if end < start {
+                    return Err(format!("invalid range `{}-{}`", start, end));
+                }
+                items.push(ClassItem::Range(start, end));
+            } else {
+                items.push(ClassItem::Range(start, start));
+            }
+        }
+        Ok(Node::Class { items, negated })
+    }
+}
+
+fn compile_alternatives(alternatives: &[Vec<Node>], program: &mut Vec<Inst>) {
+    let mut jumps = Vec::new();
+    for (i, sequence) in alternatives.iter().enumerate() {
+        if i + 1 == alternatives.len() {
+            compile_sequence(sequence, program);
+            break;
+        }
+        let split = program.len();
+        program.push(Inst::Split(split + 1, 0));
+        compile_sequence(sequence, program);
+        jumps.push(program.len());
+        program.push(Inst::Jump(0));
+        program[split] = Inst::Split(split + 1, program.len());
+    }
+    let end = program.len();
+    for jump in jumps {
+        program[jump] = Inst::Jump(end);
+    }
+}
+
+fn compile_sequence(nodes: &[Node], program: &mut Vec<Inst>) {
+    for node in nodes {
+        compile(node, program);
+    }
+}
+
+fn compile(node: &Node, program: &mut Vec<Inst>) {
+    match node {
+        Node::Char(c) => program.push(Inst::Char(*c)),
+        Node::Any => program.push(Inst::Any),
+        Node::Class { items, negated } => program.push(Inst::Class {
+            items: items.clone(),
+            negated: *negated,
+        }),
+        Node::Start => program.push(Inst::Start),
+        Node::End => program.push(Inst::End),
+        Node::Group(alternatives) => compile_alternatives(alternatives, program),
+        Node::Repeat { node, min, max } => {
+            for _ in 0..*min {
+                compile(node, program);
+            }
+            match max {
+                None => {
+                    let split = program.len();
+                    program.push(Inst::Split(split + 1, 0));
+                    compile(node, program);
+                    program.push(Inst::Jump(split));
+                    program[split] = Inst::Split(split + 1, program.len());
+                }
+                Some(max) => {
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(program.len());
+                        program.push(Inst::Split(0, 0));
+                        compile(node, program);
+                    }
+                    let end = program.len();
+                    for split in splits {
+                        program[split] = Inst::Split(split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Regex;
+    use std::convert::TryFrom;
+
+    fn regex(pattern: &str) -> Regex {
+        Regex::try_from(pattern.to_string()).unwrap()
+    }
+
+    #[test]
+    fn literals_and_any() {
+        assert!(regex("needless").is_match("clippy::needless_return"));
+        assert!(!regex("needles_").is_match("clippy::needless_return"));
+        assert!(regex("a.c").is_match("xabcx"));
+        assert!(!regex("a.c").is_match("ac"));
+        assert!(regex("").is_match(""));
+    }
+
+    #[test]
+    fn classes() {
+        assert!(regex("[a-c_]+$").is_match("x::a_b"));
+        assert!(!regex("^[a-c]+$").is_match("abd"));
+        assert!(regex("^[^/]+$").is_match("lib.rs"));
+        assert!(!regex("^[^/]+$").is_match("src/lib.rs"));
+        assert!(regex("[-x]").is_match("-"));
+        assert!(regex("[x-]").is_match("-"));
+        assert!(regex("[]]").is_match("]"));
+        assert!(regex(r"[\d.]+").is_match("1.0"));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(regex(r"^\d+$").is_match("123"));
+        assert!(!regex(r"^\d+$").is_match("12a"));
+        assert!(regex(r"^\w+$").is_match("snake_case1"));
+        assert!(regex(r"a\sb").is_match("a\tb"));
+        assert!(regex(r"\.iter\(\)").is_match("v.iter()"));
+        assert!(!regex(r"\.iter\(\)").is_match("viter()"));
+        assert!(regex(r"\\").is_match(r"a\b"));
+        assert!(Regex::try_from(r"\q".to_string()).is_err());
+        assert!(Regex::try_from(r"a\".to_string()).is_err());
+    }
+
+    #[test]
+    fn anchors() {
+        assert!(regex("^src/").is_match("src/lib.rs"));
+        assert!(!regex("^src/").is_match("x/src/lib.rs"));
+        assert!(regex(r"\.rs$").is_match("lib.rs"));
+        assert!(!regex(r"\.rs$").is_match("lib.rs.bk"));
+        assert!(regex("^$").is_match(""));
+        assert!(Regex::try_from("^*".to_string()).is_err());
+    }
+
+    #[test]
+    fn quantifiers() {
+        assert!(regex("^ab*c$").is_match("ac"));
+        assert!(regex("^ab+c$").is_match("abbbc"));
+        assert!(!regex("^ab+c$").is_match("ac"));
+        assert!(regex("^ab?c$").is_match("abc"));
+        assert!(!regex("^ab?c$").is_match("abbc"));
+        assert!(regex("^a{2}$").is_match("aa"));
+        assert!(!regex("^a{2}$").is_match("aaa"));
+        assert!(regex("^a{2,3}$").is_match("aaa"));
+        assert!(!regex("^a{2,3}$").is_match("aaaa"));
+        assert!(regex("^a{2,}$").is_match("aaaaa"));
+        assert!(!regex("^a{2,}$").is_match("a"));
+        assert!(regex("^(a*)*b$").is_match("aaab"));
+        assert!(Regex::try_from("a{3,1}".to_string()).is_err());
+        assert!(Regex::try_from("a{x}".to_string()).is_err());
+        assert!(Regex::try_from("a{1001}".to_string()).is_err());
+        assert!(Regex::try_from("*a".to_string()).is_err());
+    }
+
+    #[test]
+    fn groups_and_alternatives() {
+        assert!(regex("^(foo|bar)_baz$").is_match("bar_baz"));
+        assert!(!regex("^(foo|bar)_baz$").is_match("qux_baz"));
+        assert!(regex("^(?:ab)+$").is_match("ababab"));
+        assert!(!regex("^(?:ab)+$").is_match("aba"));
+        assert!(regex("x|^y").is_match("ay x"));
+        assert!(!regex("^x|^y").is_match("ay"));
+        assert!(Regex::try_from("(a".to_string()).is_err());
+        assert!(Regex::try_from("a)".to_string()).is_err());
+    }
+
+    #[test]
+    fn long_lines() {
+        let line = "a".repeat(100_000);
+        assert!(!regex("a+b").is_match(&line));
+        assert!(regex("a+b").is_match(&(line.clone() + "b")));
+        assert!(!regex(r"(a|aa)*c").is_match(&line));
+        let line = "v.iter()".repeat(1_250);
+        assert!(!regex(r"\.iter\(\)\.count\(\)").is_match(&line));
+        assert!(regex(r"\.iter\(\)\.count\(\)").is_match(&(line + ".count()")));
+    }
+}
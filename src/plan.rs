@@ -0,0 +1,116 @@
+//! The `plan` subcommand, which resolves the crates a run of a mode would check, after
+//! `--crates` and the diff detection of the "ci" mode, and prints them as a matrix for CI
+//! workflows that fan out one job per crate or per shard.
+//!
+//! ```json
+//! {"include":[{"shard":"1/2","crates":["rand","serde"]},{"shard":"2/2","crates":["log"]}]}
+//! ```
+
+use crate::{crates::CratesConfig, job::Job, publish, shard::Shard};
+use serde::Serialize;
+use std::{collections::BTreeSet, path::Path, str::FromStr};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanFormat {
+    /// `{"include": [...]}`, which `strategy.matrix` of a GitHub Actions job accepts through
+    /// `fromJSON`
+    GithubMatrix,
+}
+
+impl FromStr for PlanFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github-matrix" => Ok(Self::GithubMatrix),
+            err => Err(format!("Invalid option {}", err)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Matrix {
+    include: Vec<Entry>,
+}
+
+/// A job of the matrix, which runs the mode with `--crates <crate>` or `--shard <shard>`.
+#[derive(Debug, Serialize)]
+struct Entry {
+    #[serde(rename = "crate", skip_serializing_if = "Option::is_none")]
+    krate: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shard: Option<String>,
+    /// Names of the crates the job checks
+    crates: Vec<String>,
+    /// The configs that have crates in the job
+    configs: Vec<String>,
+}
+
+/// The matrix of the crates of `jobs`, with an entry per crate or, with `shards`, an entry per
+/// shard. Every shard gets an entry, even if it has no crates, because `aggregate` expects the
+/// logs of all shards.
+pub fn matrix(jobs: &[Job], shards: Option<usize>, format: PlanFormat) -> Result<String, String> {
+    let configs = jobs
+        .iter()
+        .map(|job| Ok((job.name.as_str(), CratesConfig::load(&job.config)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let entry = |krate: Option<String>, shard: Option<String>, contains: &dyn Fn(&str) -> bool| {
+        let mut crates = BTreeSet::new();
+        let mut in_configs = Vec::new();
+        for (job, config) in &configs {
+            let matching: Vec<String> = config
+                .crates
+                .values()
+                .map(|source| source.name.clone())
+                .filter(|name| contains(name))
+                .collect();
+            if !matching.is_empty() {
+                in_configs.push(job.to_string());
+            }
+            crates.extend(matching);
+        }
+        Entry {
+            krate,
+            shard,
+            crates: crates.into_iter().collect(),
+            configs: in_configs,
+        }
+    };
+    let include = match shards {
+        Some(0) => return Err("--shards must be at least 1".to_string()),
+        Some(count) => (1..=count)
+            .map(|index| {
+                let shard = Shard { index, count };
+                entry(None, Some(shard.to_string()), &|name| shard.contains(name))
+            })
+            .collect(),
+        None => {
+            let names: BTreeSet<&str> = configs
+                .iter()
+                .flat_map(|(_, config)| config.crates.values())
+                .map(|source| source.name.as_str())
+                .collect();
+            names
+                .into_iter()
+                .map(|name| entry(Some(name.to_string()), None, &|other| other == name))
+                .collect()
+        }
+    };
+    match format {
+        PlanFormat::GithubMatrix => {
+            Ok(serde_json::to_string(&Matrix { include }).expect("matrix is serializable"))
+        }
+    }
+}
+
+/// Prints the matrix, or writes it to `output`.
+pub fn write(matrix: String, output: Option<&Path>) -> Result<(), String> {
+    match output {
+        Some(path) => publish::write(path, matrix + "\n")
+            .map_err(|e| format!("couldn't write {}: {}", path.display(), e)),
+        None => {
+            println!("{}", matrix);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! External subcommands, which work like the ones of cargo: `clippy-lintcheck foo <args>` runs
+//! `clippy-lintcheck-foo <args>` from the `PATH` if `foo` isn't a subcommand of its own, so that
+//! teams can ship custom reporters and analyzers without upstreaming them.
+//!
+//! The global options are passed in the environment of the subcommand:
+//!
+//! - `CLIPPY_LINTCHECK`: the path of this executable, to run it again
+//! - `CLIPPY_LINTCHECK_WRAPPER_CONFIG`: the path of the wrapper config
+//! - `CLIPPY_LINTCHECK_CLIPPY`: the path of the clippy checkout of `--clippy`, if it is known
+//! - `CLIPPY_LINTCHECK_LOGS`: the directory the logs of the runs are written to
+//! - `CLIPPY_LINTCHECK_BASELINE`: the directory of `--baseline`, if given
+//! - `CLIPPY_LINTCHECK_CONFIG`: the config of `--config`, if given
+
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fs, io,
+    os::unix::{fs::PermissionsExt, process::CommandExt},
+    path::PathBuf,
+    process::Command,
+};
+
+/// Prefix of the executables of external subcommands.
+const PREFIX: &str = "clippy-lintcheck-";
+
+/// The executable of the external subcommand `name` in the `PATH`.
+fn find(name: &OsStr) -> Option<PathBuf> {
+    let mut file = OsString::from(PREFIX);
+    file.push(name);
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(&file))
+        .find(|path| {
+            fs::metadata(path).is_ok_and(|metadata| {
+                metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+            })
+        })
+}
+
+/// Replaces this process with the external subcommand of `args`, its name followed by its
+/// arguments, with `env` in its environment. Only returns if that fails.
+pub fn exec(args: &[OsString], env: &[(&str, OsString)]) -> String {
+    let (name, args) = match args.split_first() {
+        Some(split) => split,
+        None => return "no subcommand given".to_string(),
+    };
+    let path = match find(name) {
+        Some(path) => path,
+        None => {
+            return format!(
+                "no such subcommand `{}`, and no `{}{}` in the PATH",
+                name.to_string_lossy(),
+                PREFIX,
+                name.to_string_lossy()
+            )
+        }
+    };
+    let error: io::Error = Command::new(&path)
+        .args(args)
+        .envs(env.iter().cloned())
+        .exec();
+    format!("couldn't execute {}: {}", path.display(), error)
+}
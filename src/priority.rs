@@ -0,0 +1,53 @@
+//! Lowering the CPU and IO priority of a run with `--nice` and `--low-priority`, so that the
+//! full corpus can be linted on a workstation that is still used. The priority of this process
+//! is lowered, which every process it starts, like lintcheck and the clippy drivers, inherits.
+
+use std::io;
+
+/// The niceness `--low-priority` runs with.
+pub const LOWEST: i32 = 19;
+
+/// Sets the niceness of the process to `nice`, from 0 for the normal priority to 19 for the
+/// lowest.
+pub fn set_nice(nice: i32) -> Result<(), String> {
+    // SAFETY: `setpriority` has no memory safety requirements.
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) };
+    if result != 0 {
+        return Err(format!(
+            "couldn't set the niceness to {}: {}",
+            nice,
+            io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+/// Puts the process into the idle IO scheduling class, in which it only gets disk time no other
+/// process wants.
+#[cfg(target_os = "linux")]
+pub fn set_idle_io() -> Result<(), String> {
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+    // SAFETY: `ioprio_set` only takes integers.
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        )
+    };
+    if result != 0 {
+        return Err(format!(
+            "couldn't set the idle IO priority: {}",
+            io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_idle_io() -> Result<(), String> {
+    Err("the idle IO priority is only supported on Linux".to_string())
+}
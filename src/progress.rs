@@ -0,0 +1,76 @@
+//! Completion state of a batched run in `logs/<job>_progress.json`, which `--resume` uses to
+//! continue an interrupted run without checking the crates of finished batches again.
+
+use crate::{crates::CratesConfig, publish};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+/// The batches of a job that lintcheck finished, in the order they finished.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Progress {
+    pub batches: Vec<Batch>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Batch {
+    /// Number of the batch, which names its log `logs/<job>_batch<index>_logs.txt`
+    pub index: usize,
+    pub config: CratesConfig,
+    /// Output of lintcheck, which shows the crates it checked
+    pub stdout: String,
+}
+
+impl Progress {
+    pub fn path(job: &str) -> String {
+        format!("logs/{}_progress.json", job)
+    }
+
+    /// Loads the progress from `path`. A missing file is treated as no progress.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| format!("invalid progress {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("couldn't read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).expect("progress is serializable");
+        publish::write(path, json + "\n")
+            .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+    }
+
+    /// Keeps only the batches whose crates are all still in `config` with the same source and
+    /// whose log still exists, and removes their crates from `config`, so that only the
+    /// remaining crates are checked.
+    pub fn resume(&mut self, job: &str, config: &mut CratesConfig) {
+        self.batches.retain(|batch| {
+            batch
+                .config
+                .crates
+                .iter()
+                .all(|(key, source)| config.crates.get(key) == Some(source))
+                && Path::new(&batch_log(job, batch.index)).is_file()
+        });
+        for batch in &self.batches {
+            for key in batch.config.crates.keys() {
+                config.crates.remove(key);
+            }
+        }
+    }
+
+    /// The number of the next batch, which doesn't clash with the logs of finished batches.
+    pub fn next_index(&self) -> usize {
+        self.batches
+            .iter()
+            .map(|batch| batch.index + 1)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// Path of the log of the batch `index` of `job`.
+pub fn batch_log(job: &str, index: usize) -> String {
+    format!("logs/{}_batch{}_logs.txt", job, index)
+}
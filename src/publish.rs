@@ -0,0 +1,23 @@
+//! Publication of output files: everything is written to a temporary file next to its
+//! destination and renamed into place, so that readers of `logs/` never see half-written files.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Temporary path `path` is staged at before it's published, in the same directory so that the
+/// rename is atomic.
+pub fn staged_path(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    let file = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.tmp", file))
+}
+
+/// Atomically replaces `path` with `contents`, like an atomic `fs::write`.
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path.as_ref();
+    let staged = staged_path(path);
+    fs::write(&staged, contents)?;
+    fs::rename(&staged, path)
+}
@@ -0,0 +1,185 @@
+//! `--remote`, which runs on another machine over SSH, e.g. a build server for configs that are
+//! too big for a laptop.
+//!
+//! The working directory, without `target/`, and the clippy checkout are synced with rsync to
+//! the remote directory, where the checkout ends up in `rust-clippy/`. This binary is copied
+//! along and runs there with the same arguments, minus the remote options. Afterwards `logs/` is
+//! synced back. Paths in the arguments must be relative and inside of the working directory.
+//! The target dirs on the remote machine are kept between runs.
+
+use crate::{container, distribute, wrapper_config::Checkout};
+use std::{
+    env,
+    ffi::OsString,
+    fs::File,
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    process::{Command, Output},
+};
+
+/// The options that select the remote machine, and the clippy checkout, which the remote run
+/// finds synced to `REMOTE_CHECKOUT` instead.
+pub const OPTIONS: &[&str] = &["--remote", "--remote-dir", "--clippy-path"];
+/// Where the checkout is synced to, relative to the remote directory.
+const REMOTE_CHECKOUT: &str = "rust-clippy";
+/// Where this binary is copied to, relative to the remote directory.
+const REMOTE_BINARY: &str = ".clippy-lintcheck";
+
+/// Runs this binary with `args` in `dir` on `host`, e.g. `user@host`, with `clippy` synced there.
+/// Returns the exit code of the remote run.
+pub fn run(
+    host: &str,
+    dir: &str,
+    clippy: &Checkout,
+    args: impl IntoIterator<Item = OsString>,
+) -> Result<i32, String> {
+    let remote = Remote::new(host, dir);
+    remote.sync(clippy)?;
+    let code = remote.exec(args, None);
+    // The logs are synced back even if the run failed, they say why.
+    remote.pull_logs(Path::new("logs"))?;
+    code
+}
+
+/// A directory on another machine runs happen in.
+#[derive(Debug)]
+pub struct Remote<'a> {
+    host: &'a str,
+    dir: &'a str,
+}
+
+impl<'a> Remote<'a> {
+    pub fn new(host: &'a str, dir: &'a str) -> Self {
+        Self { host, dir }
+    }
+
+    fn path(&self, path: &str) -> String {
+        format!("{}:{}/{}", self.host, self.dir, path)
+    }
+
+    /// Syncs the working directory, `clippy` and this binary to the remote directory.
+    pub fn sync(&self, clippy: &Checkout) -> Result<(), String> {
+        let binary = env::current_exe()
+            .map_err(|e| format!("couldn't find the clippy-lintcheck binary: {}", e))?;
+        ssh(
+            self.host,
+            &format!("mkdir -p {}", quote(self.dir.as_bytes())),
+        )?;
+        println!("syncing the working directory to {}", self.path(""));
+        // Excluded files are also kept from being deleted, so the build artifacts of earlier
+        // runs and the checkout survive.
+        rsync(
+            &[
+                "--exclude=/target/",
+                &format!("--exclude=/{}/", REMOTE_CHECKOUT),
+                &format!("--exclude=/{}/", distribute::WORKERS_DIR),
+                "--exclude=/logs/.lock",
+                "--exclude=/logs/status.sock",
+            ],
+            "./",
+            &self.path(""),
+        )?;
+        println!(
+            "syncing {} to {}",
+            clippy.path.display(),
+            self.path(REMOTE_CHECKOUT)
+        );
+        rsync(
+            &["--exclude=/target/"],
+            &format!("{}/", clippy.path.display()),
+            &self.path(REMOTE_CHECKOUT),
+        )?;
+        rsync(
+            &[],
+            &binary.display().to_string(),
+            &self.path(REMOTE_BINARY),
+        )?;
+        Ok(())
+    }
+
+    /// Runs the synced binary with `args` and returns its exit code. Its output goes to
+    /// `output` if given.
+    pub fn exec(
+        &self,
+        args: impl IntoIterator<Item = OsString>,
+        output: Option<File>,
+    ) -> Result<i32, String> {
+        let mut script = format!("cd {} && ./{}", quote(self.dir.as_bytes()), REMOTE_BINARY);
+        for arg in args {
+            script.push(' ');
+            script.push_str(&quote(arg.as_bytes()));
+        }
+        script.push_str(&format!(" --clippy-path {}", REMOTE_CHECKOUT));
+        let mut ssh = Command::new("ssh");
+        ssh.arg(self.host).arg(script);
+        if let Some(output) = output {
+            let stderr = output
+                .try_clone()
+                .map_err(|e| format!("couldn't duplicate the output file: {}", e))?;
+            ssh.stdout(output).stderr(stderr);
+        }
+        let status = ssh
+            .status()
+            .map_err(|e| format!("couldn't execute ssh: {}", e))?;
+        match status.code() {
+            Some(255) => Err(format!("ssh couldn't connect to {}", self.host)),
+            Some(code) => Ok(code),
+            None => Err("ssh was killed by a signal".to_string()),
+        }
+    }
+
+    /// Syncs the `logs/` of the remote directory to `destination`.
+    pub fn pull_logs(&self, destination: &Path) -> Result<(), String> {
+        println!(
+            "syncing the logs back from {} to {}",
+            self.path("logs"),
+            destination.display()
+        );
+        rsync(
+            &["--exclude=/.lock", "--exclude=/status.sock"],
+            &self.path("logs/"),
+            &destination.display().to_string(),
+        )?;
+        Ok(())
+    }
+}
+
+/// The arguments of this process without the remote options.
+pub fn forwarded_args() -> Vec<OsString> {
+    container::forwarded_args(OPTIONS)
+}
+
+fn ssh(host: &str, script: &str) -> Result<Output, String> {
+    checked(Command::new("ssh").arg(host).arg(script), "ssh")
+}
+
+fn rsync(options: &[&str], source: &str, destination: &str) -> Result<Output, String> {
+    checked(
+        Command::new("rsync")
+            .args(["--archive", "--compress", "--delete"])
+            .args(options)
+            .arg(source)
+            .arg(destination),
+        "rsync",
+    )
+}
+
+fn checked(command: &mut Command, name: &str) -> Result<Output, String> {
+    let output = command
+        .output()
+        .map_err(|e| format!("couldn't execute {}: {}", name, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed with {}: {}",
+            name,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output)
+}
+
+/// Quotes `arg` for the shell on the remote machine.
+fn quote(arg: &[u8]) -> String {
+    format!("'{}'", String::from_utf8_lossy(arg).replace('\'', r"'\''"))
+}
@@ -0,0 +1,107 @@
+//! Markdown report of a job, with the summary of its latest log, charts of its history, the lints
+//! with a high churn and its warnings grouped by crate and file.
+
+use crate::{
+    chart,
+    history::{Churning, Run},
+    log::Warning,
+    publish,
+};
+use std::collections::BTreeMap;
+
+/// Writes `logs/<job>_report.md` and the charts it embeds next to it.
+pub fn write(
+    job: &str,
+    summary: &str,
+    warnings: &[Warning],
+    runs: &[Run],
+    churning: &[Churning],
+) -> Result<(), String> {
+    let charts = [
+        (
+            format!("{}_groups.svg", job),
+            "warnings per lint group",
+            chart::groups(&format!("{}: warnings per lint group", job), runs),
+        ),
+        (
+            format!("{}_ices.svg", job),
+            "ICEs",
+            chart::ices(&format!("{}: ICEs", job), runs),
+        ),
+    ];
+    let mut report = format!("# {}\n\n", job);
+    if let Some(Run {
+        clippy_sha: Some(sha),
+        ..
+    }) = runs.last()
+    {
+        report.push_str(&format!("clippy {}\n\n", sha));
+    }
+    report.push_str(&format!("```text\n{}```\n", summary));
+    for (file, alt, svg) in &charts {
+        let path = format!("logs/{}", file);
+        publish::write(&path, svg).map_err(|e| format!("couldn't write {}: {}", path, e))?;
+        report.push_str(&format!("\n![{}]({})\n", alt, file));
+    }
+    if !churning.is_empty() {
+        report.push_str(
+            "\n## High churn lints\n\nFindings of these lints came and went over the last runs \
+             of the same config, which hints at unstable spans or nondeterminism in the lint.\n\n\
+             | lint | appeared | disappeared | average warnings |\n| --- | --- | --- | --- |\n",
+        );
+        for churning in churning {
+            report.push_str(&format!(
+                "| `{}` | {} | {} | {:.1} |\n",
+                churning.lint, churning.appeared, churning.disappeared, churning.average
+            ));
+        }
+    }
+    report.push_str(&by_file(warnings));
+    let path = format!("logs/{}_report.md", job);
+    publish::write(&path, report).map_err(|e| format!("couldn't write {}: {}", path, e))
+}
+
+/// The warnings grouped by crate and file, in the order of their lines, with every file a
+/// collapsed section. Identical warnings are listed once with their number.
+fn by_file(warnings: &[Warning]) -> String {
+    type Location = (usize, Option<usize>);
+    let mut crates = BTreeMap::<_, BTreeMap<_, BTreeMap<(Location, &str, &str), usize>>>::new();
+    for warning in warnings {
+        *crates
+            .entry((warning.krate.as_str(), warning.version.as_str()))
+            .or_default()
+            .entry(warning.file.as_str())
+            .or_default()
+            .entry((
+                (warning.line, warning.column),
+                warning.lint.as_str(),
+                warning.message.as_str(),
+            ))
+            .or_default() += 1;
+    }
+    let mut out = String::from("\n## Warnings by file\n");
+    for ((krate, version), files) in crates {
+        out.push_str(&format!("\n### {} {}\n\n", krate, version));
+        for (file, file_warnings) in files {
+            let total: usize = file_warnings.values().sum();
+            out.push_str(&format!(
+                "<details><summary><code>{}</code>: {} warning(s)</summary>\n\n",
+                file, total
+            ));
+            for (((line, column), lint, message), count) in file_warnings {
+                let location = match column {
+                    Some(column) => format!("{}:{}", line, column),
+                    None => line.to_string(),
+                };
+                let count = if count > 1 {
+                    format!(" (x{})", count)
+                } else {
+                    String::new()
+                };
+                out.push_str(&format!("- {} `{}` {}{}\n", location, lint, message, count));
+            }
+            out.push_str("\n</details>\n");
+        }
+    }
+    out
+}
@@ -0,0 +1,174 @@
+//! `--result-cache`, which keeps the results of every crate lintcheck checked, so that runs with
+//! the same clippy don't lint the crate again.
+//!
+//! A result is stored as a log of the warnings and ICEs of one `[crates]` entry in
+//! `<dir>/<key>.txt`, where the key is the SHA-256 of everything that decides on the result: the
+//! commit of clippy, the toolchain, the clippy config and the entry itself. Path crates aren't
+//! cached because their sources can change under the same entry, and neither are crates that
+//! lintcheck skipped or that seemingly failed to compile, so that they are retried.
+
+use crate::{
+    clippy_conf::ClippyConf,
+    crates::{self, CrateSource, CratesConfig},
+    lintcheck,
+    log::Log,
+    publish,
+    wrapper_config::Checkout,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const DEFAULT_DIR: &str = "target/result-cache";
+
+#[derive(Debug)]
+pub struct ResultCache {
+    dir: PathBuf,
+    /// The part of the key shared by all crates of the run
+    context: String,
+}
+
+/// The results of the crates `ResultCache::restore` found.
+#[derive(Debug)]
+pub struct Restored {
+    pub log: Log,
+    /// A line for every crate as lintcheck reports the crates it lints, see `lintcheck::CACHED`
+    pub stdout: String,
+    /// Number of `[crates]` entries
+    pub count: usize,
+}
+
+impl ResultCache {
+    /// The cache in `dir` for runs with `clippy` at the commit `clippy_sha`.
+    pub fn new(dir: &Path, clippy: &Checkout, clippy_sha: &str) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            context: format!(
+                "clippy {}\ntoolchain {}\n",
+                clippy_sha,
+                clippy.toolchain.as_deref().unwrap_or("default")
+            ),
+        }
+    }
+
+    /// Path of the result of `source` linted with the clippy config in `clippy_conf`.
+    fn path(
+        &self,
+        source: &CrateSource,
+        clippy_conf: Option<&ClippyConf>,
+    ) -> Result<PathBuf, String> {
+        let conf = match clippy_conf.and_then(ClippyConf::file) {
+            Some(path) => fs::read_to_string(&path)
+                .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?,
+            None => String::new(),
+        };
+        let source =
+            toml::to_string(source).map_err(|e| format!("couldn't serialize config: {}", e))?;
+        let key = format!("{}conf {}\n{}", self.context, conf, source);
+        let hash = crates::sha256sum(key.as_bytes())?;
+        Ok(self.dir.join(format!("{}.txt", hash)))
+    }
+
+    /// Removes the crates whose results are cached from `config` and returns their results.
+    pub fn restore(
+        &self,
+        config: &mut CratesConfig,
+        clippy_conf: Option<&ClippyConf>,
+    ) -> Result<Restored, String> {
+        let mut restored = Restored {
+            log: Log::default(),
+            stdout: String::new(),
+            count: 0,
+        };
+        let mut hits = Vec::new();
+        for (key, source) in &config.crates {
+            if source.path.is_some() {
+                continue;
+            }
+            let path = self.path(source, clippy_conf)?;
+            let log = match Log::load(&path) {
+                Ok(log) => log,
+                Err(_) if !path.exists() => continue,
+                Err(message) => return Err(message),
+            };
+            if restored.count == 0 {
+                restored.log.clippy_version = log.clippy_version.clone();
+                restored.log.complete = true;
+            }
+            restored.log.merge(log);
+            for krate in source.krates() {
+                restored.stdout.push_str(&format!(
+                    "{} Linting {} {}\n",
+                    lintcheck::CACHED,
+                    krate.name,
+                    krate.version
+                ));
+            }
+            restored.count += 1;
+            hits.push(key.clone());
+        }
+        for key in hits {
+            config.crates.remove(&key);
+        }
+        Ok(restored)
+    }
+
+    /// Stores the results of the crates of `config` in `log`, which lintcheck wrote with the
+    /// `stdout`, except for the crates that were skipped or seemingly failed to compile.
+    /// Returns the number of `[crates]` entries stored.
+    pub fn store(
+        &self,
+        config: &CratesConfig,
+        log: &Log,
+        stdout: &str,
+        clippy: &Checkout,
+        clippy_conf: Option<&ClippyConf>,
+    ) -> Result<usize, String> {
+        let skipped = lintcheck::skipped_crates(config, stdout, clippy);
+        let silent = lintcheck::silent_crates(config, log, stdout, clippy, &skipped);
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("couldn't create {}: {}", self.dir.display(), e))?;
+        let mut stored = 0;
+        for source in config.crates.values() {
+            let krates = source.krates();
+            let retried = krates.iter().any(|krate| {
+                skipped
+                    .iter()
+                    .map(|skipped| &skipped.krate)
+                    .chain(&silent)
+                    .any(|other| other.name == krate.name && other.version == krate.version)
+            });
+            if source.path.is_some() || retried {
+                continue;
+            }
+            let result = Log {
+                metadata: Default::default(),
+                clippy_version: log.clippy_version.clone(),
+                warnings: log
+                    .warnings
+                    .iter()
+                    .filter(|warning| {
+                        krates.iter().any(|krate| {
+                            warning.krate == krate.name && warning.version == krate.version
+                        })
+                    })
+                    .cloned()
+                    .collect(),
+                ices: log
+                    .ices
+                    .iter()
+                    .filter(|ice| ice.krate.as_deref() == Some(source.name.as_str()))
+                    .cloned()
+                    .collect(),
+                complete: true,
+                unrecognized: 0,
+            };
+            let path = self.path(source, clippy_conf)?;
+            publish::write(&path, result.render())
+                .map_err(|e| format!("couldn't write {}: {}", path.display(), e))?;
+            stored += 1;
+        }
+        Ok(stored)
+    }
+}
@@ -0,0 +1,87 @@
+//! Append-only log of normalized results in `logs/results.jsonl`, one JSON record per line, so
+//! that the results of every run can be shipped to external pipelines by ordinary log shippers.
+
+use crate::{
+    failure::Failure,
+    log::{Log, Warning},
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const RESULTS_PATH: &str = "logs/results.jsonl";
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    /// One per job and run
+    Run {
+        time: u64,
+        job: &'a str,
+        clippy_sha: Option<&'a str>,
+        clippy_version: &'a str,
+        /// The header of the log
+        metadata: &'a BTreeMap<String, String>,
+        warnings: usize,
+        ices: usize,
+        failures: &'a [Failure],
+    },
+    /// One per warning, if enabled
+    Warning {
+        time: u64,
+        job: &'a str,
+        clippy_sha: Option<&'a str>,
+        #[serde(flatten)]
+        warning: &'a Warning,
+    },
+}
+
+/// Appends the record of the run of `job` and, if `warnings` is set, a record of each of its
+/// warnings.
+pub fn append(
+    job: &str,
+    clippy_sha: Option<&str>,
+    log: &Log,
+    failures: &[Failure],
+    warnings: bool,
+) -> Result<(), String> {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let run = Record::Run {
+        time,
+        job,
+        clippy_sha,
+        clippy_version: &log.clippy_version,
+        metadata: &log.metadata,
+        warnings: log.warnings.len(),
+        ices: log.ices.len(),
+        failures,
+    };
+    let warning_records = log
+        .warnings
+        .iter()
+        .filter(|_| warnings)
+        .map(|warning| Record::Warning {
+            time,
+            job,
+            clippy_sha,
+            warning,
+        });
+    let mut lines = String::new();
+    for record in std::iter::once(run).chain(warning_records) {
+        lines.push_str(&serde_json::to_string(&record).expect("records are serializable"));
+        lines.push('\n');
+    }
+    // A single write, so that concurrent readers never see a partial run.
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(RESULTS_PATH)
+        .and_then(|mut file| file.write_all(lines.as_bytes()))
+        .map_err(|e| format!("couldn't append to {}: {}", RESULTS_PATH, e))
+}
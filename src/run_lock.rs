@@ -0,0 +1,106 @@
+//! `logs/run.lock`, the exact crates a run linted, so that a historical run can be reproduced
+//! even after the configs changed or versions were yanked.
+//!
+//! ```toml
+//! [[crate]]
+//! job = "integration"
+//! name = "serde"
+//! version = "1.0.136"
+//! source = "crates.io"
+//! checksum = "..."
+//! ```
+
+use crate::{
+    crates::{self, CrateSource, LINTCHECK_DOWNLOADS},
+    lintcheck::Skipped,
+    publish,
+    wrapper_config::Checkout,
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::Path};
+
+pub const RUN_LOCK_PATH: &str = "logs/run.lock";
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RunLock {
+    #[serde(rename = "crate", default)]
+    crates: Vec<LockedCrate>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct LockedCrate {
+    job: String,
+    name: String,
+    /// The version as in the log: the crates.io version, the git hash or `local`
+    version: String,
+    /// `crates.io`, `git+<url>` or `path+<path>`
+    source: String,
+    /// SHA-256 of the `.crate` archive, which is the checksum crates.io publishes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+}
+
+impl RunLock {
+    /// Loads the lock from `path`. A missing file is treated as an empty lock.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text)
+                .map_err(|e| format!("invalid run lock {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("couldn't read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text =
+            toml::to_string(self).map_err(|e| format!("couldn't serialize the run lock: {}", e))?;
+        publish::write(
+            path,
+            format!("# The crates lintcheck linted in the run\n\n{}", text),
+        )
+        .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+    }
+
+    /// Records the crates of `sources` that `job` linted, which are all but the `skipped` ones.
+    pub fn record<'a>(
+        &mut self,
+        job: &str,
+        clippy: &Checkout,
+        sources: impl IntoIterator<Item = &'a CrateSource>,
+        skipped: &[Skipped],
+    ) -> Result<(), String> {
+        for source in sources {
+            for krate in source.krates() {
+                let is_skipped = skipped.iter().any(|skipped| {
+                    skipped.krate.name == krate.name && skipped.krate.version == krate.version
+                });
+                if is_skipped {
+                    continue;
+                }
+                let (source, checksum) = match (&source.git_url, &source.path) {
+                    (Some(git_url), _) => (format!("git+{}", git_url), None),
+                    (None, Some(path)) => (format!("path+{}", path), None),
+                    (None, None) => {
+                        let archive = clippy
+                            .path
+                            .join(LINTCHECK_DOWNLOADS)
+                            .join(format!("{}-{}.crate.tar.gz", krate.name, krate.version));
+                        let checksum = match fs::read(&archive) {
+                            Ok(data) => Some(crates::sha256sum(&data)?),
+                            Err(_) => None,
+                        };
+                        ("crates.io".to_string(), checksum)
+                    }
+                };
+                self.crates.push(LockedCrate {
+                    job: job.to_string(),
+                    name: krate.name,
+                    version: krate.version,
+                    source,
+                    checksum,
+                });
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,174 @@
+//! The summary `--summary-out` writes, a compact JSON description of the outcome of a run for
+//! scripts that wrap it, so that they neither have to parse the output nor know the layout of
+//! `logs/`.
+//!
+//! ```json
+//! {
+//!   "status": "failed",
+//!   "exit_code": 1,
+//!   "duration_secs": 812.4,
+//!   "new_warnings": 3,
+//!   "configs": [{"name": "integration", "status": "failed", "duration_secs": 790.1,
+//!                "warnings": 1432, "lints": 201, "ices": 0, "new_warnings": 3,
+//!                "log": "logs/integration_logs.txt", "config_fingerprint": "5f1d..."}],
+//!   "artifacts": ["logs/integration_diff.txt", "logs/integration_logs.txt", ...],
+//!   "failures": [...]
+//! }
+//! ```
+
+use crate::{
+    badge::BADGE_PATH,
+    failure::{self, Failure},
+    fingerprint, gzip,
+    job::{Job, RunOptions},
+    log::Log,
+    metadata::METADATA_PATH,
+    publish,
+    run_lock::RUN_LOCK_PATH,
+    shard::SHARD_PATH,
+};
+use serde::Serialize;
+use std::{fs, path::Path, time::Duration};
+
+/// Where the summary is written for the hooks of the wrapper config without `--summary-out`.
+pub const DEFAULT_PATH: &str = "logs/summary.json";
+/// Files of a run in `logs/` that don't belong to a single config.
+const RUN_ARTIFACTS: &[&str] = &[METADATA_PATH, RUN_LOCK_PATH, SHARD_PATH, BADGE_PATH];
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    Passed,
+    Failed,
+    /// Skipped because of an earlier failure
+    NotRun,
+}
+
+#[derive(Debug, Serialize)]
+struct ConfigSummary {
+    name: String,
+    status: Status,
+    duration_secs: Option<f64>,
+    /// `None` if the config has no log
+    warnings: Option<usize>,
+    lints: Option<usize>,
+    ices: Option<usize>,
+    /// Warnings that aren't in the baseline, `None` without `--baseline`
+    new_warnings: Option<usize>,
+    log: Option<String>,
+    /// Fingerprint of the effective config from the header of the log, see `fingerprint`
+    config_fingerprint: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    status: Status,
+    exit_code: i32,
+    duration_secs: f64,
+    /// Sum of the new warnings of the configs, `None` without `--baseline`
+    new_warnings: Option<usize>,
+    configs: Vec<ConfigSummary>,
+    /// The files the run wrote to `logs/`
+    artifacts: Vec<String>,
+    failures: Vec<Failure>,
+}
+
+impl Default for RunSummary {
+    fn default() -> Self {
+        Self {
+            status: Status::NotRun,
+            exit_code: 0,
+            duration_secs: 0.0,
+            new_warnings: None,
+            configs: Vec::new(),
+            artifacts: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+}
+
+impl RunSummary {
+    /// Records the outcome of `jobs`, of which the first ones ran with the `results` and took
+    /// `durations`.
+    pub fn record(
+        &mut self,
+        jobs: &[Job],
+        results: &[Vec<Failure>],
+        durations: &[Duration],
+        options: &RunOptions,
+    ) {
+        for (i, job) in jobs.iter().enumerate() {
+            let log_path = gzip::find(job.log_path());
+            let log = Log::load(&log_path).ok();
+            let new_warnings = options
+                .baseline
+                .as_ref()
+                .and_then(|baseline| job.new_warnings(baseline, options));
+            self.configs.push(ConfigSummary {
+                name: job.name.clone(),
+                status: match results.get(i) {
+                    Some(failures) if failures.is_empty() => Status::Passed,
+                    Some(_) => Status::Failed,
+                    None => Status::NotRun,
+                },
+                duration_secs: durations.get(i).map(Duration::as_secs_f64),
+                warnings: log.as_ref().map(|log| log.warnings.len()),
+                lints: log.as_ref().map(|log| log.lint_counts().len()),
+                ices: log.as_ref().map(|log| log.ices.len()),
+                new_warnings,
+                config_fingerprint: log
+                    .as_ref()
+                    .and_then(|log| log.metadata.get(fingerprint::HEADER_KEY).cloned()),
+                log: log.map(|_| log_path.display().to_string()),
+            });
+            if let Some(new_warnings) = new_warnings {
+                *self.new_warnings.get_or_insert(0) += new_warnings;
+            }
+        }
+    }
+
+    /// Completes the summary of a run that took `duration` and ended with `failures`, listing
+    /// the files of the run in `logs/`.
+    pub fn finish(&mut self, failures: &[Failure], duration: Duration) {
+        self.exit_code = failure::exit_code(failures);
+        self.status = if failures.is_empty() {
+            Status::Passed
+        } else {
+            Status::Failed
+        };
+        self.duration_secs = duration.as_secs_f64();
+        self.failures = failures.to_vec();
+        let prefixes: Vec<String> = self
+            .configs
+            .iter()
+            .map(|config| format!("{}_", config.name))
+            .collect();
+        let mut artifacts: Vec<String> = fs::read_dir("logs")
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|file| {
+                prefixes
+                    .iter()
+                    .any(|prefix| file.starts_with(prefix.as_str()))
+            })
+            .map(|file| format!("logs/{}", file))
+            .chain(
+                RUN_ARTIFACTS
+                    .iter()
+                    .filter(|path| Path::new(path).exists())
+                    .map(|path| path.to_string()),
+            )
+            .collect();
+        artifacts.sort();
+        self.artifacts = artifacts;
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).expect("summary is serializable");
+        publish::write(path, json + "\n")
+            .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+    }
+}
@@ -0,0 +1,68 @@
+//! `--sample`, which checks a random subset of the crates of every job for a quick run that is
+//! still representative of the whole config. The subset only depends on the crates of the config
+//! and the seed, which is recorded in the run metadata, so that an interesting sample can be
+//! checked again with `--sample <size> --seed <seed>`.
+
+use crate::crates::CratesConfig;
+use serde::Serialize;
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Sample {
+    /// Number of `[crates]` entries picked
+    pub size: usize,
+    pub seed: u64,
+}
+
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "--sample {} --seed {}", self.size, self.seed)
+    }
+}
+
+impl Sample {
+    /// `size` crates of `config`, picked with a Fisher-Yates shuffle of its entries in the
+    /// order of their keys.
+    pub fn select(&self, config: &CratesConfig) -> CratesConfig {
+        let mut entries: Vec<_> = config.crates.iter().collect();
+        let mut rng = SplitMix64(self.seed);
+        let size = self.size.min(entries.len());
+        for i in 0..size {
+            let j = i + (rng.next() % (entries.len() - i) as u64) as usize;
+            entries.swap(i, j);
+        }
+        CratesConfig {
+            crates: entries
+                .into_iter()
+                .take(size)
+                .map(|(key, source)| (key.clone(), source.clone()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A seed for a sample without `--seed`.
+pub fn random_seed() -> u64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    SplitMix64(now.as_secs() ^ u64::from(now.subsec_nanos()) ^ u64::from(std::process::id())).next()
+}
+
+/// SplitMix64, which unlike the generators of `std` gives the same numbers on every machine and
+/// Rust version.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
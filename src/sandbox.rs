@@ -0,0 +1,148 @@
+//! Running lintcheck in a sandbox, because linting the corpus executes the build scripts and
+//! proc macros of arbitrary crates.
+//!
+//! The sandbox is [bubblewrap](https://github.com/containers/bubblewrap): the whole filesystem is
+//! mounted read-only, except for the clippy checkout, its target dir and the cargo home, and
+//! there is no network. Because lintcheck downloads the crates itself, `prefetch` downloads them
+//! and their dependencies to where lintcheck expects them before. Lintcheck has no way to skip
+//! build scripts, so they still run, but only inside the sandbox.
+
+use crate::{
+    crates::{CrateSource, CratesConfig, LINTCHECK_DOWNLOADS, LINTCHECK_SOURCES},
+    wrapper_config::{Checkout, Registries},
+};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Downloads every crate of `config` the way lintcheck would and fetches their dependencies and
+/// the ones of clippy, so that lintcheck doesn't need the network anymore.
+pub fn prefetch(
+    clippy: &Checkout,
+    config: &CratesConfig,
+    registries: &Registries,
+) -> Result<(), String> {
+    // The sandbox can only bind existing directories.
+    let target_dir = clippy.target_dir();
+    fs::create_dir_all(&target_dir)
+        .map_err(|e| format!("couldn't create {}: {}", target_dir.display(), e))?;
+    run(
+        Command::new("cargo").arg("fetch").current_dir(&clippy.path),
+        "fetching the dependencies of clippy",
+    )?;
+    for source in config.crates.values() {
+        prefetch_crate(clippy, source)?;
+    }
+    for krate in config.krates() {
+        let source_dir = clippy.path.join(&krate.source_dir);
+        let mut cargo = Command::new("cargo");
+        cargo.arg("fetch").current_dir(&source_dir);
+        for (name, registry) in registries {
+            cargo.envs(registry.cargo_env(name)?);
+        }
+        run(
+            &mut cargo,
+            &format!("fetching the dependencies of {}", krate.name),
+        )?;
+    }
+    Ok(())
+}
+
+fn prefetch_crate(clippy: &Checkout, source: &CrateSource) -> Result<(), String> {
+    if let Some(versions) = &source.versions {
+        let downloads = clippy.path.join(LINTCHECK_DOWNLOADS);
+        let sources = clippy.path.join(LINTCHECK_SOURCES);
+        fs::create_dir_all(&downloads)
+            .and_then(|()| fs::create_dir_all(&sources))
+            .map_err(|e| format!("couldn't create the lintcheck directories: {}", e))?;
+        for version in versions {
+            let archive = downloads.join(format!("{}-{}.crate.tar.gz", source.name, version));
+            if !archive.is_file() {
+                run(
+                    Command::new("curl")
+                        .args([
+                            "--silent",
+                            "--show-error",
+                            "--fail",
+                            "--location",
+                            "--output",
+                        ])
+                        .arg(&archive)
+                        .arg(format!(
+                            "https://crates.io/api/v1/crates/{}/{}/download",
+                            source.name, version
+                        )),
+                    &format!("downloading {} {}", source.name, version),
+                )?;
+            }
+            run(
+                Command::new("tar")
+                    .arg("-xzf")
+                    .arg(&archive)
+                    .arg("-C")
+                    .arg(&sources),
+                &format!("extracting {}", archive.display()),
+            )?;
+        }
+    } else if let (Some(git_url), Some(git_hash)) = (&source.git_url, &source.git_hash) {
+        let repo = clippy.path.join(LINTCHECK_SOURCES).join(&source.name);
+        if !repo.is_dir() {
+            run(
+                Command::new("git").args(["clone", git_url]).arg(&repo),
+                &format!("cloning {}", git_url),
+            )?;
+        }
+        run(
+            Command::new("git")
+                .arg("-C")
+                .arg(&repo)
+                .args(["checkout", git_hash]),
+            &format!("checking out {} of {}", git_hash, source.name),
+        )?;
+    }
+    Ok(())
+}
+
+/// A `bwrap` command that runs `cargo` in the sandbox, with read access to the `read_only`
+/// paths, e.g. the lintcheck config, which may be temporary files. Arguments and environment
+/// variables added to it are passed on to cargo.
+pub fn cargo<'a>(clippy: &Checkout, read_only: impl IntoIterator<Item = &'a Path>) -> Command {
+    let cargo_home = env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")));
+    let mut command = Command::new("bwrap");
+    command
+        .args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"])
+        .args(["--tmpfs", "/tmp", "--unshare-net", "--die-with-parent"]);
+    for dir in [
+        Some(clippy.path.clone()),
+        Some(clippy.target_dir()),
+        cargo_home,
+    ]
+    .iter()
+    .flatten()
+    {
+        command.arg("--bind").arg(dir).arg(dir);
+    }
+    for path in read_only {
+        command.arg("--ro-bind").arg(path).arg(path);
+    }
+    command.arg("cargo").env("CARGO_NET_OFFLINE", "true");
+    command
+}
+
+fn run(command: &mut Command, what: &str) -> Result<(), String> {
+    let output = command
+        .output()
+        .map_err(|e| format!("{} failed: {}", what, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            what,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
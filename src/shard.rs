@@ -0,0 +1,185 @@
+//! Deterministic sharding of the crates of a run across CI jobs with `--shard i/n`, and the
+//! aggregation of the shards into one report.
+//!
+//! Every shard writes `logs/shard.json` with its jobs and failures. `aggregate` reads the logs
+//! directories of all shards, merges the logs of every job into `logs/`, with the shards every
+//! warning came from in `logs/<job>_merged.json`, and decides on the outcome of the whole run
+//! from the failures of all shards.
+
+use crate::{
+    badge::Badge,
+    crates::CratesConfig,
+    failure::{Failure, FailureKind},
+    gzip,
+    lints::LintIndex,
+    log::Log,
+    merge::Merged,
+    publish, summary,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+pub const SHARD_PATH: &str = "logs/shard.json";
+
+/// The `index`th of `count` shards, counting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid shard {}, expected e.g. 2/8", s);
+        let (index, count) = s.split_once('/').ok_or_else(invalid)?;
+        let index = index.parse().map_err(|_| invalid())?;
+        let count = count.parse().map_err(|_| invalid())?;
+        if index == 0 || index > count {
+            return Err(invalid());
+        }
+        Ok(Self { index, count })
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+impl Shard {
+    /// Whether the crate `name` belongs to this shard. Crates are assigned by the hash of their
+    /// name, so that adding a crate to a config doesn't move the others to other shards and all
+    /// versions of a crate end up in the same shard.
+    pub fn contains(&self, name: &str) -> bool {
+        // FNV-1a, which unlike `DefaultHasher` is the same on every machine and Rust version.
+        let hash = name.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+        hash % self.count as u64 == (self.index - 1) as u64
+    }
+
+    /// The crates of `config` that belong to this shard.
+    pub fn select(&self, config: &CratesConfig) -> CratesConfig {
+        CratesConfig {
+            crates: config
+                .crates
+                .iter()
+                .filter(|(_, source)| self.contains(&source.name))
+                .map(|(key, source)| (key.clone(), source.clone()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// The content of `logs/shard.json`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The shard in the `i/n` notation
+    pub shard: String,
+    /// Names of the jobs whose logs the shard wrote
+    pub jobs: Vec<String>,
+    pub failures: Vec<Failure>,
+}
+
+impl Manifest {
+    pub fn write(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).expect("manifest is serializable") + "\n";
+        publish::write(Path::new(SHARD_PATH), json)
+            .map_err(|e| format!("couldn't write {}: {}", SHARD_PATH, e))
+    }
+
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let path = dir.join("shard.json");
+        let json = fs::read_to_string(&path)
+            .map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        serde_json::from_str(&json).map_err(|e| format!("invalid {}: {}", path.display(), e))
+    }
+}
+
+/// Merges the logs of the shards whose logs directories are `dirs` into `logs/`. Returns the
+/// failures of all shards, with their log paths pointing into `dirs`, and a failure for every
+/// shard that is missing or occurs twice.
+pub fn aggregate(dirs: &[PathBuf]) -> Vec<Failure> {
+    let error = |message| Failure::new(FailureKind::Environment, "aggregate", message);
+    let mut failures = Vec::new();
+    let mut shards = BTreeMap::new();
+    let mut count = None;
+    for dir in dirs {
+        let manifest = match Manifest::load(dir) {
+            Ok(manifest) => manifest,
+            Err(message) => return vec![error(message)],
+        };
+        let shard: Shard = match manifest.shard.parse() {
+            Ok(shard) => shard,
+            Err(message) => return vec![error(message)],
+        };
+        if *count.get_or_insert(shard.count) != shard.count {
+            return vec![error(format!(
+                "{} is shard {}, but other shards are of {}",
+                dir.display(),
+                shard,
+                count.unwrap_or_default()
+            ))];
+        }
+        if shards.insert(shard.index, (dir, manifest.jobs)).is_some() {
+            failures.push(error(format!("shard {} was given twice", shard)));
+        }
+        for mut failure in manifest.failures {
+            for log in &mut failure.logs {
+                if let Ok(file) = log.strip_prefix("logs") {
+                    *log = dir.join(file);
+                }
+            }
+            failures.push(failure);
+        }
+    }
+    let count = count.unwrap_or_default();
+    for index in (1..=count).filter(|index| !shards.contains_key(index)) {
+        failures.push(error(format!("shard {}/{} is missing", index, count)));
+    }
+
+    let mut jobs = BTreeMap::<&str, Vec<&Path>>::new();
+    for (dir, names) in shards.values() {
+        for name in names {
+            jobs.entry(name).or_default().push(dir);
+        }
+    }
+    for (job, dirs) in jobs {
+        let mut merged = Merged::new();
+        for dir in &dirs {
+            let path = gzip::find(dir.join(format!("{}_logs.txt", job)));
+            match Log::load(&path) {
+                Ok(log) => merged.add(&path.display().to_string(), log),
+                Err(message) => failures.push(Failure::new(FailureKind::Lintcheck, job, message)),
+            }
+        }
+        let log_path = format!("logs/{}_logs.txt", job);
+        let provenance_path = format!("logs/{}_merged.json", job);
+        let written = publish::write(Path::new(&log_path), merged.log().render())
+            .map_err(|e| format!("couldn't write {}: {}", log_path, e))
+            .and_then(|()| merged.write(Path::new(&provenance_path)));
+        if let Err(message) = written {
+            failures.push(Failure::new(FailureKind::Environment, job, message));
+            continue;
+        }
+        print!(
+            "[{}] summary of {} shard(s):\n{}",
+            job,
+            dirs.len(),
+            summary::render(&merged.log().warnings, &LintIndex::default())
+        );
+    }
+    if let Err(message) = Badge::new(None, &failures).write(None) {
+        failures.push(error(message));
+    }
+    failures
+}
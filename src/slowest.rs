@@ -0,0 +1,60 @@
+//! The slowest crates of a run, with their share of the time linting took and how much slower or
+//! faster they got since they were linted before, so that corpus maintainers can decide whether
+//! a crate is worth its cost in CI minutes.
+
+use crate::{
+    crates::CratesConfig,
+    history::{self, History},
+    lock, publish,
+};
+use std::{collections::BTreeSet, path::Path};
+
+/// Number of crates listed.
+const COUNT: usize = 10;
+
+/// Prints the slowest crates of the config at `config` that the run of `job` linted, i.e. that
+/// were linted at or after `since`, in seconds since the Unix epoch, and writes them to
+/// `logs/<job>_slowest.txt`. Nothing is written if the run didn't lint any crates.
+pub fn report(job: &str, config: &Path, since: u64) -> Result<(), String> {
+    let config = CratesConfig::load(config)?;
+    let krates = config.krates();
+    let names: BTreeSet<&str> = krates.iter().map(|krate| krate.name.as_str()).collect();
+    let history = {
+        let _shared = lock::shared_files();
+        History::load(Path::new(history::HISTORY_PATH))?
+    };
+    let durations = history.durations_since(names, since);
+    if durations.is_empty() {
+        return Ok(());
+    }
+    let total: f64 = durations.iter().map(|(_, duration)| duration.seconds).sum();
+    let slowest = &durations[..durations.len().min(COUNT)];
+    let width = slowest
+        .iter()
+        .map(|(krate, _)| krate.len())
+        .max()
+        .unwrap_or_default();
+    let mut text = format!(
+        "{} slowest of {} crate(s), which took {:.1}s to lint:\n",
+        slowest.len(),
+        durations.len(),
+        total
+    );
+    for (krate, duration) in slowest {
+        let change = duration.previous.map_or_else(
+            || "new".to_string(),
+            |previous| format!("{:+.1}s", duration.seconds - previous),
+        );
+        text.push_str(&format!(
+            "    {:width$} {:>9.1}s {:>5.1}% {:>9}\n",
+            krate,
+            duration.seconds,
+            duration.seconds / total.max(f64::EPSILON) * 100.0,
+            change,
+            width = width
+        ));
+    }
+    print!("[{}] {}", job, text);
+    let path = format!("logs/{}_slowest.txt", job);
+    publish::write(Path::new(&path), text).map_err(|e| format!("couldn't write {}: {}", path, e))
+}
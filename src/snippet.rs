@@ -0,0 +1,68 @@
+//! Snippets of the code warnings point at, read from the crate sources lintcheck extracted, so
+//! that triaging a warning doesn't require downloading the crate.
+
+use crate::{crates::LINTCHECK_SOURCES, log::Warning, wrapper_config::Checkout};
+use std::fs;
+
+/// Number of lines shown before and after the line of a warning.
+const CONTEXT_LINES: usize = 2;
+
+/// The lines around `warning` with a marker under its column, `None` if the sources of its crate
+/// aren't in the checkout.
+pub fn render(clippy: &Checkout, warning: &Warning) -> Option<String> {
+    let sources = clippy.path.join(LINTCHECK_SOURCES);
+    let text = [
+        format!("{}-{}", warning.krate, warning.version),
+        warning.krate.clone(),
+    ]
+    .iter()
+    .find_map(|dir| fs::read_to_string(sources.join(dir).join(&warning.file)).ok())?;
+    let lines: Vec<&str> = text.lines().collect();
+    let line = warning
+        .line
+        .checked_sub(1)
+        .filter(|&line| line < lines.len())?;
+    let first = line.saturating_sub(CONTEXT_LINES);
+    let last = (line + CONTEXT_LINES).min(lines.len() - 1);
+    let width = (last + 1).to_string().len();
+    let mut snippet = String::new();
+    for (index, text) in lines.iter().enumerate().take(last + 1).skip(first) {
+        snippet.push_str(&format!(
+            "{:>width$} | {}\n",
+            index + 1,
+            text,
+            width = width
+        ));
+        if index == line {
+            let column = warning.column.unwrap_or(1).max(1);
+            snippet.push_str(&format!(
+                "{:>width$} | {:>column$} {}\n",
+                "",
+                "^",
+                warning.lint,
+                width = width,
+                column = column
+            ));
+        }
+    }
+    Some(snippet)
+}
+
+/// A Markdown section with the snippet of every warning whose sources are available.
+pub fn section<'a>(clippy: &Checkout, warnings: impl IntoIterator<Item = &'a Warning>) -> String {
+    let mut section = String::from("\n## Snippets\n");
+    for warning in warnings {
+        if let Some(snippet) = render(clippy, warning) {
+            section.push_str(&format!(
+                "\n### {} {} `{}:{}`\n\n{}\n\n```text\n{}```\n",
+                warning.krate,
+                warning.version,
+                warning.file,
+                warning.line,
+                warning.message,
+                snippet
+            ));
+        }
+    }
+    section
+}
@@ -0,0 +1,77 @@
+//! `--stability-check`, which lints the crates of a job a second time with the same clippy and
+//! reports the findings that differ between the two runs. Such findings come from nondeterminism
+//! in a lint or in rustc rather than from a change of clippy, so they are listed apart from the
+//! new warnings instead of being bisected.
+//!
+//! The builds of lintcheck are removed before the second run, because cargo would otherwise
+//! replay the diagnostics of the first one. The downloaded crates and the build of clippy are
+//! reused.
+
+use crate::{
+    diff::Diff,
+    failure::{Failure, FailureKind},
+    job::{Job, RunOptions},
+    lintcheck,
+    log::{Log, Warning},
+    publish, summary, suppress,
+    wrapper_config::Checkout,
+};
+use std::path::Path;
+
+/// Runs lintcheck on the config of `job` again and diffs the result against `first`, the log
+/// of the first run with the suppressions applied. Returns the findings of both runs that
+/// differ.
+///
+/// Writes `logs/<job>_rerun_logs.txt` and `logs/<job>_unstable.txt`.
+pub fn check(
+    clippy: &Checkout,
+    job: &Job,
+    first: &Log,
+    options: &RunOptions,
+) -> Result<Vec<Warning>, Failure> {
+    let error = |message| Failure::new(FailureKind::Environment, &job.name, message);
+    let msrv_conf = job.msrv_conf(options).map_err(error)?;
+    let clippy_conf = msrv_conf.as_ref().or(options.clippy_conf.as_ref());
+    lintcheck::clean_builds(clippy).map_err(error)?;
+    println!("[{}] linting the crates again to check stability", job.name);
+    let rerun_path = format!("logs/{}_rerun_logs.txt", job.name);
+    lintcheck::check(
+        clippy,
+        &job.config,
+        &format!("{}_rerun", job.name),
+        clippy_conf.map(|conf| conf.dir.as_path()),
+        Path::new(&rerun_path),
+        options,
+    )?;
+    let mut rerun = Log::load(&rerun_path).map_err(error)?;
+    suppress::apply(&options.suppress, &mut rerun);
+
+    let diff = Diff::new(&first.warnings, &rerun.warnings);
+    let unstable_path = format!("logs/{}_unstable.txt", job.name);
+    publish::write(&unstable_path, diff.render(&options.lints))
+        .map_err(|e| error(format!("couldn't write {}: {}", unstable_path, e)))?;
+    let unstable: Vec<Warning> = diff
+        .added
+        .iter()
+        .copied()
+        .chain(diff.removed.iter().copied())
+        .chain(diff.reworded.iter().flat_map(|&(old, new)| [old, new]))
+        .cloned()
+        .collect();
+    if unstable.is_empty() {
+        println!(
+            "[{}] stability check: both runs have the same findings",
+            job.name
+        );
+    } else {
+        print!(
+            "[{}] stability check: {} finding(s) differ between two runs of the same clippy, see \
+             {}\n{}",
+            job.name,
+            unstable.len(),
+            unstable_path,
+            summary::render(&unstable, &options.lints)
+        );
+    }
+    Ok(unstable)
+}
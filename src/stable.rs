@@ -0,0 +1,88 @@
+//! Comparison of the clippy checkout against the clippy of an installed toolchain, answering
+//! "what will users newly see with the next release".
+
+use crate::{
+    clippy::Clippy,
+    crates::CratesConfig,
+    diff::Diff,
+    failure::{Failure, FailureKind},
+    job::Job,
+    lints::LintIndex,
+    log::Log,
+    publish,
+    wrapper_config::Checkout,
+};
+use std::{collections::BTreeMap, path::Path};
+
+/// Lints the crates of `job`, which lintcheck already downloaded into the `clippy` checkout,
+/// with the clippy of `toolchain` and diffs the result against the `master` log of the checkout.
+///
+/// Writes `logs/<job>_stable_logs.txt` and `logs/<job>_stable_diff.txt`.
+pub fn compare(
+    clippy: &Checkout,
+    job: &Job,
+    toolchain: &str,
+    master: &Log,
+    lints: &LintIndex,
+) -> Result<(), Failure> {
+    let error = |kind, message: String| Failure::new(kind, &job.name, message);
+    let config = CratesConfig::load(&job.config).map_err(|e| error(FailureKind::Environment, e))?;
+
+    let stable_clippy = Clippy::Toolchain(toolchain);
+    let mut stable = Log {
+        clippy_version: stable_clippy.version(),
+        complete: true,
+        ..Log::default()
+    };
+    let target_dir = clippy.path.join("target/lintcheck/stable");
+    for krate in config.krates() {
+        let source_dir = clippy.path.join(&krate.source_dir);
+        if !source_dir.exists() {
+            eprintln!(
+                "skipping {} {}: sources not found at {}",
+                krate.name,
+                krate.version,
+                source_dir.display()
+            );
+            continue;
+        }
+        println!(
+            "Linting {} {} with {} clippy...",
+            krate.name, krate.version, toolchain
+        );
+        let output = stable_clippy
+            .lint(&krate, &source_dir, &target_dir)
+            .map_err(|e| error(FailureKind::Lintcheck, e))?;
+        stable
+            .warnings
+            .extend(output.diagnostics.into_iter().map(|d| d.warning));
+    }
+
+    let stable_path = format!("logs/{}_stable_logs.txt", job.name);
+    write(Path::new(&stable_path), &stable.render())
+        .map_err(|e| error(FailureKind::Environment, e))?;
+    let diff = Diff::new(&stable.warnings, &master.warnings);
+    let diff_path = format!("logs/{}_stable_diff.txt", job.name);
+    write(Path::new(&diff_path), &diff.render(lints))
+        .map_err(|e| error(FailureKind::Environment, e))?;
+
+    println!(
+        "{} warning(s) only with the checkout, {} only with {} clippy, see {}",
+        diff.added.len(),
+        diff.removed.len(),
+        toolchain,
+        diff_path
+    );
+    let mut new_lints = BTreeMap::<_, usize>::new();
+    for warning in &diff.added {
+        *new_lints.entry(&warning.lint).or_default() += 1;
+    }
+    for (lint, count) in new_lints {
+        println!("    {} {}", lint, count);
+    }
+    Ok(())
+}
+
+fn write(path: &Path, text: &str) -> Result<(), String> {
+    publish::write(path, text).map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+}
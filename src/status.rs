@@ -0,0 +1,207 @@
+//! The status of a run in progress, served on the Unix socket `logs/status.sock` while the run
+//! is active, so that long runs can be looked into with `clippy-lintcheck status`.
+//!
+//! The status is built from the same events as `--events`, see `observe`, and the phases the
+//! run sets. A connection to the socket receives the status as JSON and is closed.
+
+use crate::events::Event;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const SOCKET_PATH: &str = "logs/status.sock";
+
+static STATE: Mutex<State> = Mutex::new(State {
+    phase: String::new(),
+    start: None,
+    linting_start: None,
+    configs: Vec::new(),
+});
+
+#[derive(Debug)]
+struct State {
+    phase: String,
+    start: Option<Instant>,
+    /// When the first config started linting
+    linting_start: Option<Instant>,
+    configs: Vec<ConfigStatus>,
+}
+
+/// What a connection to the socket receives.
+#[derive(Debug, Serialize, Deserialize)]
+struct Report {
+    phase: String,
+    elapsed_secs: u64,
+    configs: Vec<ConfigStatus>,
+    /// Estimated from the rate at which lintcheck started linting the crates so far
+    eta_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ConfigStatus {
+    name: String,
+    /// Number of crates of the config
+    crates: usize,
+    /// Crates lintcheck started linting
+    started: usize,
+    /// Whether the config finished, including its checks
+    finished: bool,
+}
+
+/// Serves the status on the socket while it is alive. Dropping it removes the socket.
+#[derive(Debug)]
+pub struct Server {
+    path: PathBuf,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Starts serving the status on the socket at `path`. A socket left behind by a run that didn't
+/// exit cleanly is replaced, which is safe because the caller holds the lock on the logs.
+pub fn serve(path: &Path) -> Result<Server, String> {
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != ErrorKind::NotFound {
+            return Err(format!("couldn't remove {}: {}", path.display(), e));
+        }
+    }
+    let listener =
+        UnixListener::bind(path).map_err(|e| format!("couldn't bind {}: {}", path.display(), e))?;
+    *STATE.lock().unwrap_or_else(PoisonError::into_inner) = State {
+        phase: "starting".to_string(),
+        start: Some(Instant::now()),
+        linting_start: None,
+        configs: Vec::new(),
+    };
+    // The thread blocks in `accept` until the process exits.
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let json = serde_json::to_string(&report()).expect("status is serializable");
+            // The client going away early is its own business.
+            let _ = stream.write_all(json.as_bytes());
+        }
+    });
+    Ok(Server {
+        path: path.to_path_buf(),
+    })
+}
+
+/// Sets the phase the run is in, e.g. "building clippy".
+pub fn set_phase(phase: &str) {
+    STATE.lock().unwrap_or_else(PoisonError::into_inner).phase = phase.to_string();
+}
+
+/// Updates the status with an event of the run.
+pub fn observe(event: &Event<'_>) {
+    let mut state = STATE.lock().unwrap_or_else(PoisonError::into_inner);
+    match *event {
+        Event::ConfigStarted { config, crates } => {
+            state.linting_start.get_or_insert_with(Instant::now);
+            state.configs.push(ConfigStatus {
+                name: config.to_string(),
+                crates,
+                started: 0,
+                finished: false,
+            });
+        }
+        Event::CrateStarted { config, .. } => {
+            // Batches of a config are checked as `<config>_batch<i>`.
+            if let Some(status) = state.configs.iter_mut().rev().find(|status| {
+                config == status.name
+                    || config
+                        .strip_prefix(status.name.as_str())
+                        .is_some_and(|rest| rest.starts_with("_batch"))
+            }) {
+                status.started = (status.started + 1).min(status.crates);
+            }
+        }
+        Event::ConfigFinished { config, .. } => {
+            if let Some(status) = state
+                .configs
+                .iter_mut()
+                .rev()
+                .find(|status| status.name == config)
+            {
+                status.finished = true;
+                status.started = status.crates;
+            }
+        }
+        Event::RunFinished { .. } => state.phase = "finished".to_string(),
+        _ => {}
+    }
+}
+
+fn report() -> Report {
+    let state = STATE.lock().unwrap_or_else(PoisonError::into_inner);
+    let total: usize = state.configs.iter().map(|status| status.crates).sum();
+    let started: usize = state.configs.iter().map(|status| status.started).sum();
+    let eta_secs = state
+        .linting_start
+        .filter(|_| started > 0 && started < total)
+        .map(|linting_start| {
+            let per_crate = linting_start.elapsed().as_secs_f64() / started as f64;
+            (per_crate * (total - started) as f64) as u64
+        });
+    Report {
+        phase: state.phase.clone(),
+        elapsed_secs: state.start.map_or(0, |start| start.elapsed().as_secs()),
+        configs: state.configs.clone(),
+        eta_secs,
+    }
+}
+
+/// Asks the run serving the socket at `path` for its status and describes it.
+pub fn query(path: &Path) -> Result<String, String> {
+    let mut stream = UnixStream::connect(path).map_err(|e| {
+        format!(
+            "no run is in progress, couldn't connect to {}: {}",
+            path.display(),
+            e
+        )
+    })?;
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .map_err(|e| format!("couldn't set a timeout on {}: {}", path.display(), e))?;
+    let mut json = String::new();
+    stream
+        .read_to_string(&mut json)
+        .map_err(|e| format!("couldn't read the status from {}: {}", path.display(), e))?;
+    let report: Report = serde_json::from_str(&json)
+        .map_err(|e| format!("invalid status from {}: {}", path.display(), e))?;
+    let mut out = format!(
+        "phase: {}\nelapsed: {}\n",
+        report.phase,
+        duration(report.elapsed_secs)
+    );
+    for config in &report.configs {
+        out.push_str(&format!(
+            "{}: {}/{} crate(s) started{}\n",
+            config.name,
+            config.started,
+            config.crates,
+            if config.finished { ", finished" } else { "" }
+        ));
+    }
+    if let Some(eta_secs) = report.eta_secs {
+        out.push_str(&format!("eta: {}\n", duration(eta_secs)));
+    }
+    Ok(out)
+}
+
+fn duration(secs: u64) -> String {
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+    }
+}
@@ -0,0 +1,90 @@
+//! Expected and actual results that don't match, rendered as a structural diff: the differing
+//! entries are grouped under the section they belong to, e.g. the crate of a warning, with `-`
+//! for the expected entries that are missing and `+` for the unexpected ones. The diff is
+//! colored on terminals and plain in CI, in files and in failure messages.
+
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+/// Number of differences shown by default, the others are only counted.
+pub const MAX_DIFFERENCES: usize = 20;
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// The differences of one section of the results.
+#[derive(Debug, Default)]
+pub struct Section {
+    pub title: String,
+    /// Expected entries that are missing
+    pub missing: Vec<String>,
+    /// Entries that weren't expected
+    pub unexpected: Vec<String>,
+}
+
+impl Section {
+    fn len(&self) -> usize {
+        self.missing.len() + self.unexpected.len()
+    }
+}
+
+/// Number of differences of `sections`.
+pub fn count(sections: &[Section]) -> usize {
+    sections.iter().map(Section::len).sum()
+}
+
+/// Whether diffs printed to stdout are colored: only on terminals, and neither in CI nor with
+/// `NO_COLOR` set.
+pub fn color() -> bool {
+    io::stdout().is_terminal() && env::var_os("CI").is_none() && env::var_os("NO_COLOR").is_none()
+}
+
+/// Renders the differences of `sections` between what `expected` and `actual` name, at most
+/// `max` of them followed by the number of the others.
+pub fn render(
+    expected: &str,
+    actual: &str,
+    sections: &[Section],
+    max: usize,
+    color: bool,
+) -> String {
+    let paint = |style: &str, line: String| {
+        if color {
+            format!("{}{}{}\n", style, line, RESET)
+        } else {
+            line + "\n"
+        }
+    };
+    let mut text =
+        paint(BOLD, format!("--- {}", expected)) + &paint(BOLD, format!("+++ {}", actual));
+    let mut shown = 0;
+    for section in sections.iter().filter(|section| section.len() > 0) {
+        if shown == max {
+            break;
+        }
+        text.push_str(&paint(BOLD, format!("@@ {} @@", section.title)));
+        let lines = section
+            .missing
+            .iter()
+            .map(|entry| (RED, format!("-{}", entry)))
+            .chain(
+                section
+                    .unexpected
+                    .iter()
+                    .map(|entry| (GREEN, format!("+{}", entry))),
+            );
+        for (style, line) in lines.take(max - shown) {
+            text.push_str(&paint(style, line));
+            shown += 1;
+        }
+    }
+    let total = count(sections);
+    if total > shown {
+        text.push_str(&format!("... and {} more difference(s)\n", total - shown));
+    }
+    text
+}
@@ -0,0 +1,125 @@
+//! Summaries of lintcheck logs.
+
+use crate::{
+    lints::{Level, LintIndex},
+    log::{Log, Warning},
+};
+use std::collections::BTreeMap;
+
+/// Number of lints listed per level.
+const TOP_LINTS: usize = 5;
+
+/// Groups `warnings` by the default level of their lint.
+pub fn by_level<'a>(
+    warnings: impl IntoIterator<Item = &'a Warning>,
+    lints: &LintIndex,
+) -> BTreeMap<Level, BTreeMap<&'a str, usize>> {
+    let mut levels = BTreeMap::<_, BTreeMap<_, usize>>::new();
+    for warning in warnings {
+        *levels
+            .entry(lints.level(&warning.lint))
+            .or_default()
+            .entry(warning.lint.as_str())
+            .or_default() += 1;
+    }
+    levels
+}
+
+/// Number of warnings per lint group.
+pub fn by_group<'a, 'l>(
+    warnings: impl IntoIterator<Item = &'a Warning>,
+    lints: &'l LintIndex,
+) -> BTreeMap<&'l str, usize> {
+    let mut groups = BTreeMap::new();
+    for warning in warnings {
+        *groups.entry(lints.group(&warning.lint)).or_default() += 1;
+    }
+    groups
+}
+
+/// Renders a summary of `warnings` grouped by level, listing the most frequent lints of each
+/// level, followed by the number of warnings per group.
+pub fn render<'a>(
+    warnings: impl IntoIterator<Item = &'a Warning> + Clone,
+    lints: &LintIndex,
+) -> String {
+    let mut out = String::new();
+    for (level, counts) in by_level(warnings.clone(), lints) {
+        let total: usize = counts.values().sum();
+        out.push_str(&format!(
+            "{}: {} warning(s) from {} lint(s)\n",
+            level,
+            total,
+            counts.len()
+        ));
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_by(|(a_lint, a), (b_lint, b)| b.cmp(a).then(a_lint.cmp(b_lint)));
+        for (lint, count) in counts.into_iter().take(TOP_LINTS) {
+            match lints.get(lint) {
+                Some(info) => out.push_str(&format!("    {} ({}) {}\n", lint, info.group, count)),
+                None => out.push_str(&format!("    {} {}\n", lint, count)),
+            }
+        }
+    }
+    let groups = by_group(warnings, lints);
+    if !groups.is_empty() {
+        let groups: Vec<_> = groups
+            .into_iter()
+            .map(|(group, count)| format!("{} {}", group, count))
+            .collect();
+        out.push_str(&format!("groups: {}\n", groups.join(", ")));
+    }
+    out
+}
+
+/// Renders the counts of a whole log: the totals, then the warnings per lint, the warnings per
+/// crate and the ICEs per crate, most frequent first.
+pub fn counts(log: &Log) -> String {
+    let mut lints = BTreeMap::<&str, usize>::new();
+    let mut crates = BTreeMap::<String, usize>::new();
+    for warning in &log.warnings {
+        *lints.entry(&warning.lint).or_default() += 1;
+        *crates
+            .entry(format!("{} {}", warning.krate, warning.version))
+            .or_default() += 1;
+    }
+    let mut ices = BTreeMap::<&str, usize>::new();
+    for ice in &log.ices {
+        *ices
+            .entry(ice.krate.as_deref().unwrap_or("unknown"))
+            .or_default() += 1;
+    }
+
+    let mut out = format!(
+        "{} warning(s) from {} lint(s) in {} crate(s), {} ICE(s)\n",
+        log.warnings.len(),
+        lints.len(),
+        crates.len(),
+        log.ices.len()
+    );
+    let sections = [
+        ("warnings per lint", sorted(lints)),
+        ("warnings per crate", sorted(crates)),
+        ("ICEs per crate", sorted(ices)),
+    ];
+    for (title, counts) in sections {
+        if counts.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("{}:\n", title));
+        for (name, count) in counts {
+            out.push_str(&format!("    {} {}\n", name, count));
+        }
+    }
+    out
+}
+
+/// The entries of `counts` as strings, the largest count first.
+fn sorted<K: ToString>(counts: BTreeMap<K, usize>) -> Vec<(String, usize)> {
+    let mut counts: Vec<_> = counts
+        .into_iter()
+        .map(|(name, count)| (name.to_string(), count))
+        .collect();
+    counts.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then(a_name.cmp(b_name)));
+    counts
+}
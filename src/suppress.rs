@@ -0,0 +1,92 @@
+//! Suppression rules of the wrapper config, which drop known noise from the logs after parsing,
+//! before any expectation is checked or baseline is diffed.
+//!
+//! ```toml
+//! [[suppress]]
+//! # optional, shown in the report of the rules
+//! name = "generated bindings"
+//! # patterns, see `pattern`, that must all match
+//! crate = "^windows"
+//! path = "^src/bindings/"
+//! lint = "clippy::(missing_safety_doc|too_many_arguments)"
+//! message = "..."
+//! ```
+
+use crate::{
+    log::{Log, Warning},
+    pattern::Regex,
+};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    name: Option<String>,
+    lint: Option<Regex>,
+    #[serde(rename = "crate")]
+    krate: Option<Regex>,
+    path: Option<Regex>,
+    message: Option<Regex>,
+}
+
+impl Rule {
+    pub fn matches(&self, warning: &Warning) -> bool {
+        [
+            (&self.lint, &warning.lint),
+            (&self.krate, &warning.krate),
+            (&self.path, &warning.file),
+            (&self.message, &warning.message),
+        ]
+        .iter()
+        .all(|(pattern, text)| {
+            pattern
+                .as_ref()
+                .is_none_or(|pattern| pattern.is_match(text))
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let patterns: Vec<_> = [
+            ("lint", &self.lint),
+            ("crate", &self.krate),
+            ("path", &self.path),
+            ("message", &self.message),
+        ]
+        .iter()
+        .filter_map(|(field, pattern)| Some(format!("{} = `{}`", field, pattern.as_ref()?)))
+        .collect();
+        patterns.join(", ")
+    }
+}
+
+/// Removes the warnings of `log` that a rule matches. Returns the number of warnings every rule
+/// suppressed, counting each warning for the first rule that matches it.
+pub fn apply(rules: &[Rule], log: &mut Log) -> Vec<usize> {
+    let mut counts = vec![0; rules.len()];
+    log.warnings.retain(
+        |warning| match rules.iter().position(|rule| rule.matches(warning)) {
+            Some(index) => {
+                counts[index] += 1;
+                false
+            }
+            None => true,
+        },
+    );
+    counts
+}
+
+/// Renders the number of warnings every rule suppressed, so that rules that no longer suppress
+/// anything can be removed.
+pub fn render(rules: &[Rule], counts: &[usize]) -> String {
+    if rules.is_empty() {
+        return String::new();
+    }
+    let mut out = format!("suppressed: {} warning(s)\n", counts.iter().sum::<usize>());
+    for (rule, count) in rules.iter().zip(counts) {
+        out.push_str(&format!("    {} {}\n", rule.describe(), count));
+    }
+    out
+}
@@ -0,0 +1,255 @@
+//! The `triage` subcommand, which walks the new findings of a config one by one and records a
+//! verdict with an optional note for each of them in `triage.json`, so that the next triage only
+//! shows the findings nobody looked at yet and runs can tell which of their new warnings are
+//! known false positives.
+//!
+//! Verdicts are keyed by the fingerprint of a finding, which is made of its crate, file, lint and
+//! message. The version of the crate and the line are left out, so that a verdict carries over
+//! to newer versions of the crate and to code that moved.
+
+use crate::{
+    diff::Diff,
+    gzip,
+    log::{Log, Warning},
+    publish, snippet,
+    wrapper_config::Checkout,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::{self, BufRead, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const VERDICTS_PATH: &str = "triage.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Verdict {
+    TruePositive,
+    FalsePositive,
+    NeedsInvestigation,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::TruePositive => "true positive",
+            Self::FalsePositive => "false positive",
+            Self::NeedsInvestigation => "needs investigation",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Entry {
+    pub verdict: Verdict,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// The finding as it was logged when the verdict was given
+    pub warning: String,
+    /// The code of the finding, if its sources were available
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// Seconds since the Unix epoch
+    pub time: u64,
+}
+
+/// The verdicts of all triaged findings, by fingerprint.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Verdicts {
+    verdicts: BTreeMap<String, Entry>,
+}
+
+impl Verdicts {
+    /// Loads the verdicts from `path`. A missing file is treated as no verdicts.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| format!("invalid verdicts {}: {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("couldn't read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).expect("verdicts are serializable");
+        publish::write(path, json + "\n")
+            .map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.verdicts.is_empty()
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.verdicts.values()
+    }
+
+    pub fn get(&self, warning: &Warning) -> Option<&Entry> {
+        self.verdicts.get(&fingerprint(warning))
+    }
+
+    fn insert(
+        &mut self,
+        warning: &Warning,
+        verdict: Verdict,
+        note: Option<String>,
+        snippet: Option<String>,
+    ) {
+        self.verdicts.insert(
+            fingerprint(warning),
+            Entry {
+                verdict,
+                note,
+                warning: warning.to_string(),
+                snippet,
+                time: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |since| since.as_secs()),
+            },
+        );
+    }
+
+    /// Describes how many of `warnings` were triaged, with which verdicts.
+    pub fn describe<'a>(&self, warnings: impl IntoIterator<Item = &'a Warning>) -> String {
+        let mut counts = BTreeMap::<Verdict, usize>::new();
+        let mut untriaged = 0;
+        for warning in warnings {
+            match self.get(warning) {
+                Some(entry) => *counts.entry(entry.verdict).or_default() += 1,
+                None => untriaged += 1,
+            }
+        }
+        let mut parts: Vec<_> = counts
+            .iter()
+            .map(|(verdict, count)| format!("{} {}", count, verdict))
+            .collect();
+        parts.push(format!("{} untriaged", untriaged));
+        parts.join(", ")
+    }
+}
+
+/// The fingerprint of `warning`, the hash of its crate, file, lint and message.
+pub fn fingerprint(warning: &Warning) -> String {
+    hash([
+        warning.krate.as_str(),
+        &warning.file,
+        &warning.lint,
+        &warning.message,
+    ])
+}
+
+/// The 64 bit FNV-1a hash of `parts`, which is fast enough to be computed for every warning of a
+/// log.
+pub fn hash<'a>(parts: impl IntoIterator<Item = &'a str>) -> String {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    let mut hash = OFFSET;
+    for byte in parts
+        .into_iter()
+        .flat_map(|part| part.bytes().chain(Some(0)))
+    {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(PRIME);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Walks the findings of the log of `config` that aren't in the log of `baseline`, or all of
+/// its findings without a baseline, and asks for a verdict on each of them on `input`. The
+/// findings that already have a verdict are skipped unless `all` is set. The verdicts are saved
+/// to `verdicts_path` after every answer, so quitting early loses nothing.
+pub fn run(
+    config: &str,
+    baseline: Option<&Path>,
+    verdicts_path: &Path,
+    all: bool,
+    clippy: Option<&Checkout>,
+    mut input: impl BufRead,
+) -> Result<(), String> {
+    let log_name = format!("{}_logs.txt", config);
+    let log = Log::load(gzip::find(Path::new("logs").join(&log_name)))?;
+    let old = match baseline {
+        Some(baseline) => Log::load(gzip::find(baseline.join(&log_name)))?.warnings,
+        None => Vec::new(),
+    };
+    let diff = Diff::new(&old, &log.warnings);
+    let mut verdicts = Verdicts::load(verdicts_path)?;
+    let new: Vec<&Warning> = diff
+        .added
+        .iter()
+        .chain(diff.reworded.iter().map(|(_, new)| new))
+        .copied()
+        .collect();
+    let findings: Vec<&Warning> = new
+        .iter()
+        .copied()
+        .filter(|warning| all || verdicts.get(warning).is_none())
+        .collect();
+    println!(
+        "[{}] {} finding(s) to triage, verdicts are saved to {}",
+        config,
+        findings.len(),
+        verdicts_path.display()
+    );
+    let mut given = 0;
+    'findings: for (i, warning) in findings.iter().enumerate() {
+        println!("\n[{}/{}] {}", i + 1, findings.len(), warning);
+        let snippet = clippy.and_then(|clippy| snippet::render(clippy, warning));
+        if let Some(snippet) = &snippet {
+            print!("{}", snippet);
+        }
+        if let Some(entry) = verdicts.get(warning) {
+            println!(
+                "previous verdict: {}{}",
+                entry.verdict,
+                entry
+                    .note
+                    .as_ref()
+                    .map(|note| format!(" ({})", note))
+                    .unwrap_or_default()
+            );
+        }
+        let verdict = loop {
+            let answer = prompt(
+                &mut input,
+                "[t]rue positive, [f]alse positive, [n]eeds investigation, [s]kip, [q]uit: ",
+            )?;
+            match answer.as_deref() {
+                Some("t") => break Verdict::TruePositive,
+                Some("f") => break Verdict::FalsePositive,
+                Some("n") => break Verdict::NeedsInvestigation,
+                Some("s") => continue 'findings,
+                Some("q") | None => break 'findings,
+                Some(_) => {}
+            }
+        };
+        let note = prompt(&mut input, "note (empty for none): ")?.filter(|note| !note.is_empty());
+        verdicts.insert(warning, verdict, note, snippet);
+        verdicts.save(verdicts_path)?;
+        given += 1;
+    }
+    println!(
+        "\n[{}] {} verdict(s) given, the new findings are now: {}",
+        config,
+        given,
+        verdicts.describe(new)
+    );
+    Ok(())
+}
+
+/// Asks for a line on `input`, `None` at its end.
+fn prompt(input: &mut impl BufRead, question: &str) -> Result<Option<String>, String> {
+    print!("{}", question);
+    io::stdout()
+        .flush()
+        .map_err(|e| format!("couldn't write to stdout: {}", e))?;
+    let mut line = String::new();
+    let read = input
+        .read_line(&mut line)
+        .map_err(|e| format!("couldn't read the answer: {}", e))?;
+    Ok(Some(line.trim().to_string()).filter(|_| read > 0))
+}
@@ -0,0 +1,176 @@
+//! Refreshing the clippy checkout, e.g. the `rust-clippy` submodule, before a run.
+
+use crate::wrapper_config::Checkout;
+use std::{fmt, path::PathBuf, process::Command, str::FromStr};
+
+/// Ref checked out by `--update-clippy` without a value.
+pub const DEFAULT_REF: &str = "origin/master";
+
+/// Which of the refs GitHub keeps for a pull request is checked out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrRef {
+    /// The last commit of the pull request
+    Head,
+    /// The pull request merged into its base branch, as long as it merges cleanly
+    Merge,
+}
+
+impl FromStr for PrRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "head" => Ok(Self::Head),
+            "merge" => Ok(Self::Merge),
+            err => Err(format!("Invalid option {}", err)),
+        }
+    }
+}
+
+impl fmt::Display for PrRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Head => "head",
+            Self::Merge => "merge",
+        })
+    }
+}
+
+/// The ref GitHub keeps `pr_ref` of the pull request `pr` under, e.g. `pull/123/head`.
+pub fn pr_ref(pr: u64, pr_ref: PrRef) -> String {
+    format!("pull/{}/{}", pr, pr_ref)
+}
+
+/// Fetches `git_ref` in the checkout and checks it out, returning the SHA of the new `HEAD`.
+/// Refuses to touch a checkout with uncommitted changes unless `allow_dirty` is set.
+pub fn update(clippy: &Checkout, git_ref: &str, allow_dirty: bool) -> Result<String, String> {
+    ensure_updatable(clippy, allow_dirty)?;
+    fetch(clippy, git_ref)?;
+    git(clippy, &["checkout", "--detach", git_ref])?;
+    head_sha(clippy)
+}
+
+/// Fetches `pr_ref` of the pull request `pr` from `origin` and checks it out like `update`,
+/// returning the SHA of the new `HEAD`.
+pub fn update_to_pr(
+    clippy: &Checkout,
+    pr: u64,
+    pr_ref: PrRef,
+    allow_dirty: bool,
+) -> Result<String, String> {
+    ensure_updatable(clippy, allow_dirty)?;
+    let sha = fetch_pr(clippy, pr, pr_ref)?;
+    git(clippy, &["checkout", "--detach", &sha])?;
+    head_sha(clippy)
+}
+
+fn ensure_updatable(clippy: &Checkout, allow_dirty: bool) -> Result<(), String> {
+    ensure_worktree(clippy)?;
+    if !allow_dirty && !git(clippy, &["status", "--porcelain"])?.is_empty() {
+        return Err(format!(
+            "the clippy checkout at {} has uncommitted changes, pass --allow-dirty to update it \
+             anyway",
+            clippy.path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Fetches the remote branch `git_ref` is of, e.g. `origin/master`, or all of `origin` if it
+/// isn't of a remote.
+pub fn fetch(clippy: &Checkout, git_ref: &str) -> Result<(), String> {
+    let remotes = git(clippy, &["remote"])?;
+    match git_ref.split_once('/') {
+        Some((remote, branch)) if remotes.lines().any(|r| r == remote) => {
+            println!("fetching {} in the clippy checkout", git_ref);
+            git(clippy, &["fetch", remote, branch])?;
+        }
+        _ => {
+            println!("fetching origin in the clippy checkout");
+            git(clippy, &["fetch", "origin"])?;
+        }
+    }
+    Ok(())
+}
+
+/// Fetches `pr_ref` of the pull request `pr` from `origin`, e.g. rust-lang/rust-clippy,
+/// returning its SHA. GitHub only has a merge ref for pull requests that merge cleanly.
+pub fn fetch_pr(clippy: &Checkout, pr: u64, pr_ref: PrRef) -> Result<String, String> {
+    println!(
+        "fetching pull request #{} ({}) in the clippy checkout",
+        pr, pr_ref
+    );
+    git(clippy, &["fetch", "origin", &self::pr_ref(pr, pr_ref)])?;
+    git(clippy, &["rev-parse", "FETCH_HEAD"])
+}
+
+/// SHA of the commit `git_ref` points to.
+pub fn resolve(clippy: &Checkout, git_ref: &str) -> Result<String, String> {
+    git(
+        clippy,
+        &["rev-parse", "--verify", &format!("{}^{{commit}}", git_ref)],
+    )
+}
+
+/// SHA of the best common ancestor of `a` and `b`.
+pub fn merge_base(clippy: &Checkout, a: &str, b: &str) -> Result<String, String> {
+    git(clippy, &["merge-base", a, b])
+}
+
+/// The branch checked out in the checkout, or the SHA of a detached `HEAD`, so that it can be
+/// checked out again with `check_out`.
+pub fn current(clippy: &Checkout) -> Result<String, String> {
+    match git(clippy, &["symbolic-ref", "--quiet", "--short", "HEAD"]) {
+        Ok(branch) => Ok(branch),
+        Err(_) => head_sha(clippy),
+    }
+}
+
+/// Checks out `git_ref` without fetching anything.
+pub fn check_out(clippy: &Checkout, git_ref: &str) -> Result<(), String> {
+    ensure_worktree(clippy)?;
+    git(clippy, &["checkout", "--quiet", git_ref]).map(drop)
+}
+
+/// SHA of the commit checked out in the checkout.
+pub fn head_sha(clippy: &Checkout) -> Result<String, String> {
+    ensure_worktree(clippy)?;
+    git(clippy, &["rev-parse", "HEAD"])
+}
+
+/// Whether tracked files of the checkout have uncommitted changes, which its commit doesn't
+/// describe.
+pub fn is_dirty(clippy: &Checkout) -> Result<bool, String> {
+    git(clippy, &["status", "--porcelain", "--untracked-files=no"]).map(|status| !status.is_empty())
+}
+
+fn ensure_worktree(clippy: &Checkout) -> Result<(), String> {
+    // An uninitialized submodule is inside the worktree of the superproject, which mustn't be
+    // mistaken for the checkout.
+    let toplevel = git(clippy, &["rev-parse", "--show-toplevel"]).ok();
+    if toplevel.map(PathBuf::from).as_ref() != Some(&clippy.path) {
+        return Err(format!(
+            "{} isn't a git submodule or worktree",
+            clippy.path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Runs git in the checkout, returning its trimmed stdout.
+fn git(clippy: &Checkout, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(&clippy.path)
+        .args(args)
+        .output()
+        .map_err(|e| format!("couldn't execute git {}: {}", args[0], e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} in the clippy checkout failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
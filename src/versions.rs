@@ -0,0 +1,117 @@
+//! Updating the pinned versions of a config to the latest releases on crates.io, so that the
+//! corpus doesn't age until someone updates it by hand, and finding pinned versions that were
+//! yanked, which make runs irreproducible.
+
+use crate::{
+    crates::{self, CratesConfig},
+    crates_io, publish,
+};
+use std::{fs, path::Path};
+
+/// Bumps the version of every crate of the config at `path` that pins a single crates.io
+/// version to its latest release. The file is edited line by line, so that comments and
+/// formatting survive. With `dry_run` the changes are only printed.
+pub fn update(path: &Path, dry_run: bool) -> Result<(), String> {
+    if !crates::is_toml(&path.to_string_lossy()) {
+        return Err(format!(
+            "{} isn't a TOML config, only those can be updated in place",
+            path.display()
+        ));
+    }
+    let config = CratesConfig::load(path)?;
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let mut updated = 0;
+    for (key, source) in &config.crates {
+        let current = match source.versions.as_deref() {
+            Some([version]) if source.registry.is_none() => version,
+            Some([]) | None => continue,
+            Some(_) => {
+                println!("{}: pins several versions, left as is", key);
+                continue;
+            }
+        };
+        let versions = crates_io::versions(&source.name)?;
+        let latest = match crates_io::latest(&versions) {
+            Some(latest) => latest,
+            None => {
+                println!("{}: no release on crates.io", key);
+                continue;
+            }
+        };
+        if crates_io::release(latest) <= crates_io::release(current) {
+            continue;
+        }
+        let line = match entry_line(&lines, key) {
+            Some(i) => &mut lines[i],
+            None => {
+                println!("{}: entry not found, update it to {} by hand", key, latest);
+                continue;
+            }
+        };
+        let bumped = replace_version(line, current, latest);
+        println!("-{}\n+{}", line, bumped);
+        *line = bumped;
+        updated += 1;
+    }
+    if dry_run || updated == 0 {
+        println!("{} crate(s) can be updated", updated);
+        return Ok(());
+    }
+    println!("updated {} crate(s)", updated);
+    let mut text = lines.join("\n");
+    text.push('\n');
+    publish::write(path, text).map_err(|e| format!("couldn't write {}: {}", path.display(), e))
+}
+
+/// Index of the line of the `[crates]` entry `key` in the `lines` of a config, which is a single
+/// line in the configs of this repository.
+pub fn entry_line(lines: &[String], key: &str) -> Option<usize> {
+    lines.iter().position(|line| {
+        line.trim_start()
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with('='))
+    })
+}
+
+/// `line` with the quoted version `current` replaced by `new`.
+pub fn replace_version(line: &str, current: &str, new: &str) -> String {
+    ['\'', '"'].iter().fold(line.to_string(), |line, quote| {
+        line.replace(
+            &format!("{0}{1}{0}", quote, current),
+            &format!("{0}{1}{0}", quote, new),
+        )
+    })
+}
+
+/// Finds the crates.io versions pinned in the config at `path` that were yanked or were never
+/// published. Returns the key of each such crate with a description that suggests a
+/// replacement.
+pub fn yanked(path: &Path) -> Result<Vec<(String, String)>, String> {
+    let config = CratesConfig::load(path)?;
+    let mut yanked = Vec::new();
+    for (key, source) in &config.crates {
+        let pinned = match &source.versions {
+            Some(pinned) if source.registry.is_none() => pinned,
+            _ => continue,
+        };
+        let versions = crates_io::versions(&source.name)?;
+        for version in pinned {
+            let problem = match versions.iter().find(|published| published.num == *version) {
+                Some(published) if published.yanked => "is yanked",
+                Some(_) => continue,
+                None => "isn't published on crates.io",
+            };
+            let suggestion = match crates_io::replacement(&versions, version) {
+                Some(replacement) => format!(", use {} instead", replacement),
+                None => String::new(),
+            };
+            yanked.push((
+                key.clone(),
+                format!("{} {} {}{}", source.name, version, problem, suggestion),
+            ));
+        }
+    }
+    Ok(yanked)
+}
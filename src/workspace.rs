@@ -0,0 +1,182 @@
+//! `config from-workspace`, which generates a config pinning the dependencies of a cargo
+//! workspace, so that linting everything a project depends on is one command.
+
+use crate::{
+    crates::{CrateSource, CratesConfig},
+    crates_io,
+};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    path::Path,
+    process::Command,
+};
+
+/// Sources of crates from crates.io in `cargo metadata`.
+const CRATES_IO: &[&str] = &[
+    "registry+https://github.com/rust-lang/crates.io-index",
+    "sparse+https://index.crates.io/",
+];
+
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_members: Vec<String>,
+    resolve: Resolve,
+}
+
+#[derive(Debug, Deserialize)]
+struct Package {
+    id: String,
+    name: String,
+    version: String,
+    source: Option<String>,
+    manifest_path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Resolve {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Node {
+    id: String,
+    deps: Vec<NodeDep>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NodeDep {
+    pkg: String,
+    dep_kinds: Vec<DepKind>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DepKind {
+    /// `None` for normal dependencies, otherwise `dev` or `build`
+    kind: Option<String>,
+}
+
+/// The config of the dependencies of the workspace at `path`, a directory or its `Cargo.toml`,
+/// up to `depth` levels below the members of the workspace, all of them if `None`. Dependencies
+/// from crates.io are pinned to their resolved versions, git dependencies to their commit and
+/// path dependencies outside of the workspace are linted in place. Dev-dependencies and crates
+/// of other registries are left out.
+pub fn config(path: &Path, depth: Option<usize>) -> Result<CratesConfig, String> {
+    let manifest = if path.is_dir() {
+        path.join("Cargo.toml")
+    } else {
+        path.to_path_buf()
+    };
+    let output = Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--manifest-path"])
+        .arg(&manifest)
+        .output()
+        .map_err(|e| format!("couldn't execute cargo metadata: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "cargo metadata exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        ));
+    }
+    let metadata: Metadata = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("invalid output of cargo metadata: {}", e))?;
+
+    let members: BTreeSet<&str> = metadata
+        .workspace_members
+        .iter()
+        .map(String::as_str)
+        .collect();
+    let nodes: BTreeMap<&str, &Node> = metadata
+        .resolve
+        .nodes
+        .iter()
+        .map(|node| (node.id.as_str(), node))
+        .collect();
+    // Walks the dependency graph breadth first, so that every package is reached at its lowest
+    // depth.
+    let mut reached: BTreeSet<&str> = members.clone();
+    let mut queue: VecDeque<(&str, usize)> = members.iter().map(|&id| (id, 0)).collect();
+    while let Some((id, level)) = queue.pop_front() {
+        if depth.is_some_and(|depth| level >= depth) {
+            continue;
+        }
+        let deps = nodes.get(id).map_or(&[][..], |node| &node.deps[..]);
+        for dep in deps {
+            let dev_only = dep
+                .dep_kinds
+                .iter()
+                .all(|kind| kind.kind.as_deref() == Some("dev"));
+            if !dev_only && reached.insert(&dep.pkg) {
+                queue.push_back((&dep.pkg, level + 1));
+            }
+        }
+    }
+
+    let mut config = CratesConfig::default();
+    for package in &metadata.packages {
+        if !reached.contains(package.id.as_str()) || members.contains(package.id.as_str()) {
+            continue;
+        }
+        let source = match &package.source {
+            Some(source) => source,
+            None => {
+                let dir = Path::new(&package.manifest_path)
+                    .parent()
+                    .unwrap_or_else(|| Path::new("."));
+                config.crates.insert(
+                    package.name.clone(),
+                    crate_source(&package.name, |source| {
+                        source.path = Some(dir.to_string_lossy().into_owned())
+                    }),
+                );
+                continue;
+            }
+        };
+        if CRATES_IO.contains(&source.as_str()) {
+            let entry = config
+                .crates
+                .entry(package.name.clone())
+                .or_insert_with(|| {
+                    crate_source(&package.name, |source| source.versions = Some(Vec::new()))
+                });
+            if let Some(versions) = &mut entry.versions {
+                versions.push(package.version.clone());
+                versions.sort_by_key(|version| crates_io::release(version));
+            }
+        } else if let Some(git) = source.strip_prefix("git+") {
+            let (url, hash) = match git.rsplit_once('#') {
+                Some((url, hash)) => (url.split('?').next().unwrap_or(url), hash),
+                None => return Err(format!("git source {} isn't locked to a commit", source)),
+            };
+            config.crates.insert(
+                format!("{}-{}", package.name, &hash[..hash.len().min(7)]),
+                crate_source(&package.name, |source| {
+                    source.git_url = Some(url.to_string());
+                    source.git_hash = Some(hash.to_string());
+                }),
+            );
+        } else {
+            eprintln!(
+                "{} {} is from {}, which lintcheck can't download, left out",
+                package.name, package.version, source
+            );
+        }
+    }
+    Ok(config)
+}
+
+fn crate_source(name: &str, set: impl FnOnce(&mut CrateSource)) -> CrateSource {
+    let mut source = CrateSource {
+        name: name.to_string(),
+        versions: None,
+        git_url: None,
+        git_hash: None,
+        path: None,
+        registry: None,
+        features: None,
+    };
+    set(&mut source);
+    source
+}
@@ -0,0 +1,378 @@
+//! The optional `clippy-lintcheck.toml` configuring the wrapper itself, as opposed to the crate
+//! configs in `config/` that are handed to lintcheck.
+//!
+//! ```toml
+//! # checkout used when `--clippy` isn't passed
+//! default-clippy = "master"
+//! # optional: machines `--distributed` runs on, reachable over SSH
+//! workers = ["me@build-1", "me@build-2"]
+//!
+//! [clippy.master]
+//! path = "rust-clippy"
+//!
+//! [clippy.my-fork]
+//! path = "../my-clippy"
+//! # optional: run lintcheck with `cargo +<toolchain>`
+//! toolchain = "nightly-2021-02-11"
+//! # optional: passed to lintcheck as `CARGO_TARGET_DIR`
+//! target-dir = "target/my-fork"
+//! # optional: the cargo alias lintcheck is run with, "dev-lintcheck" or "lintcheck". Detected
+//! # from the aliases of the checkout by default
+//! lintcheck = "lintcheck"
+//! # optional: where lintcheck writes the log of a config, searched in this order. `{checkout}`,
+//! # `{target}` and `{config}` stand for the checkout, its target dir and the file stem of the
+//! # config. Defaults to the places the cargo alias of the checkout wrote it to over time
+//! log-paths = ["{target}/lintcheck-logs/{config}_logs.txt", "{target}/lintcheck/{config}.md"]
+//!
+//! # maximum number of new warnings compared to `--baseline`, per default level of the lint
+//! [new-warning-budget]
+//! deny = 0
+//! warn = 20
+//!
+//! # maximum increase of the warnings of any single lint compared to `--baseline`
+//! [lint-increase-limit]
+//! percent = 20
+//! absolute = 50
+//!
+//! # alternative registry crates of the configs can use with `registry = "internal"`
+//! [registries.internal]
+//! index = "https://git.example.com/crates-index"
+//! # where the `.crate` files are downloaded from, like the `dl` of the index's config.json
+//! download = "https://crates.example.com/api/v1/crates/{crate}/{version}/download"
+//! # optional: environment variable holding the token of the registry
+//! token-env = "INTERNAL_REGISTRY_TOKEN"
+//!
+//! # warnings dropped from the logs before they are checked, see `suppress`
+//! [[suppress]]
+//! crate = "^windows"
+//! lint = "clippy::missing_safety_doc"
+//!
+//! # where `clippy-lintcheck publish` pushes the reports to
+//! [pages]
+//! # optional: git repository, defaults to the `origin` of the current repository
+//! repository = "git@github.com:me/lintcheck-results.git"
+//! # optional: defaults to "gh-pages"
+//! branch = "gh-pages"
+//!
+//! # expectations on the warnings of single crates, checked after every run
+//! [crate-expectations.serde]
+//! max-warnings = 12
+//!
+//! [crate-expectations.regex]
+//! # the crate must trigger exactly these lints
+//! lints = ["clippy::needless_lifetimes", "clippy::single_match_else"]
+//!
+//! # commands run around every run with `sh -c`, see `hooks`
+//! [hooks]
+//! pre-run = ["ping -c 1 vpn.example.com"]
+//! post-run = ["rsync -a logs/ results.example.com:lintcheck/"]
+//! on-failure = ["./page.sh \"$CLIPPY_LINTCHECK_SUMMARY\""]
+//! ```
+
+use crate::{hooks::Hooks, lintcheck::EntryPoint, lints::Level, suppress::Rule};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+pub const DEFAULT_PATH: &str = "clippy-lintcheck.toml";
+/// Checkout used if no wrapper config registers any.
+const DEFAULT_CHECKOUT: &str = "rust-clippy";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WrapperConfig {
+    default_clippy: Option<String>,
+    #[serde(default)]
+    clippy: BTreeMap<String, Checkout>,
+    #[serde(default)]
+    pub new_warning_budget: LevelBudgets,
+    #[serde(default)]
+    pub lint_increase_limit: IncreaseLimit,
+    #[serde(default)]
+    pub registries: Registries,
+    #[serde(default)]
+    pub crate_expectations: CrateExpectations,
+    #[serde(default)]
+    pub pages: Pages,
+    #[serde(default)]
+    pub suppress: Vec<Rule>,
+    #[serde(default)]
+    pub workers: Vec<String>,
+    #[serde(default)]
+    pub hooks: Hooks,
+}
+
+/// The branch reports are published to.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pages {
+    pub repository: Option<String>,
+    #[serde(default = "Pages::default_branch")]
+    pub branch: String,
+}
+
+impl Pages {
+    fn default_branch() -> String {
+        "gh-pages".to_string()
+    }
+}
+
+impl Default for Pages {
+    fn default() -> Self {
+        Self {
+            repository: None,
+            branch: Self::default_branch(),
+        }
+    }
+}
+
+/// Expectations keyed by crate name.
+pub type CrateExpectations = BTreeMap<String, CrateExpectation>;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CrateExpectation {
+    pub max_warnings: Option<usize>,
+    /// The set of lints the crate triggers, with or without the `clippy::` prefix
+    pub lints: Option<BTreeSet<String>>,
+}
+
+/// Alternative registries, keyed by the name the configs refer to them with.
+pub type Registries = BTreeMap<String, Registry>;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Registry {
+    pub index: String,
+    /// URL of the `.crate` files with `{crate}` and `{version}` placeholders
+    pub download: String,
+    pub token_env: Option<String>,
+}
+
+impl Registry {
+    /// The token from the `token-env` variable, if the registry has one.
+    pub fn token(&self) -> Result<Option<String>, String> {
+        self.token_env
+            .as_ref()
+            .map(|var| {
+                std::env::var(var)
+                    .map_err(|e| format!("couldn't read the registry token from {}: {}", var, e))
+            })
+            .transpose()
+    }
+
+    /// Environment variables that make cargo, and with that lintcheck, aware of the registry
+    /// `name`, so that crates depending on other crates of the registry can be built.
+    pub fn cargo_env(&self, name: &str) -> Result<Vec<(String, String)>, String> {
+        let prefix = format!("CARGO_REGISTRIES_{}", name.to_uppercase().replace('-', "_"));
+        let mut env = vec![(format!("{}_INDEX", prefix), self.index.clone())];
+        if let Some(token) = self.token()? {
+            env.push((format!("{}_TOKEN", prefix), token));
+        }
+        Ok(env)
+    }
+}
+
+/// Maximum number of warnings per default lint level. Levels without a budget are unlimited.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelBudgets {
+    deny: Option<usize>,
+    warn: Option<usize>,
+    allow: Option<usize>,
+    unknown: Option<usize>,
+}
+
+impl LevelBudgets {
+    pub fn get(&self, level: Level) -> Option<usize> {
+        match level {
+            Level::Deny => self.deny,
+            Level::Warn => self.warn,
+            Level::Allow => self.allow,
+            Level::Unknown => self.unknown,
+        }
+    }
+}
+
+/// Maximum increase of the number of warnings of a lint, relative and absolute. Exceeding either
+/// limit is a regression. The relative limit only applies to lints that had warnings before,
+/// new lints are covered by the absolute limit and the `LevelBudgets`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IncreaseLimit {
+    percent: Option<f64>,
+    absolute: Option<usize>,
+}
+
+impl IncreaseLimit {
+    /// Describes how the change from `before` to `after` exceeds the limit, if it does.
+    pub fn check(&self, before: usize, after: usize) -> Option<String> {
+        let increase = after.checked_sub(before).filter(|&increase| increase > 0)?;
+        match (self.absolute, self.percent) {
+            (Some(absolute), _) if increase > absolute => Some(format!(
+                "increased by {}, but at most {} are allowed",
+                increase, absolute
+            )),
+            (_, Some(percent))
+                if before > 0 && increase as f64 * 100.0 > percent * before as f64 =>
+            {
+                Some(format!(
+                    "increased by {:.0}%, but at most {}% are allowed",
+                    increase as f64 * 100.0 / before as f64,
+                    percent
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A clippy checkout to run lintcheck in.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Checkout {
+    pub path: PathBuf,
+    pub toolchain: Option<String>,
+    pub target_dir: Option<PathBuf>,
+    pub lintcheck: Option<EntryPoint>,
+    #[serde(default)]
+    pub log_paths: Vec<String>,
+}
+
+impl Checkout {
+    /// Target dir cargo uses in the checkout.
+    pub fn target_dir(&self) -> PathBuf {
+        self.target_dir
+            .clone()
+            .unwrap_or_else(|| self.path.join("target"))
+    }
+
+    /// The checkout at `path` with the toolchain and cargo alias of this one. It uses the target
+    /// dir inside of it, because the target dir of this one belongs to another copy.
+    pub fn at(self, path: &Path) -> Result<Self, String> {
+        Ok(Self {
+            path: path.canonicalize().map_err(|e| {
+                format!(
+                    "couldn't find the clippy checkout at {}: {}",
+                    path.display(),
+                    e
+                )
+            })?,
+            target_dir: None,
+            ..self
+        })
+    }
+
+    /// The cargo alias lintcheck is run with in the checkout.
+    pub fn entry_point(&self) -> EntryPoint {
+        self.lintcheck
+            .unwrap_or_else(|| EntryPoint::detect(&self.path))
+    }
+
+    /// The paths lintcheck run with `entry_point` may write the log of the config with the file
+    /// stem `stem` to, in the order they are searched: `log-paths` if set, the defaults of the
+    /// entry point otherwise. Relative paths are relative to the checkout.
+    pub fn log_paths(&self, entry_point: EntryPoint, stem: &str) -> Vec<PathBuf> {
+        let patterns: Vec<&str> = if self.log_paths.is_empty() {
+            entry_point.log_paths().to_vec()
+        } else {
+            self.log_paths.iter().map(String::as_str).collect()
+        };
+        patterns
+            .into_iter()
+            .map(|pattern| {
+                let path = pattern
+                    .replace("{checkout}", &self.path.to_string_lossy())
+                    .replace("{target}", &self.target_dir().to_string_lossy())
+                    .replace("{config}", stem);
+                self.path.join(path)
+            })
+            .collect()
+    }
+}
+
+impl WrapperConfig {
+    /// Loads the wrapper config from `path`. A missing file at the default location is treated
+    /// as an empty config.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let text = match fs::read_to_string(path.unwrap_or_else(|| Path::new(DEFAULT_PATH))) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound && path.is_none() => {
+                return Ok(Self::default())
+            }
+            Err(e) => return Err(format!("couldn't read wrapper config: {}", e)),
+        };
+        toml::from_str(&text).map_err(|e| format!("invalid wrapper config: {}", e))
+    }
+
+    /// Returns the checkout registered as `name`, or the default checkout if `name` is `None`.
+    /// Without any registered checkout, that is `rust-clippy` or, if that doesn't exist, the
+    /// cargo workspace of the current directory if it is a clippy checkout, so that
+    /// `cargo lintcheck-runner` can be run in a clippy checkout.
+    ///
+    /// Relative paths are resolved against the current directory.
+    pub fn checkout(&self, name: Option<&str>) -> Result<Checkout, String> {
+        let checkout = match name.or(self.default_clippy.as_deref()) {
+            Some(name) => self.clippy.get(name).cloned().ok_or_else(|| {
+                format!(
+                    "no clippy checkout named `{}`, registered checkouts: {}",
+                    name,
+                    self.clippy.keys().cloned().collect::<Vec<_>>().join(", ")
+                )
+            })?,
+            None if self.clippy.len() == 1 => self.clippy.values().next().unwrap().clone(),
+            None => Checkout {
+                path: default_checkout(),
+                toolchain: None,
+                target_dir: None,
+                lintcheck: None,
+                log_paths: Vec::new(),
+            },
+        };
+        Ok(Checkout {
+            path: checkout.path.canonicalize().map_err(|e| {
+                format!(
+                    "couldn't find the clippy checkout at {}: {}",
+                    checkout.path.display(),
+                    e
+                )
+            })?,
+            target_dir: checkout
+                .target_dir
+                .map(|dir| absolute(&dir))
+                .transpose()
+                .map_err(|e| format!("couldn't resolve the target dir: {}", e))?,
+            ..checkout
+        })
+    }
+}
+
+fn default_checkout() -> PathBuf {
+    let default = PathBuf::from(DEFAULT_CHECKOUT);
+    if default.exists() {
+        return default;
+    }
+    Command::new("cargo")
+        .args(["locate-project", "--workspace", "--message-format", "plain"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            let manifest = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+            Some(manifest.parent()?.to_path_buf())
+        })
+        .filter(|workspace| workspace.join("clippy_lints").is_dir())
+        .unwrap_or(default)
+}
+
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(std::env::current_dir()?.join(path))
+    }
+}
@@ -0,0 +1,3 @@
+pub fn count(v: &[u8]) -> usize {
+    v.iter().count()
+}
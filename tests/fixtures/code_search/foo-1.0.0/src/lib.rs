@@ -0,0 +1,4 @@
+pub fn count(v: &[u8]) -> usize {
+    let n = v.iter().count();
+    n + v.iter().count()
+}